@@ -0,0 +1,103 @@
+#![allow(missing_docs)]
+
+//! Counts the allocations made by the API key extractor when a request is rejected, against
+//! copying the header into an owned `String` before validating it, as the extractor used to.
+
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use std::{alloc::System, hint::black_box, time::Instant};
+
+use axum::extract::FromRequestParts;
+use futures_util::FutureExt;
+use http::Request;
+use ts_api_helper::{ApiKey, ApiKeyValidationConfig, HasApiKeyValidationConfig};
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+struct State(ApiKeyValidationConfig);
+impl HasApiKeyValidationConfig for State {
+    fn api_key_config(&self) -> &ApiKeyValidationConfig {
+        &self.0
+    }
+}
+
+const ITERATIONS: u32 = 100_000;
+
+/// The most allocations a rejected request may make.
+const MAX_ALLOCATIONS_PER_REJECTION: f64 = 0.0;
+
+/// Run `f` for each iteration, returning the allocations per iteration and printing them.
+fn measure(name: &str, mut f: impl FnMut()) -> f64 {
+    let start = Instant::now();
+    let allocations_before = ALLOCATIONS.load(Ordering::Relaxed);
+
+    for _ in 0..ITERATIONS {
+        f();
+    }
+
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations_before;
+    let allocations = u32::try_from(allocations).unwrap_or(u32::MAX);
+    let allocations = f64::from(allocations) / f64::from(ITERATIONS);
+    let elapsed = start.elapsed();
+
+    println!(
+        "{name}: {allocations:.2} allocations/request, {:?}/request",
+        elapsed / ITERATIONS,
+    );
+
+    allocations
+}
+
+fn main() {
+    let state = State(ApiKeyValidationConfig {
+        allowed_api_keys: vec!["allowed-key".to_string()],
+        ..Default::default()
+    });
+
+    let (mut parts, _) = Request::builder()
+        .header("X-TS-API-Key", "rejected-key")
+        .body(())
+        .expect("request should build")
+        .into_parts();
+
+    let owned = measure("rejected API key, owned header", || {
+        let header = parts
+            .headers
+            .get(&state.0.header)
+            .and_then(|header| header.to_str().ok())
+            .map(str::to_owned);
+        let is_allowed = header.is_some_and(|header| state.is_allowed_api_key(&header));
+        assert!(!black_box(is_allowed));
+    });
+
+    let borrowed = measure("rejected API key, extractor", || {
+        let result = ApiKey::from_request_parts(&mut parts, &state)
+            .now_or_never()
+            .expect("the extractor should not wait");
+        assert!(black_box(result).is_err());
+    });
+
+    assert!(
+        borrowed <= MAX_ALLOCATIONS_PER_REJECTION && borrowed < owned,
+        "rejecting an API key allocates {borrowed:.2} times per request, expected at most \
+         {MAX_ALLOCATIONS_PER_REJECTION:.2} and fewer than the owned header's {owned:.2}"
+    );
+}
@@ -0,0 +1,80 @@
+#![allow(missing_docs)]
+
+//! Compares a verified token cache hit against verifying the token's signature.
+
+use std::time::Instant;
+
+use base64ct::{Base64UrlUnpadded, Encoding};
+use jiff::SignedDuration;
+use openssl::{
+    bn::{BigNum, BigNumContext},
+    ec::{EcGroup, EcKey},
+    nid::Nid,
+};
+use ts_api_helper::token::{
+    Algorithm, JsonWebKey, SigningJsonWebKey, VerifiedTokenCache, VerifyingJsonWebKey,
+    json_web_key::{Curve, JsonWebKeyParameters},
+    json_web_token::TokenType,
+};
+
+const ITERATIONS: u32 = 10_000;
+
+fn main() {
+    let ec_key =
+        EcKey::generate(&EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap()).unwrap();
+
+    let mut ctx = BigNumContext::new().unwrap();
+    let mut x = BigNum::new().unwrap();
+    let mut y = BigNum::new().unwrap();
+    ec_key
+        .public_key()
+        .affine_coordinates(ec_key.group(), &mut x, &mut y, &mut ctx)
+        .unwrap();
+
+    let jwk = JsonWebKey {
+        kid: "1".to_string(),
+        alg: Algorithm::ES256,
+        usage: "sig".to_string(),
+        parameters: JsonWebKeyParameters::EC {
+            crv: Curve::P256,
+            x: Base64UrlUnpadded::encode_string(&x.to_vec_padded(32).unwrap()),
+            y: Base64UrlUnpadded::encode_string(&y.to_vec_padded(32).unwrap()),
+        },
+        x5t: None,
+        x5t_s256: None,
+    };
+
+    let signing_key =
+        SigningJsonWebKey::try_from_pem(jwk.clone(), &ec_key.private_key_to_pem().unwrap())
+            .unwrap();
+    let verifying_key = VerifyingJsonWebKey::try_from(jwk).unwrap();
+
+    let token = signing_key
+        .issue("subject".to_string(), TokenType::Common)
+        .unwrap();
+    let serialized = token.serialize();
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        assert!(verifying_key.verify(&token).unwrap());
+    }
+    println!("verify signature: {:?}/token", start.elapsed() / ITERATIONS);
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let cache = VerifiedTokenCache::new(SignedDuration::from_secs(5));
+
+    runtime.block_on(async {
+        cache.insert(serialized.clone(), token).await;
+
+        let start = Instant::now();
+        for _ in 0..ITERATIONS {
+            assert!(cache.get(&serialized).await.is_some());
+        }
+        println!(
+            "verified token cache hit: {:?}/token",
+            start.elapsed() / ITERATIONS
+        );
+    });
+}
@@ -0,0 +1,87 @@
+use core::{cmp::Reverse, convert::Infallible};
+
+use axum::extract::FromRequestParts;
+use http::{header::ACCEPT_LANGUAGE, request::Parts};
+
+/// Extractor for the caller's preferred languages from the `Accept-Language` header.
+///
+/// The languages are lowercase, ordered from most to least preferred, and each language with a
+/// region is followed by its primary language, e.g., `en-nz, en, fr`. Languages with a quality of
+/// zero and the `*` wildcard are ignored. A missing or invalid header is an empty list.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AcceptLanguage(pub Vec<String>);
+
+impl AcceptLanguage {
+    /// Parse the value of an `Accept-Language` header.
+    pub fn parse(header: &str) -> Self {
+        let mut weighted: Vec<(&str, u16)> = header
+            .split(',')
+            .filter_map(|entry| {
+                let mut parts = entry.split(';');
+                let language = parts.next()?.trim();
+
+                let quality = parts
+                    .find_map(|parameter| parameter.trim().strip_prefix("q="))
+                    .map_or(Some(1000), parse_quality)?;
+
+                if language.is_empty() || language == "*" || quality == 0 {
+                    return None;
+                }
+
+                Some((language, quality))
+            })
+            .collect();
+
+        // Stable, so languages of equal quality keep the caller's order.
+        weighted.sort_by_key(|(_, quality)| Reverse(*quality));
+
+        let mut languages: Vec<String> = Vec::new();
+        for (language, _) in weighted {
+            let language = language.to_ascii_lowercase();
+            let primary = language
+                .split_once('-')
+                .map(|(primary, _)| primary.to_string());
+
+            for language in core::iter::once(language).chain(primary) {
+                if !languages.contains(&language) {
+                    languages.push(language);
+                }
+            }
+        }
+
+        Self(languages)
+    }
+}
+
+/// Parse a quality value into thousandths, e.g., `0.8` is 800.
+pub(crate) fn parse_quality(quality: &str) -> Option<u16> {
+    let (whole, fraction) = quality.split_once('.').unwrap_or((quality, ""));
+    if fraction.len() > 3 || !fraction.chars().all(|digit| digit.is_ascii_digit()) {
+        return None;
+    }
+
+    let fraction = format!("{fraction:0<3}").parse::<u16>().ok()?;
+    match whole {
+        "0" => Some(fraction),
+        "1" if fraction == 0 => Some(1000),
+        _ => None,
+    }
+}
+
+impl<S> FromRequestParts<S> for AcceptLanguage
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let languages = parts
+            .headers
+            .get(ACCEPT_LANGUAGE)
+            .and_then(|header| header.to_str().ok())
+            .map(Self::parse)
+            .unwrap_or_default();
+
+        Ok(languages)
+    }
+}
@@ -1,36 +1,342 @@
-use axum::extract::{FromRequestParts, OptionalFromRequestParts};
-use http::request::Parts;
+use core::{
+    error::Error,
+    fmt,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use axum::{
+    extract::{FromRequestParts, OptionalFromRequestParts},
+    response::{IntoResponse, Response},
+};
+use http::{HeaderMap, Request, request::Parts};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use tokio::{task::JoinHandle, time::MissedTickBehavior};
+use tower_layer::Layer;
+use tower_service::Service;
 
 use crate::ErrorResponse;
 
 /// Extractor to validate the request's API key.
+///
+/// Requests without an API key, or with an untrusted API key, are rejected as unauthenticated, as
+/// the caller could not be identified. If no API keys are trusted, the requests are handled as set
+/// by [`ApiKeyValidationConfig::when_empty`].
+///
+/// Behind [`api_key_layer`], the validated key is also available as an `Extension<ApiKey>`.
+#[derive(Clone)]
 pub struct ApiKey(pub String);
+impl ApiKey {
+    /// Validate the API key in the headers.
+    fn validate<V: HasApiKeyValidationConfig + ?Sized>(
+        headers: &HeaderMap,
+        validator: &V,
+    ) -> Result<Self, ErrorResponse> {
+        let config = validator.api_key_config();
+
+        if config.has_no_api_keys() && config.when_empty == Some(EmptyApiKeysPolicy::AllowAll) {
+            let header = headers
+                .get(&config.header)
+                .and_then(|header| header.to_str().ok())
+                .unwrap_or_default();
+            return Ok(Self(header.to_owned()));
+        }
+
+        let header = headers
+            .get(&config.header)
+            .ok_or_else(ErrorResponse::unauthenticated)?
+            .to_str()
+            .map_err(|_| ErrorResponse::unauthenticated())?;
+
+        if !validator.is_allowed_api_key(header) {
+            if config.has_no_api_keys() && config.when_empty.is_none() {
+                log::warn!("rejected API key: {NoApiKeysError}");
+            }
+            return Err(ErrorResponse::unauthenticated());
+        }
+
+        Ok(Self(header.to_owned()))
+    }
+}
 
 /// Config for the trusted API keys.
-#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
-#[serde(rename_all = "camelCase")]
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct ApiKeyValidationConfig {
     /// List of trusted API keys.
     pub allowed_api_keys: Vec<String>,
 
     /// The header to look for the API keys in.
     pub header: String,
+
+    /// A file of additional trusted API keys, one per line, that is reloaded when it changes, see
+    /// [`ReloadableApiKeys::spawn_reloader`]. Only used by [`ReloadableApiKeys`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_api_keys_file: Option<PathBuf>,
+
+    /// How requests are handled if there are no trusted API keys and no API keys file.
+    ///
+    /// If this is not set, every request is rejected, but [`Self::validate`] fails, as an empty
+    /// list of keys is more likely a misconfiguration than an intent to reject every request. A
+    /// validator that trusts keys from elsewhere by overriding
+    /// [`HasApiKeyValidationConfig::is_allowed_api_key`] should set this to
+    /// [`EmptyApiKeysPolicy::DenyAll`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub when_empty: Option<EmptyApiKeysPolicy>,
+}
+impl ApiKeyValidationConfig {
+    /// Validate that the config trusts some API keys, has an API keys file, or explicitly sets
+    /// [`Self::when_empty`].
+    ///
+    /// Call this when loading the config, as [`ApiKey`] otherwise rejects every request without
+    /// failing.
+    pub fn validate(&self) -> Result<(), NoApiKeysError> {
+        if self.has_no_api_keys() && self.when_empty.is_none() {
+            return Err(NoApiKeysError);
+        }
+
+        Ok(())
+    }
+
+    /// Returns if the config has no trusted API keys and no API keys file.
+    fn has_no_api_keys(&self) -> bool {
+        self.allowed_api_keys.is_empty() && self.allowed_api_keys_file.is_none()
+    }
+}
+impl fmt::Debug for ApiKeyValidationConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ApiKeyValidationConfig")
+            .field(
+                "allowed_api_keys",
+                &format_args!("[***; {}]", self.allowed_api_keys.len()),
+            )
+            .field("header", &self.header)
+            .field("allowed_api_keys_file", &self.allowed_api_keys_file)
+            .field("when_empty", &self.when_empty)
+            .finish()
+    }
 }
 impl Default for ApiKeyValidationConfig {
     fn default() -> Self {
         Self {
             allowed_api_keys: Default::default(),
             header: "X-TS-API-Key".to_string(),
+            allowed_api_keys_file: None,
+            when_empty: None,
         }
     }
 }
 
+/// How requests are handled if there are no trusted API keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub enum EmptyApiKeysPolicy {
+    /// Reject every request as unauthenticated.
+    DenyAll,
+    /// Accept every request, e.g., in local development. The [`ApiKey`] is the request's API key,
+    /// or empty if it has none.
+    AllowAll,
+}
+
+/// The API key config has no trusted API keys, no API keys file, and does not set how requests
+/// are handled without them.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct NoApiKeysError;
+impl fmt::Display for NoApiKeysError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "no API keys are trusted, so every request is rejected; add API keys or set `whenEmpty`"
+        )
+    }
+}
+impl Error for NoApiKeysError {}
+
 /// Mark that some State has an API config.
 pub trait HasApiKeyValidationConfig {
     /// Get the API config.
     fn api_key_config(&self) -> &ApiKeyValidationConfig;
+
+    /// Returns if an API key is trusted.
+    fn is_allowed_api_key(&self, api_key: &str) -> bool {
+        self.api_key_config()
+            .allowed_api_keys
+            .iter()
+            .any(|key| key == api_key)
+    }
+}
+
+impl HasApiKeyValidationConfig for ApiKeyValidationConfig {
+    fn api_key_config(&self) -> &ApiKeyValidationConfig {
+        self
+    }
+}
+
+/// API key validation where the trusted keys are also read from a file, so keys can be rotated
+/// without a restart.
+///
+/// The file is read when this is created, and reloaded by the task from [`Self::spawn_reloader`]
+/// when its modification time or length changes. Blank lines and lines starting with `#` are
+/// ignored. If a reload fails, the previous keys remain trusted.
+#[derive(Debug)]
+pub struct ReloadableApiKeys {
+    config: ApiKeyValidationConfig,
+    file: Option<Arc<RwLock<ApiKeysFile>>>,
+}
+impl ReloadableApiKeys {
+    /// Create the API key validation, reading the config's API keys file if it has one.
+    pub fn new(config: ApiKeyValidationConfig) -> io::Result<Self> {
+        let file = config
+            .allowed_api_keys_file
+            .as_deref()
+            .map(ApiKeysFile::read)
+            .transpose()?
+            .map(|file| Arc::new(RwLock::new(file)));
+
+        Ok(Self { config, file })
+    }
+
+    /// Spawn a task that checks the API keys file for changes every `interval`, e.g., 30 seconds,
+    /// and reloads it when it has changed.
+    ///
+    /// The task stops once this is dropped, or immediately if there is no API keys file. Requests
+    /// are validated against the keys loaded so far, so they never wait on the file system.
+    pub fn spawn_reloader(&self, interval: Duration) -> JoinHandle<()> {
+        let path = self.config.allowed_api_keys_file.clone();
+        let file = self.file.as_ref().map(Arc::downgrade);
+
+        tokio::spawn(async move {
+            let (Some(path), Some(file)) = (path, file) else {
+                return;
+            };
+
+            let mut interval = tokio::time::interval(interval);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+
+                let Some(file) = file.upgrade() else {
+                    return;
+                };
+                reload_if_changed(&path, &file).await;
+            }
+        })
+    }
+}
+
+/// Reload the API keys file if it has changed.
+async fn reload_if_changed(path: &Path, file: &RwLock<ApiKeysFile>) {
+    let version = match FileVersion::of(path).await {
+        Ok(version) => version,
+        Err(error) => {
+            log::error!("could not read metadata of API keys file: {error}");
+            return;
+        }
+    };
+
+    let is_current = file.read().is_ok_and(|current| current.version == version);
+    if is_current {
+        return;
+    }
+
+    let reloaded = match tokio::fs::read_to_string(path).await {
+        Ok(contents) => ApiKeysFile::parse(version, &contents),
+        Err(error) => {
+            log::error!("could not reload API keys file: {error}");
+            return;
+        }
+    };
+
+    if let Ok(mut current) = file.write() {
+        *current = reloaded;
+        log::info!("reloaded API keys file");
+    }
+}
+impl HasApiKeyValidationConfig for ReloadableApiKeys {
+    fn api_key_config(&self) -> &ApiKeyValidationConfig {
+        &self.config
+    }
+
+    fn is_allowed_api_key(&self, api_key: &str) -> bool {
+        if self
+            .config
+            .allowed_api_keys
+            .iter()
+            .any(|key| key == api_key)
+        {
+            return true;
+        }
+
+        let Some(file) = &self.file else {
+            return false;
+        };
+
+        file.read()
+            .is_ok_and(|file| file.keys.iter().any(|key| key == api_key))
+    }
+}
+
+/// The keys read from an API keys file.
+struct ApiKeysFile {
+    version: FileVersion,
+    keys: Vec<String>,
+}
+impl fmt::Debug for ApiKeysFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ApiKeysFile")
+            .field("version", &self.version)
+            .field("keys", &format_args!("[***; {}]", self.keys.len()))
+            .finish()
+    }
+}
+impl ApiKeysFile {
+    fn read(path: &Path) -> io::Result<Self> {
+        let metadata = fs::metadata(path)?;
+        let version = FileVersion {
+            modified: metadata.modified()?,
+            len: metadata.len(),
+        };
+        let contents = fs::read_to_string(path)?;
+
+        Ok(Self::parse(version, &contents))
+    }
+
+    fn parse(version: FileVersion, contents: &str) -> Self {
+        let keys = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string)
+            .collect();
+
+        Self { version, keys }
+    }
+}
+
+/// Identifies a version of a file by its modification time and length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileVersion {
+    modified: SystemTime,
+    len: u64,
+}
+impl FileVersion {
+    async fn of(path: &Path) -> io::Result<Self> {
+        let metadata = tokio::fs::metadata(path).await?;
+
+        Ok(Self {
+            modified: metadata.modified()?,
+            len: metadata.len(),
+        })
+    }
 }
 
 impl<S> OptionalFromRequestParts<S> for ApiKey
@@ -61,20 +367,91 @@ where
     type Rejection = ErrorResponse;
 
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
-        let config = state.api_key_config();
+        Self::validate(&parts.headers, state)
+    }
+}
 
-        let header = parts
-            .headers
-            .get(&config.header)
-            .ok_or_else(ErrorResponse::unauthenticated)?
-            .to_str()
-            .map_err(|_| ErrorResponse::unauthenticated())?
-            .to_owned();
+/// Layer that validates the API key of every request before routing, so a whole router can be
+/// protected without adding the [`ApiKey`] extractor to each handler.
+///
+/// Requests without an API key, or with an untrusted API key, are rejected as unauthenticated. The
+/// validated key is inserted into the request extensions.
+///
+/// The validator may be an [`ApiKeyValidationConfig`], a [`ReloadableApiKeys`], or the state. A
+/// warning is logged if its config fails [`ApiKeyValidationConfig::validate`].
+pub fn api_key_layer<V: HasApiKeyValidationConfig>(validator: V) -> ApiKeyLayer<V> {
+    if let Err(error) = validator.api_key_config().validate() {
+        log::warn!("API key layer is misconfigured: {error}");
+    }
+
+    ApiKeyLayer {
+        validator: Arc::new(validator),
+    }
+}
+
+/// Layer that validates the API key of every request.
+#[derive(Debug)]
+#[must_use]
+pub struct ApiKeyLayer<V> {
+    validator: Arc<V>,
+}
+impl<V> Clone for ApiKeyLayer<V> {
+    fn clone(&self) -> Self {
+        Self {
+            validator: Arc::clone(&self.validator),
+        }
+    }
+}
+impl<S, V> Layer<S> for ApiKeyLayer<V> {
+    type Service = ApiKeyService<S, V>;
 
-        if !config.allowed_api_keys.contains(&header) {
-            return Err(ErrorResponse::forbidden());
+    fn layer(&self, inner: S) -> Self::Service {
+        ApiKeyService {
+            inner,
+            validator: Arc::clone(&self.validator),
         }
+    }
+}
 
-        Ok(Self(header))
+/// Middleware that validates the API key of every request.
+#[derive(Debug)]
+#[must_use]
+pub struct ApiKeyService<S, V> {
+    inner: S,
+    validator: Arc<V>,
+}
+impl<S: Clone, V> Clone for ApiKeyService<S, V> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            validator: Arc::clone(&self.validator),
+        }
+    }
+}
+impl<S, V, ReqBody> Service<Request<ReqBody>> for ApiKeyService<S, V>
+where
+    S: Service<Request<ReqBody>, Response = Response>,
+    S::Future: Send + 'static,
+    V: HasApiKeyValidationConfig,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<ReqBody>) -> Self::Future {
+        match ApiKey::validate(request.headers(), self.validator.as_ref()) {
+            Ok(api_key) => {
+                request.extensions_mut().insert(api_key);
+                Box::pin(self.inner.call(request))
+            }
+            Err(error) => {
+                let response = error.into_response();
+                Box::pin(async { Ok(response) })
+            }
+        }
     }
 }
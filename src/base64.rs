@@ -1,6 +1,23 @@
-use base64ct::{Base64UrlUnpadded, Encoding};
+use base64ct::{Base64Url, Base64UrlUnpadded, Encoding};
+
+/// Decode URL base-64 that may or may not be padded.
+///
+/// Some browser and authenticator combinations pad the URL base-64 in WebAuthn payloads, so padded
+/// input is accepted when it is not valid unpadded.
+pub(crate) fn decode_url_base64(value: &str) -> Result<Vec<u8>, base64ct::Error> {
+    Base64UrlUnpadded::decode_vec(value).or_else(|error| {
+        if value.ends_with('=') {
+            Base64Url::decode_vec(value)
+        } else {
+            Err(error)
+        }
+    })
+}
 
 /// Serde helper for serializing bytes to and from base 64.
+///
+/// Bytes are serialized as unpadded URL base-64, and deserialized from padded or unpadded URL
+/// base-64.
 pub mod serde_base64 {
     use base64ct::{Base64UrlUnpadded, Encoding};
     use serde::{Deserialize, Deserializer, Serializer, de};
@@ -20,11 +37,13 @@ pub mod serde_base64 {
     {
         let value: &str = Deserialize::deserialize(deserializer)?;
 
-        Base64UrlUnpadded::decode_vec(value).map_err(de::Error::custom)
+        super::decode_url_base64(value).map_err(de::Error::custom)
     }
 }
 
 /// Serde helper for maybe serializing bytes to and from base 64.
+///
+/// Like [`serde_base64`], padded URL base-64 is accepted when deserializing.
 pub mod maybe_serde_base64 {
     use base64ct::{Base64UrlUnpadded, Encoding};
     use serde::{Deserialize, Deserializer, Serializer, de};
@@ -51,7 +70,7 @@ pub mod maybe_serde_base64 {
 
         match value {
             Some(value) => Ok(Some(
-                Base64UrlUnpadded::decode_vec(value).map_err(de::Error::custom)?,
+                super::decode_url_base64(value).map_err(de::Error::custom)?,
             )),
             None => Ok(None),
         }
@@ -0,0 +1,248 @@
+use axum::extract::FromRequestParts;
+use http::{HeaderName, request::Parts};
+use openssl::x509::{X509, X509Ref};
+use percent_encoding::percent_decode_str;
+
+use crate::ErrorResponse;
+
+/// The `X-Forwarded-Client-Cert` header.
+static X_FORWARDED_CLIENT_CERT: HeaderName = HeaderName::from_static("x-forwarded-client-cert");
+
+/// The format a proxy forwards the client certificate in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ClientCertificateFormat {
+    /// The XFCC format of Envoy's `X-Forwarded-Client-Cert`, `;` separated `key=value` pairs,
+    /// e.g., `Hash=...;Subject="CN=client";URI=spiffe://...;DNS=client.example.com`.
+    ///
+    /// If the pairs include the URL encoded certificate, `Cert`, the identity is read from the
+    /// certificate, otherwise it is read from the `Subject`, `URI`, and `DNS` pairs.
+    #[default]
+    Xfcc,
+    /// A URL encoded PEM certificate, e.g., nginx's `$ssl_client_escaped_cert`.
+    Pem,
+}
+
+/// Trait for if some state configures the header an mTLS-terminating proxy forwards the client
+/// certificate in.
+pub trait HasClientCertificateHeader {
+    /// The header the client certificate is forwarded in, e.g., `X-Forwarded-Client-Cert` or
+    /// `X-Client-Cert`.
+    fn client_certificate_header(&self) -> &HeaderName {
+        &X_FORWARDED_CLIENT_CERT
+    }
+
+    /// The format the client certificate is forwarded in.
+    fn client_certificate_format(&self) -> ClientCertificateFormat {
+        ClientCertificateFormat::default()
+    }
+}
+
+/// Extractor for the identity of the client certificate of a mutual-TLS request, as forwarded by
+/// the proxy that terminated the TLS connection.
+///
+/// Requests without the configured forwarded header, or with a malformed header or certificate,
+/// are rejected as forbidden. If a chain of proxies forwarded a list of certificates, the first,
+/// i.e., the certificate of the client, is used.
+///
+/// The forwarded header can be set by the client, so this must only be used behind a trusted proxy
+/// that verifies the client certificate and overwrites the header on every request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientCertificate {
+    /// The subject of the certificate, e.g., `CN=client,O=Example`.
+    pub subject: Option<String>,
+    /// The DNS names of the certificate's subject alternative names.
+    pub dns_names: Vec<String>,
+    /// The URIs of the certificate's subject alternative names, e.g., a SPIFFE ID.
+    pub uris: Vec<String>,
+}
+
+impl ClientCertificate {
+    /// Read the identity of a certificate.
+    fn from_x509(certificate: &X509Ref) -> Self {
+        // RFC 4514 lists the most specific attribute first, the reverse of the certificate.
+        let mut attributes: Vec<_> = certificate
+            .subject_name()
+            .entries()
+            .filter_map(|entry| {
+                let name = entry.object().nid().short_name().ok()?;
+                let value = entry.data().as_utf8().ok()?;
+                Some(format!("{name}={}", escape_attribute_value(&value)))
+            })
+            .collect();
+        attributes.reverse();
+
+        let mut dns_names = Vec::new();
+        let mut uris = Vec::new();
+        for name in certificate.subject_alt_names().into_iter().flatten() {
+            if let Some(dns_name) = name.dnsname() {
+                dns_names.push(dns_name.to_string());
+            }
+            if let Some(uri) = name.uri() {
+                uris.push(uri.to_string());
+            }
+        }
+
+        Self {
+            subject: (!attributes.is_empty()).then(|| attributes.join(",")),
+            dns_names,
+            uris,
+        }
+    }
+
+    /// Parse a URL encoded PEM certificate.
+    fn parse_pem(value: &str) -> Option<Self> {
+        let pem: Vec<u8> = percent_decode_str(value).collect();
+        let certificate = X509::from_pem(&pem).ok()?;
+
+        Some(Self::from_x509(&certificate))
+    }
+
+    /// Parse the first element of an XFCC header.
+    fn parse_xfcc(value: &str) -> Option<Self> {
+        let element = split_unquoted(value, ',')?.into_iter().next()?;
+
+        let mut identity = Self {
+            subject: None,
+            dns_names: Vec::new(),
+            uris: Vec::new(),
+        };
+        let mut certificate = None;
+
+        for pair in split_unquoted(element, ';')? {
+            if pair.trim().is_empty() {
+                continue;
+            }
+
+            let (key, value) = pair.split_once('=')?;
+            let value = unquote(value.trim());
+            match key.trim().to_ascii_lowercase().as_str() {
+                "cert" => certificate = Some(value),
+                "subject" => identity.subject = Some(value).filter(|value| !value.is_empty()),
+                "uri" => identity.uris.push(value),
+                "dns" => identity.dns_names.push(value),
+                _ => {}
+            }
+        }
+
+        if let Some(certificate) = certificate {
+            return Self::parse_pem(&certificate);
+        }
+
+        let has_identity = identity.subject.is_some()
+            || !identity.uris.is_empty()
+            || !identity.dns_names.is_empty();
+        has_identity.then_some(identity)
+    }
+}
+
+/// Escape an attribute value of an RFC 4514 distinguished name.
+fn escape_attribute_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    let last = value.chars().count().saturating_sub(1);
+
+    for (index, character) in value.chars().enumerate() {
+        let needs_escape = matches!(character, ',' | '+' | '"' | '\\' | '<' | '>' | ';')
+            || (index == 0 && matches!(character, '#' | ' '))
+            || (index == last && character == ' ');
+        if needs_escape {
+            escaped.push('\\');
+        }
+        escaped.push(character);
+    }
+
+    escaped
+}
+
+/// Split a value on a separator that is not inside a quoted string, or `None` if a quoted string
+/// is not closed.
+fn split_unquoted(value: &str, separator: char) -> Option<Vec<&str>> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut is_quoted = false;
+    let mut is_escaped = false;
+
+    for (index, character) in value.char_indices() {
+        if is_escaped {
+            is_escaped = false;
+            continue;
+        }
+
+        match character {
+            '\\' if is_quoted => is_escaped = true,
+            '"' => is_quoted = !is_quoted,
+            _ if character == separator && !is_quoted => {
+                parts.push(&value[start..index]);
+                start = index + character.len_utf8();
+            }
+            _ => {}
+        }
+    }
+
+    if is_quoted {
+        return None;
+    }
+
+    parts.push(&value[start..]);
+    Some(parts)
+}
+
+/// Remove the quotes and the `\"` and `\\` escapes from a quoted string, or return the value as is
+/// if it is not quoted.
+///
+/// Other escapes are kept, as they belong to the value, e.g., the `\,` of an RFC 4514 subject.
+fn unquote(value: &str) -> String {
+    let Some(quoted) = value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+    else {
+        return value.to_string();
+    };
+
+    let mut unquoted = String::with_capacity(quoted.len());
+    let mut characters = quoted.chars();
+    while let Some(character) = characters.next() {
+        match character {
+            '\\' => match characters.next() {
+                Some(escaped @ ('"' | '\\')) => unquoted.push(escaped),
+                Some(escaped) => {
+                    unquoted.push('\\');
+                    unquoted.push(escaped);
+                }
+                None => unquoted.push('\\'),
+            },
+            character => unquoted.push(character),
+        }
+    }
+    unquoted
+}
+
+impl<S> FromRequestParts<S> for ClientCertificate
+where
+    S: Send + Sync + HasClientCertificateHeader,
+{
+    type Rejection = ErrorResponse;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let header = state.client_certificate_header();
+
+        let Some(value) = parts
+            .headers
+            .get(header)
+            .and_then(|value| value.to_str().ok())
+        else {
+            log::warn!("request has no valid `{header}` header");
+            return Err(ErrorResponse::forbidden());
+        };
+
+        let certificate = match state.client_certificate_format() {
+            ClientCertificateFormat::Xfcc => Self::parse_xfcc(value),
+            ClientCertificateFormat::Pem => Self::parse_pem(value),
+        };
+
+        certificate.ok_or_else(|| {
+            log::warn!("request has a malformed client certificate in `{header}`");
+            ErrorResponse::forbidden()
+        })
+    }
+}
@@ -1,12 +1,24 @@
-use core::net::{Ipv4Addr, Ipv6Addr};
+use core::{
+    mem,
+    net::{Ipv4Addr, Ipv6Addr},
+    task::{Context, Poll},
+};
 
 use http::{
-    HeaderName, Method, Uri,
-    header::{ACCEPT, AUTHORIZATION, CONTENT_ENCODING, CONTENT_TYPE},
+    HeaderName, HeaderValue, Method, Request, Response, Uri,
+    header::{
+        ACCEPT, ACCESS_CONTROL_REQUEST_HEADERS, ACCESS_CONTROL_REQUEST_METHOD, AUTHORIZATION,
+        CONTENT_ENCODING, CONTENT_TYPE, ORIGIN,
+    },
 };
-use tower_http::cors::{AllowOrigin, CorsLayer};
+use tower_http::cors::{AllowOrigin, Cors, CorsLayer, ResponseFuture};
+use tower_layer::Layer;
+use tower_service::Service;
 
 /// Cors layer where the common HTTP methods, headers, and localhost are all allowed by default.
+///
+/// Responses include `Vary: origin, access-control-request-method, access-control-request-headers`
+/// so caches do not share responses between origins.
 pub fn cors_layer(
     additional_allowed_origins: Vec<Uri>,
     additional_allowed_headers: &[HeaderName],
@@ -34,30 +46,149 @@ pub fn cors_layer(
         let Ok(origin) = Uri::try_from(origin) else {
             return false;
         };
-        let Some(host) = origin.host() else {
-            return false;
-        };
-
-        // Allow localhost regardless of port or scheme.
-        if host == "localhost"
-            || host.parse::<Ipv4Addr>() == Ok(Ipv4Addr::LOCALHOST)
-            || host.parse::<Ipv6Addr>() == Ok(Ipv6Addr::LOCALHOST)
-        {
-            return true;
-        }
 
-        // Allow origin if it matches the scheme, host, and port of an allowed origin.
-        additional_allowed_origins.iter().any(|allowed_origin| {
-            allowed_origin.scheme().eq(&origin.scheme())
-                && allowed_origin.host().eq(&origin.host())
-                && allowed_origin.port().eq(&origin.port())
-        })
+        is_allowed_origin(&origin, &additional_allowed_origins)
     });
 
     CorsLayer::new()
+        .vary([
+            ORIGIN,
+            ACCESS_CONTROL_REQUEST_METHOD,
+            ACCESS_CONTROL_REQUEST_HEADERS,
+        ])
         .allow_origin(allowed_origins)
         .allow_credentials(true)
         .allow_headers(allowed_headers)
         .allow_methods(allowed_methods)
         .expose_headers(exposed_headers)
 }
+
+/// Returns if an origin is localhost or matches one of the additional allowed origins.
+pub(crate) fn is_allowed_origin(origin: &Uri, additional_allowed_origins: &[Uri]) -> bool {
+    let Some(host) = origin.host() else {
+        return false;
+    };
+
+    // Allow localhost regardless of port or scheme.
+    let ip = host.trim_start_matches('[').trim_end_matches(']');
+    if host == "localhost"
+        || ip.parse::<Ipv4Addr>() == Ok(Ipv4Addr::LOCALHOST)
+        || ip.parse::<Ipv6Addr>() == Ok(Ipv6Addr::LOCALHOST)
+    {
+        return true;
+    }
+
+    // Allow origin if it matches the scheme, host, and port of an allowed origin.
+    additional_allowed_origins
+        .iter()
+        .any(|allowed_origin| origin_matches(allowed_origin, origin))
+}
+
+/// Returns if an origin matches the scheme, host, and port of an allowed origin.
+fn origin_matches(allowed_origin: &Uri, origin: &Uri) -> bool {
+    allowed_origin.scheme().eq(&origin.scheme())
+        && allowed_origin.host().eq(&origin.host())
+        && allowed_origin.port().eq(&origin.port())
+}
+
+/// Cors layer that applies a different policy depending on the origin of the request.
+///
+/// Each request is handled by the first override whose origins match the scheme, host, and port of
+/// the request's `Origin` header, or by the default policy if none match or the header is absent.
+/// The selected policy is applied as is, so it must also allow the origins it is selected for.
+///
+/// A preflight request carries the same `Origin` header as the request it precedes, so the
+/// `Access-Control-Allow-Headers`, `Access-Control-Allow-Methods`, and
+/// `Access-Control-Allow-Origin` headers of the preflight response come from the same policy that
+/// later handles the request. Each policy adds `Vary: origin` to its responses, so caches do not
+/// share responses between origins.
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct PerOriginCorsLayer {
+    /// The policy for origins without an override.
+    pub default: CorsLayer,
+    /// The policies for specific origins.
+    pub overrides: Vec<(Vec<Uri>, CorsLayer)>,
+}
+impl PerOriginCorsLayer {
+    /// Create a new layer that applies `default` to all origins.
+    pub fn new(default: CorsLayer) -> Self {
+        Self {
+            default,
+            overrides: Vec::new(),
+        }
+    }
+
+    /// Apply `policy` to requests from any of `origins` instead of the default.
+    pub fn with_override(mut self, origins: Vec<Uri>, policy: CorsLayer) -> Self {
+        self.overrides.push((origins, policy));
+        self
+    }
+
+    /// Get the policy for a request's `Origin` header.
+    fn policy(&self, origin: Option<&HeaderValue>) -> &CorsLayer {
+        let Some(origin) = origin
+            .and_then(|origin| origin.to_str().ok())
+            .and_then(|origin| Uri::try_from(origin).ok())
+        else {
+            return &self.default;
+        };
+
+        self.overrides
+            .iter()
+            .find(|(origins, _)| {
+                origins
+                    .iter()
+                    .any(|allowed_origin| origin_matches(allowed_origin, &origin))
+            })
+            .map_or(&self.default, |(_, policy)| policy)
+    }
+}
+impl<S> Layer<S> for PerOriginCorsLayer {
+    type Service = PerOriginCors<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        // Surface invalid policies when the layer is applied rather than on the first request.
+        let _ = self.default.layer(());
+        for (_, policy) in &self.overrides {
+            let _ = policy.layer(());
+        }
+
+        PerOriginCors {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// Middleware that applies a different cors policy depending on the origin of the request.
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct PerOriginCors<S> {
+    inner: S,
+    layer: PerOriginCorsLayer,
+}
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for PerOriginCors<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>> + Clone,
+    ResBody: Default,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        // The service that was polled ready must be the one that is called.
+        let clone = self.inner.clone();
+        let inner = mem::replace(&mut self.inner, clone);
+
+        let policy = self.layer.policy(request.headers().get(ORIGIN));
+        let mut cors: Cors<S> = policy.layer(inner);
+
+        cors.call(request)
+    }
+}
@@ -0,0 +1,59 @@
+use axum::extract::FromRequestParts;
+use http::{
+    Uri,
+    header::{ORIGIN, REFERER},
+    request::Parts,
+};
+
+use crate::{ErrorResponse, cors::is_allowed_origin};
+
+/// Trait for if some state has a list of allowed origins.
+pub trait HasAllowedOrigins {
+    /// Return the origins allowed in addition to localhost, as passed to
+    /// [`cors_layer`](crate::cors_layer).
+    fn allowed_origins(&self) -> &[Uri];
+}
+
+/// Extractor that rejects requests that do not come from an allowed origin as forbidden.
+///
+/// The origin is read from the `Origin` header, falling back to the origin of the `Referer` header.
+/// Requests with neither header, or with an opaque `null` origin, are rejected. This is the same
+/// allowlist as [`cors_layer`](crate::cors_layer), enforced on the server as a defense against
+/// cross-site request forgery for state-changing requests.
+pub struct EnforceOrigin(pub Uri);
+
+impl<S> FromRequestParts<S> for EnforceOrigin
+where
+    S: Send + Sync + HasAllowedOrigins,
+{
+    type Rejection = ErrorResponse;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let origin = parts
+            .headers
+            .get(ORIGIN)
+            .or_else(|| parts.headers.get(REFERER))
+            .and_then(|header| header.to_str().ok())
+            .and_then(|header| Uri::try_from(header).ok())
+            .and_then(|uri| {
+                Uri::builder()
+                    .scheme(uri.scheme()?.clone())
+                    .authority(uri.authority()?.clone())
+                    .path_and_query("/")
+                    .build()
+                    .ok()
+            });
+
+        let Some(origin) = origin else {
+            log::warn!("request has no valid origin");
+            return Err(ErrorResponse::forbidden());
+        };
+
+        if !is_allowed_origin(&origin, state.allowed_origins()) {
+            log::warn!("request origin `{origin}` is not allowed");
+            return Err(ErrorResponse::forbidden());
+        }
+
+        Ok(Self(origin))
+    }
+}
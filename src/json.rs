@@ -1,12 +1,31 @@
+use core::{cell::Cell, fmt};
+use std::collections::HashSet;
+
 use axum::{
+    BoxError,
+    body::{Body, Bytes},
     extract::{FromRequest, OptionalFromRequest, Request},
     response::IntoResponse,
 };
-use serde::{Serialize, de::DeserializeOwned};
+use futures_util::{Stream, StreamExt, stream};
+use http::{HeaderMap, HeaderValue, header::CONTENT_TYPE};
+use serde::{
+    Deserializer, Serialize,
+    de::{
+        self, DeserializeOwned, DeserializeSeed, EnumAccess, MapAccess, SeqAccess, VariantAccess,
+        Visitor,
+    },
+};
+use serde_path_to_error::Segment;
 
-use crate::ErrorResponse;
+use crate::{ErrorResponse, Pointer, Problem};
 
 /// Custom JSON extractor for returning [`crate::ErrorResponse`] errors.
+///
+/// Bodies with a `charset` parameter other than UTF-8 in their content type, e.g.,
+/// `application/json; charset=utf-16`, are rejected as an unsupported media type, as the body is
+/// always parsed as UTF-8. A content type without a `charset` is UTF-8, as JSON must be. This also
+/// applies to [`StrictJson`] and [`PreciseJson`].
 pub struct Json<T>(pub T);
 
 impl<T: Serialize> IntoResponse for Json<T> {
@@ -16,6 +35,54 @@ impl<T: Serialize> IntoResponse for Json<T> {
     }
 }
 
+/// Responder that writes a stream of items as a JSON array as they are produced, so large result
+/// sets are not buffered in memory.
+///
+/// The status code and headers are sent before the first item, so an error from the stream, or an
+/// item that fails to serialize, can only be handled by ending the response early. The client then
+/// sees a truncated array that is not valid JSON, and the error is logged.
+pub struct JsonArray<S>(pub S);
+
+impl<S, T, E> IntoResponse for JsonArray<S>
+where
+    S: Stream<Item = Result<T, E>> + Send + 'static,
+    T: Serialize,
+    E: Into<BoxError>,
+{
+    fn into_response(self) -> axum::response::Response {
+        let Self(items) = self;
+
+        let items = items.enumerate().map(|(index, item)| {
+            let item = item
+                .map_err(Into::into)
+                .and_then(|item| serde_json::to_vec(&item).map_err(BoxError::from));
+
+            match item {
+                Ok(mut json) => {
+                    if index > 0 {
+                        json.insert(0, b',');
+                    }
+                    Ok(Bytes::from(json))
+                }
+                Err(error) => {
+                    log::error!("JSON array stream failed after {index} items: {error}");
+                    Err(error)
+                }
+            }
+        });
+
+        let body = stream::once(async { Ok::<_, BoxError>(Bytes::from_static(b"[")) })
+            .chain(items)
+            .chain(stream::once(async { Ok(Bytes::from_static(b"]")) }));
+
+        (
+            [(CONTENT_TYPE, HeaderValue::from_static("application/json"))],
+            Body::from_stream(body),
+        )
+            .into_response()
+    }
+}
+
 impl<T, S> FromRequest<S> for Json<T>
 where
     T: DeserializeOwned,
@@ -24,6 +91,8 @@ where
     type Rejection = ErrorResponse;
 
     async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        check_charset(req.headers())?;
+
         <axum::Json<_> as FromRequest<S>>::from_request(req, state)
             .await
             .map_err(ErrorResponse::from)
@@ -39,9 +108,628 @@ where
     type Rejection = ErrorResponse;
 
     async fn from_request(req: Request, state: &S) -> Result<Option<Self>, Self::Rejection> {
+        check_charset(req.headers())?;
+
         <axum::Json<_> as OptionalFromRequest<S>>::from_request(req, state)
             .await
             .map_err(ErrorResponse::from)
             .map(|value| value.map(|value| Self(value.0)))
     }
 }
+
+/// Custom JSON extractor for returning [`crate::ErrorResponse`] errors that also rejects bodies
+/// containing duplicate object keys.
+///
+/// `serde_json` silently takes the last value for a duplicate key, so a proxy and this service may
+/// disagree on the value of a field. A body with a duplicate key is rejected as a bad request with
+/// a [`Problem`] pointing at the repeated key.
+///
+/// The body is parsed twice, once to check for duplicate keys and once to deserialize `T`, and
+/// every object key is allocated while checking, so this is noticeably slower than [`Json`] for
+/// large bodies. Prefer it for security-sensitive endpoints.
+pub struct StrictJson<T>(pub T);
+
+impl<T, S> FromRequest<S> for StrictJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ErrorResponse;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let bytes = json_body(req, state).await?;
+
+        if let Some(pointer) = find_duplicate_key(&bytes) {
+            return Err(ErrorResponse::bad_request(vec![Problem::new(
+                pointer,
+                "must not be repeated",
+            )]));
+        }
+
+        axum::Json::from_bytes(&bytes)
+            .map_err(ErrorResponse::from)
+            .map(|value| Self(value.0))
+    }
+}
+
+impl<T, S> OptionalFromRequest<S> for StrictJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ErrorResponse;
+
+    async fn from_request(req: Request, state: &S) -> Result<Option<Self>, Self::Rejection> {
+        if !req.headers().contains_key(CONTENT_TYPE) {
+            return Ok(None);
+        }
+
+        <Self as FromRequest<S>>::from_request(req, state)
+            .await
+            .map(Some)
+    }
+}
+
+/// Custom JSON extractor for returning [`crate::ErrorResponse`] errors that also rejects numbers
+/// that would lose precision when deserialized into a float field.
+///
+/// A float can only represent every integer up to `2^53` for `f64`, or `2^24` for `f32`, so a body
+/// with a larger integer for a float field is rejected as a bad request with a [`Problem`]
+/// pointing at the number. Integer fields are already checked by `serde_json`.
+///
+/// Only integer literals that fit in an `i64` or `u64` are checked. Larger integers, and numbers
+/// with a fraction or exponent, are parsed as floats by `serde_json` before they can be checked.
+/// Numbers inside untagged enums or flattened fields are buffered by `serde` and are not checked.
+pub struct PreciseJson<T>(pub T);
+
+impl<T, S> FromRequest<S> for PreciseJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ErrorResponse;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let bytes = json_body(req, state).await?;
+
+        let lost_precision = Cell::new(false);
+        let mut deserializer = serde_json::Deserializer::from_slice(&bytes);
+
+        let value = serde_path_to_error::deserialize(PreciseNumbers {
+            inner: &mut deserializer,
+            lost_precision: &lost_precision,
+        });
+
+        match value {
+            Ok(value) => {
+                deserializer.end().map_err(|error| {
+                    log::warn!("request contained trailing characters: {error}");
+                    ErrorResponse::unprocessable_entity()
+                })?;
+
+                Ok(Self(value))
+            }
+            Err(error) if lost_precision.get() => {
+                let pointer = error
+                    .path()
+                    .iter()
+                    .fold(Pointer::root(), |pointer, segment| match segment {
+                        Segment::Seq { index } => pointer.index(*index),
+                        Segment::Map { key } => pointer.field(key),
+                        _ => pointer,
+                    });
+
+                Err(ErrorResponse::bad_request(vec![Problem::new(
+                    pointer,
+                    "must be representable without losing precision",
+                )]))
+            }
+            Err(error) => {
+                log::warn!("request contained an unprocessable body: {error}");
+                Err(ErrorResponse::unprocessable_entity())
+            }
+        }
+    }
+}
+
+impl<T, S> OptionalFromRequest<S> for PreciseJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ErrorResponse;
+
+    async fn from_request(req: Request, state: &S) -> Result<Option<Self>, Self::Rejection> {
+        if !req.headers().contains_key(CONTENT_TYPE) {
+            return Ok(None);
+        }
+
+        <Self as FromRequest<S>>::from_request(req, state)
+            .await
+            .map(Some)
+    }
+}
+
+/// Read the body of a request that must have a JSON content type.
+async fn json_body<S: Send + Sync>(req: Request, state: &S) -> Result<Bytes, ErrorResponse> {
+    if !has_json_content_type(req.headers()) {
+        return Err(ErrorResponse::unsupported_media_type());
+    }
+    check_charset(req.headers())?;
+
+    Bytes::from_request(req, state).await.map_err(|rejection| {
+        log::warn!(
+            "request contained an unreadable body ({}): {}",
+            rejection.status(),
+            rejection.body_text()
+        );
+        ErrorResponse::unprocessable_entity()
+    })
+}
+
+/// Returns if the headers have a JSON content type, e.g., `application/json` or
+/// `application/problem+json`.
+fn has_json_content_type(headers: &HeaderMap) -> bool {
+    let Some(content_type) = headers
+        .get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+    else {
+        return false;
+    };
+
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+
+    let Some((media_type, subtype)) = essence.split_once('/') else {
+        return false;
+    };
+
+    media_type == "application" && (subtype == "json" || subtype.ends_with("+json"))
+}
+
+/// Reject a content type with a `charset` parameter other than UTF-8.
+///
+/// The charset is in a header rather than the body, so the response has no problem pointing at it.
+fn check_charset(headers: &HeaderMap) -> Result<(), ErrorResponse> {
+    let Some(content_type) = headers
+        .get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+    else {
+        return Ok(());
+    };
+
+    let charset = content_type.split(';').skip(1).find_map(|parameter| {
+        let (name, value) = parameter.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("charset")
+            .then(|| value.trim().trim_matches('"'))
+    });
+
+    match charset {
+        Some(charset)
+            if !charset.eq_ignore_ascii_case("utf-8") && !charset.eq_ignore_ascii_case("utf8") =>
+        {
+            log::warn!("request body has an unsupported charset: {charset}");
+            Err(ErrorResponse::unsupported_media_type())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Returns a pointer to the first repeated object key in a JSON document, if any.
+///
+/// Invalid documents are reported as having no duplicates so deserialization can report the error.
+fn find_duplicate_key(bytes: &[u8]) -> Option<Pointer> {
+    let mut duplicate = None;
+    let mut deserializer = serde_json::Deserializer::from_slice(bytes);
+
+    let _ = DuplicateKeySeed {
+        pointer: Pointer::root(),
+        duplicate: &mut duplicate,
+    }
+    .deserialize(&mut deserializer);
+
+    duplicate
+}
+
+/// Walks a JSON value, recording the first repeated object key.
+struct DuplicateKeySeed<'a> {
+    pointer: Pointer,
+    duplicate: &'a mut Option<Pointer>,
+}
+
+impl<'de> DeserializeSeed<'de> for DuplicateKeySeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for DuplicateKeySeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "any JSON value")
+    }
+
+    fn visit_bool<E: de::Error>(self, _: bool) -> Result<Self::Value, E> {
+        Ok(())
+    }
+
+    fn visit_i64<E: de::Error>(self, _: i64) -> Result<Self::Value, E> {
+        Ok(())
+    }
+
+    fn visit_u64<E: de::Error>(self, _: u64) -> Result<Self::Value, E> {
+        Ok(())
+    }
+
+    fn visit_f64<E: de::Error>(self, _: f64) -> Result<Self::Value, E> {
+        Ok(())
+    }
+
+    fn visit_str<E: de::Error>(self, _: &str) -> Result<Self::Value, E> {
+        Ok(())
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut index = 0;
+
+        while seq
+            .next_element_seed(DuplicateKeySeed {
+                pointer: self.pointer.clone().index(index),
+                duplicate: &mut *self.duplicate,
+            })?
+            .is_some()
+        {
+            index += 1;
+        }
+
+        Ok(())
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut keys = HashSet::new();
+
+        while let Some(key) = map.next_key::<String>()? {
+            let pointer = self.pointer.clone().field(&key);
+
+            if !keys.insert(key) {
+                *self.duplicate = Some(pointer);
+                return Err(de::Error::custom("duplicate key"));
+            }
+
+            map.next_value_seed(DuplicateKeySeed {
+                pointer,
+                duplicate: &mut *self.duplicate,
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Every integer up to this magnitude can be represented exactly as an `f32`.
+const F32_MAX_EXACT_INTEGER: u64 = 1 << f32::MANTISSA_DIGITS;
+
+/// Every integer up to this magnitude can be represented exactly as an `f64`.
+const F64_MAX_EXACT_INTEGER: u64 = 1 << f64::MANTISSA_DIGITS;
+
+/// Wraps a deserializer, or one of the values it passes to visitors, so every float that is
+/// deserialized is checked for precision loss.
+struct PreciseNumbers<'a, X> {
+    inner: X,
+    lost_precision: &'a Cell<bool>,
+}
+
+impl<'a, X> PreciseNumbers<'a, X> {
+    fn wrap<Y>(&self, inner: Y) -> PreciseNumbers<'a, Y> {
+        PreciseNumbers {
+            inner,
+            lost_precision: self.lost_precision,
+        }
+    }
+
+    fn float<V>(&self, visitor: V, max_exact_integer: u64) -> PreciseFloat<'a, V> {
+        PreciseFloat {
+            inner: visitor,
+            max_exact_integer,
+            lost_precision: self.lost_precision,
+        }
+    }
+}
+
+/// Forward deserializer methods to the inner deserializer, wrapping the visitor.
+macro_rules! forward_deserialize {
+    ($($method:ident($($argument:ident: $type:ty),*);)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(
+                self,
+                $($argument: $type,)*
+                visitor: V,
+            ) -> Result<V::Value, Self::Error> {
+                let visitor = self.wrap(visitor);
+                self.inner.$method($($argument,)* visitor)
+            }
+        )*
+    };
+}
+
+impl<'de, D: Deserializer<'de>> Deserializer<'de> for PreciseNumbers<'_, D> {
+    type Error = D::Error;
+
+    forward_deserialize! {
+        deserialize_any();
+        deserialize_bool();
+        deserialize_i8();
+        deserialize_i16();
+        deserialize_i32();
+        deserialize_i64();
+        deserialize_i128();
+        deserialize_u8();
+        deserialize_u16();
+        deserialize_u32();
+        deserialize_u64();
+        deserialize_u128();
+        deserialize_char();
+        deserialize_str();
+        deserialize_string();
+        deserialize_bytes();
+        deserialize_byte_buf();
+        deserialize_option();
+        deserialize_unit();
+        deserialize_unit_struct(name: &'static str);
+        deserialize_newtype_struct(name: &'static str);
+        deserialize_seq();
+        deserialize_tuple(len: usize);
+        deserialize_tuple_struct(name: &'static str, len: usize);
+        deserialize_map();
+        deserialize_struct(name: &'static str, fields: &'static [&'static str]);
+        deserialize_enum(name: &'static str, variants: &'static [&'static str]);
+        deserialize_identifier();
+        deserialize_ignored_any();
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let visitor = self.float(visitor, F32_MAX_EXACT_INTEGER);
+        self.inner.deserialize_f32(visitor)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let visitor = self.float(visitor, F64_MAX_EXACT_INTEGER);
+        self.inner.deserialize_f64(visitor)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.inner.is_human_readable()
+    }
+}
+
+/// Forward visitor methods to the inner visitor.
+macro_rules! forward_visit {
+    ($($method:ident($type:ty);)*) => {
+        $(
+            fn $method<E: de::Error>(self, value: $type) -> Result<Self::Value, E> {
+                self.inner.$method(value)
+            }
+        )*
+    };
+}
+
+impl<'de, V: Visitor<'de>> Visitor<'de> for PreciseNumbers<'_, V> {
+    type Value = V::Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.expecting(f)
+    }
+
+    forward_visit! {
+        visit_bool(bool);
+        visit_i8(i8);
+        visit_i16(i16);
+        visit_i32(i32);
+        visit_i64(i64);
+        visit_i128(i128);
+        visit_u8(u8);
+        visit_u16(u16);
+        visit_u32(u32);
+        visit_u64(u64);
+        visit_u128(u128);
+        visit_f32(f32);
+        visit_f64(f64);
+        visit_char(char);
+        visit_str(&str);
+        visit_borrowed_str(&'de str);
+        visit_string(String);
+        visit_bytes(&[u8]);
+        visit_borrowed_bytes(&'de [u8]);
+        visit_byte_buf(Vec<u8>);
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+        self.inner.visit_none()
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        self.inner.visit_unit()
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        let deserializer = self.wrap(deserializer);
+        self.inner.visit_some(deserializer)
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        let deserializer = self.wrap(deserializer);
+        self.inner.visit_newtype_struct(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+        let seq = self.wrap(seq);
+        self.inner.visit_seq(seq)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+        let map = self.wrap(map);
+        self.inner.visit_map(map)
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Self::Value, A::Error> {
+        let data = self.wrap(data);
+        self.inner.visit_enum(data)
+    }
+}
+
+impl<'de, S: DeserializeSeed<'de>> DeserializeSeed<'de> for PreciseNumbers<'_, S> {
+    type Value = S::Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        let deserializer = self.wrap(deserializer);
+        self.inner.deserialize(deserializer)
+    }
+}
+
+impl<'de, A: SeqAccess<'de>> SeqAccess<'de> for PreciseNumbers<'_, A> {
+    type Error = A::Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Self::Error> {
+        let seed = self.wrap(seed);
+        self.inner.next_element_seed(seed)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
+
+impl<'de, A: MapAccess<'de>> MapAccess<'de> for PreciseNumbers<'_, A> {
+    type Error = A::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        let seed = self.wrap(seed);
+        self.inner.next_key_seed(seed)
+    }
+
+    fn next_value_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<T::Value, Self::Error> {
+        let seed = self.wrap(seed);
+        self.inner.next_value_seed(seed)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, 'de, A: EnumAccess<'de>> EnumAccess<'de> for PreciseNumbers<'a, A> {
+    type Error = A::Error;
+    type Variant = PreciseNumbers<'a, A::Variant>;
+
+    fn variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<(T::Value, Self::Variant), Self::Error> {
+        let seed = self.wrap(seed);
+        let lost_precision = self.lost_precision;
+        let (value, variant) = self.inner.variant_seed(seed)?;
+
+        Ok((
+            value,
+            PreciseNumbers {
+                inner: variant,
+                lost_precision,
+            },
+        ))
+    }
+}
+
+impl<'de, A: VariantAccess<'de>> VariantAccess<'de> for PreciseNumbers<'_, A> {
+    type Error = A::Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        self.inner.unit_variant()
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, Self::Error> {
+        let seed = self.wrap(seed);
+        self.inner.newtype_variant_seed(seed)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        let visitor = self.wrap(visitor);
+        self.inner.tuple_variant(len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        let visitor = self.wrap(visitor);
+        self.inner.struct_variant(fields, visitor)
+    }
+}
+
+/// Visitor for a float that rejects integers larger than the float can exactly represent.
+struct PreciseFloat<'a, V> {
+    inner: V,
+    max_exact_integer: u64,
+    lost_precision: &'a Cell<bool>,
+}
+
+impl<'de, V: Visitor<'de>> Visitor<'de> for PreciseFloat<'_, V> {
+    type Value = V::Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.expecting(f)
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Self::Value, E> {
+        if value.unsigned_abs() > self.max_exact_integer {
+            self.lost_precision.set(true);
+            return Err(E::custom(format!("{value} cannot be represented exactly")));
+        }
+
+        self.inner.visit_i64(value)
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+        if value > self.max_exact_integer {
+            self.lost_precision.set(true);
+            return Err(E::custom(format!("{value} cannot be represented exactly")));
+        }
+
+        self.inner.visit_u64(value)
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<Self::Value, E> {
+        self.inner.visit_f64(value)
+    }
+}
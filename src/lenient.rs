@@ -0,0 +1,36 @@
+use axum::extract::FromRequestParts;
+use http::{StatusCode, request::Parts};
+
+use crate::ErrorResponse;
+
+/// Extractor that treats a present but invalid credential the same as an absent one.
+///
+/// Where `Option<T>` is only `None` when the credential is absent, `Lenient<T>` is also `None`
+/// when the credential is rejected as unauthenticated or forbidden. Other errors, such as
+/// internal server errors, are still returned.
+///
+/// Handlers using this cannot distinguish between a client that made no attempt to authenticate
+/// and one that presented a malformed, expired, revoked, or untrusted credential, so it must only
+/// be used where both should receive the same anonymous handling.
+pub struct Lenient<T>(pub Option<T>);
+
+impl<S, T> FromRequestParts<S> for Lenient<T>
+where
+    T: FromRequestParts<S, Rejection = ErrorResponse>,
+    S: Send + Sync,
+{
+    type Rejection = ErrorResponse;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        match T::from_request_parts(parts, state).await {
+            Ok(value) => Ok(Self(Some(value))),
+            Err(error)
+                if error.status == StatusCode::UNAUTHORIZED
+                    || error.status == StatusCode::FORBIDDEN =>
+            {
+                Ok(Self(None))
+            }
+            Err(error) => Err(error),
+        }
+    }
+}
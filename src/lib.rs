@@ -1,19 +1,60 @@
 //! Helpers for working with APIs
 
+mod accept_language;
 mod api_key;
 mod base64;
+mod client_certificate;
 mod cors;
+mod enforce_origin;
 mod json;
+mod lenient;
+mod localize;
+mod negotiate;
 mod postgres;
 mod problem;
+mod request_limits;
+mod require_https;
+mod sensitive;
+#[cfg(feature = "reqwest")]
 mod state;
 pub mod token;
+mod trace;
+mod uuid_path;
+mod validated;
+mod vary;
 pub mod webauthn;
 
-pub use api_key::{ApiKey, ApiKeyValidationConfig, HasApiKeyValidationConfig};
+pub use accept_language::AcceptLanguage;
+pub use api_key::{
+    ApiKey, ApiKeyLayer, ApiKeyService, ApiKeyValidationConfig, EmptyApiKeysPolicy,
+    HasApiKeyValidationConfig, NoApiKeysError, ReloadableApiKeys, api_key_layer,
+};
 pub use base64::{DecodeBase64, EncodeBase64, maybe_serde_base64, serde_base64};
-pub use cors::cors_layer;
-pub use json::Json;
-pub use postgres::{ConnectionPool, SetupPostgresError, setup_connection_pool};
-pub use problem::{ErrorResponse, InlineErrorResponse, Problem};
+pub use client_certificate::{
+    ClientCertificate, ClientCertificateFormat, HasClientCertificateHeader,
+};
+pub use cors::{PerOriginCors, PerOriginCorsLayer, cors_layer};
+pub use enforce_origin::{EnforceOrigin, HasAllowedOrigins};
+pub use json::{Json, JsonArray, PreciseJson, StrictJson};
+pub use lenient::Lenient;
+pub use localize::{LocalizeProblemsLayer, LocalizeProblemsService, localize_problems_layer};
+pub use negotiate::{Negotiated, ResponseFormat};
+pub use postgres::{
+    ConnectionPool, PoolConfig, SetupPostgresError, TransactionConflict, TransactionFuture,
+    WithRetryError, retry_on_conflict, setup_connection_pool, setup_connection_pool_with_config,
+    transaction, with_retry,
+};
+pub use problem::{
+    ErrorResponse, InlineErrorResponse, InlineReportedErrorResponse, Pointer, Problem,
+    ProblemCatalog, ProblemCategory, ReportedError,
+};
+pub use request_limits::{
+    RequestLimits, RequestLimitsLayer, RequestLimitsService, request_limits_layer,
+};
+pub use require_https::{HasForwardedProtoHeader, RequireHttps};
+pub use sensitive::Sensitive;
+#[cfg(feature = "reqwest")]
 pub use state::{CreateHttpClientError, HasHttpClient, HttpClientConfig};
+pub use uuid_path::UuidPath;
+pub use validated::Validated;
+pub use vary::append_vary;
@@ -0,0 +1,130 @@
+use core::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+use std::sync::Arc;
+
+use axum::{body::Body, response::Response};
+use http::{
+    Request,
+    header::{ACCEPT_LANGUAGE, CONTENT_LENGTH},
+};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::{
+    AcceptLanguage, ErrorResponse, ProblemCatalog, append_vary, problem::LocalizableProblems,
+};
+
+/// Layer that localizes the details of problems in error responses using a catalog and the
+/// caller's `Accept-Language` header.
+///
+/// Only problems with a code are localized, problems without a template for any of the caller's
+/// languages keep their English details. Localized responses vary on `Accept-Language`.
+pub fn localize_problems_layer<C>(catalog: C) -> LocalizeProblemsLayer<C> {
+    LocalizeProblemsLayer {
+        catalog: Arc::new(catalog),
+    }
+}
+
+/// Layer that localizes the details of problems in error responses.
+#[derive(Debug)]
+#[must_use]
+pub struct LocalizeProblemsLayer<C> {
+    catalog: Arc<C>,
+}
+impl<C> Clone for LocalizeProblemsLayer<C> {
+    fn clone(&self) -> Self {
+        Self {
+            catalog: Arc::clone(&self.catalog),
+        }
+    }
+}
+impl<S, C> Layer<S> for LocalizeProblemsLayer<C> {
+    type Service = LocalizeProblemsService<S, C>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        LocalizeProblemsService {
+            inner,
+            catalog: Arc::clone(&self.catalog),
+        }
+    }
+}
+
+/// Middleware that localizes the details of problems in error responses.
+#[derive(Debug)]
+#[must_use]
+pub struct LocalizeProblemsService<S, C> {
+    inner: S,
+    catalog: Arc<C>,
+}
+impl<S: Clone, C> Clone for LocalizeProblemsService<S, C> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            catalog: Arc::clone(&self.catalog),
+        }
+    }
+}
+impl<S, C, ReqBody> Service<Request<ReqBody>> for LocalizeProblemsService<S, C>
+where
+    S: Service<Request<ReqBody>, Response = Response>,
+    S::Future: Send + 'static,
+    C: ProblemCatalog + Send + Sync + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        let languages = request
+            .headers()
+            .get(ACCEPT_LANGUAGE)
+            .and_then(|header| header.to_str().ok())
+            .map(AcceptLanguage::parse)
+            .unwrap_or_default();
+        let catalog = Arc::clone(&self.catalog);
+
+        let future = self.inner.call(request);
+        Box::pin(async move {
+            let response = future.await?;
+            Ok(localize_response(response, catalog.as_ref(), &languages))
+        })
+    }
+}
+
+/// Replace the body of an error response with its problems localized.
+fn localize_response<C: ProblemCatalog + ?Sized>(
+    mut response: Response,
+    catalog: &C,
+    languages: &AcceptLanguage,
+) -> Response {
+    let Some(LocalizableProblems(problems)) = response.extensions_mut().remove() else {
+        return response;
+    };
+
+    let localized = ErrorResponse {
+        status: response.status(),
+        problems,
+        source: None,
+    }
+    .localize(catalog, &languages.0);
+
+    let body = match serde_json::to_vec(&localized) {
+        Ok(body) => body,
+        Err(error) => {
+            log::error!("failed to serialize localized problems: {error}");
+            return response;
+        }
+    };
+
+    let (mut parts, _) = response.into_parts();
+    parts.headers.remove(CONTENT_LENGTH);
+    append_vary(&mut parts.headers, &[ACCEPT_LANGUAGE]);
+
+    Response::from_parts(parts, Body::from(body))
+}
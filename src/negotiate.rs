@@ -0,0 +1,119 @@
+use core::{cmp::Reverse, convert::Infallible};
+
+use axum::{extract::FromRequestParts, response::IntoResponse};
+use http::{
+    HeaderValue,
+    header::{ACCEPT, CONTENT_TYPE},
+    request::Parts,
+};
+use serde::Serialize;
+
+use crate::{ErrorResponse, accept_language::parse_quality, append_vary};
+
+/// The format a response body is serialized in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ResponseFormat {
+    /// JSON, `application/json`.
+    #[default]
+    Json,
+    /// MessagePack, `application/msgpack`.
+    MessagePack,
+}
+
+impl ResponseFormat {
+    /// Choose the format the caller prefers from the value of an `Accept` header.
+    ///
+    /// MessagePack is only chosen if it is explicitly accepted, as `application/msgpack`,
+    /// `application/vnd.msgpack`, or `application/x-msgpack`, with a higher quality than JSON or
+    /// with the same quality and listed first. Otherwise, the format is JSON.
+    pub fn parse(header: &str) -> Self {
+        let mut weighted: Vec<(Self, u16)> = header
+            .split(',')
+            .filter_map(|entry| {
+                let mut parts = entry.split(';');
+                let media_type = parts.next()?.trim().to_ascii_lowercase();
+
+                let quality = parts
+                    .find_map(|parameter| parameter.trim().strip_prefix("q="))
+                    .map_or(Some(1000), parse_quality)?;
+
+                let format = match media_type.as_str() {
+                    "application/msgpack" | "application/vnd.msgpack" | "application/x-msgpack" => {
+                        Self::MessagePack
+                    }
+                    "application/json" | "application/*" | "*/*" => Self::Json,
+                    _ => return None,
+                };
+
+                (quality != 0).then_some((format, quality))
+            })
+            .collect();
+
+        // Stable, so formats of equal quality keep the caller's order.
+        weighted.sort_by_key(|(_, quality)| Reverse(*quality));
+
+        weighted
+            .first()
+            .map(|(format, _)| *format)
+            .unwrap_or_default()
+    }
+
+    /// The content type of a body in this format.
+    pub fn content_type(self) -> HeaderValue {
+        match self {
+            Self::Json => HeaderValue::from_static("application/json"),
+            Self::MessagePack => HeaderValue::from_static("application/msgpack"),
+        }
+    }
+}
+
+impl<S> FromRequestParts<S> for ResponseFormat
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let format = parts
+            .headers
+            .get(ACCEPT)
+            .and_then(|header| header.to_str().ok())
+            .map(Self::parse)
+            .unwrap_or_default();
+
+        Ok(format)
+    }
+}
+
+/// Responder that serializes the body in the format the caller negotiated with the `Accept`
+/// header, from the [`ResponseFormat`] extractor.
+///
+/// The response sets `Vary: Accept`, so caches do not serve one format to a caller that asked for
+/// the other. MessagePack is serialized with named fields, so it has the same structure as the
+/// JSON.
+pub struct Negotiated<T>(pub ResponseFormat, pub T);
+
+impl<T: Serialize> IntoResponse for Negotiated<T> {
+    fn into_response(self) -> axum::response::Response {
+        let Self(format, value) = self;
+
+        let body = match format {
+            ResponseFormat::Json => serde_json::to_vec(&value).map_err(|error| error.to_string()),
+            ResponseFormat::MessagePack => {
+                rmp_serde::to_vec_named(&value).map_err(|error| error.to_string())
+            }
+        };
+
+        let mut response = match body {
+            Ok(body) => ([(CONTENT_TYPE, format.content_type())], body).into_response(),
+            Err(error) => {
+                log::error!("could not serialize the {format:?} response: {error}");
+                ErrorResponse::internal_server_error().into_response()
+            }
+        };
+
+        append_vary(response.headers_mut(), &[ACCEPT]);
+        response
+    }
+}
@@ -1,4 +1,5 @@
-use core::{error::Error, panic::Location};
+use core::{error::Error, fmt, panic::Location};
+use std::sync::Arc;
 
 use axum::{extract::rejection::JsonRejection, response::IntoResponse};
 use http::StatusCode;
@@ -8,6 +9,10 @@ use ts_rust_helper::error::{ErrorLogger, IntoErrorReport};
 /// Trait for providing convenience functions to mark an error as a given type.
 pub trait InlineErrorResponse<T> {
     /// Mark the error as an internal server error.
+    ///
+    /// The error is logged but not kept, use
+    /// [`InlineReportedErrorResponse::reported_internal_server_error`] to keep it as the
+    /// [`ErrorResponse::source`].
     #[track_caller]
     fn internal_server_error(self) -> Result<T, ErrorResponse>;
 
@@ -27,59 +32,116 @@ pub trait InlineErrorResponse<T> {
 impl<T, E: Error> InlineErrorResponse<T> for Result<T, E> {
     #[track_caller]
     fn internal_server_error(self) -> Result<T, ErrorResponse> {
-        self.into_report()
-            .log_error()
-            .map_err(|_| ErrorResponse::internal_server_error())
+        match self.into_report().log_error() {
+            Ok(value) => Ok(value),
+            Err(_) => Err(ErrorResponse::internal_server_error()),
+        }
     }
 
     #[track_caller]
     fn unprocessable_entity(self) -> Result<T, ErrorResponse> {
-        self.map_err(|_| ErrorResponse::unprocessable_entity())
+        match self {
+            Ok(value) => Ok(value),
+            Err(_) => Err(ErrorResponse::unprocessable_entity()),
+        }
     }
 
     #[track_caller]
     fn unauthenticated(self) -> Result<T, ErrorResponse> {
-        self.map_err(|_| ErrorResponse::unauthenticated())
+        match self {
+            Ok(value) => Ok(value),
+            Err(_) => Err(ErrorResponse::unauthenticated()),
+        }
     }
 
     #[track_caller]
     fn forbidden(self) -> Result<T, ErrorResponse> {
-        self.map_err(|_| ErrorResponse::forbidden())
+        match self {
+            Ok(value) => Ok(value),
+            Err(_) => Err(ErrorResponse::forbidden()),
+        }
+    }
+}
+
+/// Trait for marking an error as an internal server error while keeping it as the source of the
+/// response.
+pub trait InlineReportedErrorResponse<T> {
+    /// Mark the error as an internal server error, keeping it as the [`ErrorResponse::source`] so
+    /// middleware can report it.
+    #[track_caller]
+    fn reported_internal_server_error(self) -> Result<T, ErrorResponse>;
+}
+
+impl<T, E> InlineReportedErrorResponse<T> for Result<T, E>
+where
+    E: Error + Send + Sync + 'static,
+{
+    #[track_caller]
+    fn reported_internal_server_error(self) -> Result<T, ErrorResponse> {
+        match self {
+            Ok(value) => Ok(value),
+            Err(error) => Err(ErrorResponse::internal_server_error_from(error)),
+        }
     }
 }
 
 impl<T> InlineErrorResponse<T> for Option<T> {
     #[track_caller]
     fn internal_server_error(self) -> Result<T, ErrorResponse> {
-        self.into_report()
-            .log_error()
-            .map_err(|_| ErrorResponse::internal_server_error())
+        match self.into_report().log_error() {
+            Ok(value) => Ok(value),
+            Err(_) => Err(ErrorResponse::internal_server_error()),
+        }
     }
 
     #[track_caller]
     fn unprocessable_entity(self) -> Result<T, ErrorResponse> {
-        self.ok_or_else(ErrorResponse::unprocessable_entity)
+        match self {
+            Some(value) => Ok(value),
+            None => Err(ErrorResponse::unprocessable_entity()),
+        }
     }
 
     #[track_caller]
     fn unauthenticated(self) -> Result<T, ErrorResponse> {
-        self.ok_or_else(ErrorResponse::unauthenticated)
+        match self {
+            Some(value) => Ok(value),
+            None => Err(ErrorResponse::unauthenticated()),
+        }
     }
 
     #[track_caller]
     fn forbidden(self) -> Result<T, ErrorResponse> {
-        self.ok_or_else(ErrorResponse::unprocessable_entity)
+        match self {
+            Some(value) => Ok(value),
+            None => Err(ErrorResponse::forbidden()),
+        }
     }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 /// A problem detailing part of the error response.
+///
+/// Create problems with [`Self::new`] or [`Self::with_code`].
 pub struct Problem {
     /// A JSON path that identifies the part of the request that was the cause of the problem.
+    ///
+    /// This should be in the form produced by [`Pointer`], e.g., `$.items[3].price`.
     pub pointer: String,
     /// A human-readable explanation specific to this occurrence of the problem.
     pub detail: String,
+    /// A machine-readable code identifying the kind of problem, used to look up a localized
+    /// detail from a [`ProblemCatalog`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    /// The named values substituted into the localized detail for the code.
+    #[serde(skip)]
+    pub params: Vec<(String, String)>,
+    /// A coarse category of the problem, independent of the status of the response.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<ProblemCategory>,
 }
 impl Problem {
     /// Create a new problem from a pointer and some details.
@@ -87,13 +149,173 @@ impl Problem {
         Self {
             pointer: pointer.to_string(),
             detail: detail.to_string(),
+            code: None,
+            params: Vec::new(),
+            category: None,
+        }
+    }
+
+    /// Create a new problem from a pointer, a code, and the English details used when no localized
+    /// details are available.
+    pub fn with_code<S1: ToString, S2: ToString, S3: ToString>(
+        pointer: S1,
+        code: S2,
+        detail: S3,
+    ) -> Self {
+        Self {
+            code: Some(code.to_string()),
+            ..Self::new(pointer, detail)
+        }
+    }
+
+    /// Add a named value to substitute into the localized details, e.g., `{max}` in the template.
+    pub fn param<S1: ToString, S2: ToString>(mut self, name: S1, value: S2) -> Self {
+        self.params.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Set the category of the problem.
+    pub fn with_category(mut self, category: ProblemCategory) -> Self {
+        self.category = Some(category);
+        self
+    }
+
+    /// The status a response carrying only this problem should have, if the problem has a
+    /// category.
+    pub fn status_hint(&self) -> Option<StatusCode> {
+        self.category.map(ProblemCategory::status)
+    }
+
+    /// Replace the details with the catalog's template for the first of the languages that has one.
+    ///
+    /// The details are left unchanged if the problem has no code or no language has a template.
+    pub fn localize<C: ProblemCatalog + ?Sized>(&mut self, catalog: &C, languages: &[String]) {
+        let Some(code) = &self.code else {
+            return;
+        };
+
+        let Some(template) = languages
+            .iter()
+            .find_map(|language| catalog.template(code, language))
+        else {
+            return;
+        };
+
+        let mut detail = template.to_string();
+        for (name, value) in &self.params {
+            detail = detail.replace(&format!("{{{name}}}"), value);
+        }
+
+        self.detail = detail;
+    }
+}
+
+/// A coarse category of a problem.
+///
+/// The variants are ordered from least to most severe, the most severe category of a set of
+/// problems decides the status of the response, see [`ErrorResponse::from_problems`]. New
+/// categories may be added, so matches on this must have a wildcard arm.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub enum ProblemCategory {
+    /// Part of the request was invalid.
+    Validation,
+    /// The request conflicts with the current state of the resource.
+    Conflict,
+    /// The caller has made too many requests.
+    RateLimit,
+    /// The caller is not allowed to perform the request.
+    Authorization,
+    /// The caller could not be authenticated.
+    Authentication,
+    /// The request could not be completed because of a fault in the server.
+    Internal,
+}
+impl ProblemCategory {
+    /// The status of a response for problems of this category.
+    pub fn status(self) -> StatusCode {
+        match self {
+            Self::Validation => StatusCode::BAD_REQUEST,
+            Self::Conflict => StatusCode::CONFLICT,
+            Self::RateLimit => StatusCode::TOO_MANY_REQUESTS,
+            Self::Authorization => StatusCode::FORBIDDEN,
+            Self::Authentication => StatusCode::UNAUTHORIZED,
+            Self::Internal => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 }
 
+/// A catalog of localized problem details.
+///
+/// Use [`localize_problems_layer`](crate::localize_problems_layer) to localize error responses
+/// with the catalog.
+pub trait ProblemCatalog {
+    /// Get the template for a problem code in a language.
+    ///
+    /// Languages are lowercase, as produced by [`AcceptLanguage`](crate::AcceptLanguage), e.g.,
+    /// `en-nz` or `en`.
+    ///
+    /// Templates refer to the problem's params by name, e.g., `must be at most {max}`.
+    fn template(&self, code: &str, language: &str) -> Option<&str>;
+}
+
+/// Builder for a JSON path that identifies part of a request, e.g., `$.items[3].price`.
+///
+/// Fields that are not plain identifiers are written in bracket notation, e.g.,
+/// `$['content-type']`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pointer(String);
+impl Pointer {
+    /// A pointer to the root of the request.
+    pub fn root() -> Self {
+        Self("$".to_string())
+    }
+
+    /// Point to a field of the current value.
+    pub fn field(mut self, name: &str) -> Self {
+        let is_identifier = name
+            .chars()
+            .next()
+            .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+            && name
+                .chars()
+                .all(|character| character.is_ascii_alphanumeric() || character == '_');
+
+        if is_identifier {
+            self.0.push('.');
+            self.0.push_str(name);
+        } else {
+            let escaped = name.replace('\\', "\\\\").replace('\'', "\\'");
+            self.0.push_str(&format!("['{escaped}']"));
+        }
+
+        self
+    }
+
+    /// Point to an index of the current array.
+    pub fn index(mut self, index: usize) -> Self {
+        self.0.push_str(&format!("[{index}]"));
+        self
+    }
+}
+impl Default for Pointer {
+    fn default() -> Self {
+        Self::root()
+    }
+}
+impl fmt::Display for Pointer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
 /// JSON payload for an error response.
+///
+/// Create responses with the convenience functions, e.g., [`Self::bad_request`].
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct ErrorResponse {
     #[serde(skip)]
     /// Status code of the response
@@ -101,33 +323,117 @@ pub struct ErrorResponse {
     /// The list of problems to relay to the caller.
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub problems: Vec<Problem>,
+    /// The error that caused the response, which is never sent to the caller.
+    ///
+    /// When the response is produced, this is inserted into the response extensions as a
+    /// [`ReportedError`], so middleware can report it.
+    #[serde(skip)]
+    pub source: Option<ReportedError>,
 }
 
 impl ErrorResponse {
+    /// Localize the details of each problem using a catalog and the caller's preferred languages.
+    pub fn localize<C: ProblemCatalog + ?Sized>(
+        mut self,
+        catalog: &C,
+        languages: &[String],
+    ) -> Self {
+        for problem in &mut self.problems {
+            problem.localize(catalog, languages);
+        }
+
+        self
+    }
+
+    /// Create a response for a set of problems, with the status of the most severe category.
+    ///
+    /// Problems without a category are treated as [`ProblemCategory::Validation`].
+    #[track_caller]
+    pub fn from_problems(problems: Vec<Problem>) -> Self {
+        let category = problems
+            .iter()
+            .map(|problem| problem.category.unwrap_or(ProblemCategory::Validation))
+            .max()
+            .unwrap_or(ProblemCategory::Validation);
+
+        log::debug!(
+            "[{}] request had problems of category {category:?}",
+            Location::caller()
+        );
+        Self {
+            status: category.status(),
+            problems,
+            source: None,
+        }
+    }
+
     /// Convenience function for an internal server error response.
+    #[track_caller]
     pub fn internal_server_error() -> Self {
+        log::debug!(
+            "[{}] request caused an internal server error",
+            Location::caller()
+        );
         Self {
             status: StatusCode::INTERNAL_SERVER_ERROR,
             problems: vec![],
+            source: None,
+        }
+    }
+
+    /// Convenience function for an internal server error response caused by an error.
+    ///
+    /// The error is logged and kept as the [`Self::source`], so middleware can report it, but the
+    /// caller receives the same response as [`Self::internal_server_error`].
+    #[track_caller]
+    pub fn internal_server_error_from<E>(error: E) -> Self
+    where
+        E: Error + Send + Sync + 'static,
+    {
+        log::error!("[{}] {error}", Location::caller());
+        Self {
+            source: Some(ReportedError(Arc::new(error))),
+            ..Self::internal_server_error()
         }
     }
 
     /// Convenience function for an unauthenticated response.
+    ///
+    /// Use this when the caller could not be authenticated, e.g., the credential is missing,
+    /// malformed, expired, revoked, or untrusted, so the client should authenticate again. Use
+    /// [`Self::forbidden`] when the caller was authenticated but is not allowed.
     #[track_caller]
     pub fn unauthenticated() -> Self {
         log::warn!("[{}] request was unauthenticated", Location::caller());
         Self {
             status: StatusCode::UNAUTHORIZED,
             problems: vec![],
+            source: None,
+        }
+    }
+
+    /// Convenience function for an unauthenticated response, with a set of problems explaining why.
+    ///
+    /// Only use this where it is safe to tell the client why they are unauthenticated.
+    #[track_caller]
+    pub fn unauthenticated_with(problems: Vec<Problem>) -> Self {
+        log::warn!("[{}] request was unauthenticated", Location::caller());
+        Self {
+            status: StatusCode::UNAUTHORIZED,
+            problems,
+            source: None,
         }
     }
 
     /// Convenience function for a bad request response, with a set of problems that made the client
     /// should fix.
+    #[track_caller]
     pub fn bad_request(problems: Vec<Problem>) -> Self {
+        log::debug!("[{}] request was bad", Location::caller());
         Self {
             status: StatusCode::BAD_REQUEST,
             problems,
+            source: None,
         }
     }
 
@@ -138,25 +444,111 @@ impl ErrorResponse {
         Self {
             status: StatusCode::UNPROCESSABLE_ENTITY,
             problems: vec![],
+            source: None,
         }
     }
 
     /// Convenience function for a forbidden response.
+    ///
+    /// Use this when the caller was authenticated but is not allowed to make the request, e.g., the
+    /// token is of the wrong type, so authenticating again will not help.
+    #[track_caller]
     pub fn forbidden() -> Self {
+        log::debug!("[{}] request was forbidden", Location::caller());
         Self {
             status: StatusCode::FORBIDDEN,
             problems: vec![],
+            source: None,
+        }
+    }
+
+    /// Convenience function for a forbidden response, with a set of problems explaining why, e.g.,
+    /// a missing scope.
+    ///
+    /// Only use this where it is safe to tell the client why they are forbidden.
+    #[track_caller]
+    pub fn forbidden_with(problems: Vec<Problem>) -> Self {
+        log::debug!("[{}] request was forbidden", Location::caller());
+        Self {
+            status: StatusCode::FORBIDDEN,
+            problems,
+            source: None,
+        }
+    }
+
+    /// Convenience function for when the response cannot be produced in a format the client accepts.
+    #[track_caller]
+    pub fn not_acceptable() -> Self {
+        log::warn!("[{}] request was not acceptable", Location::caller());
+        Self {
+            status: StatusCode::NOT_ACCEPTABLE,
+            problems: vec![],
+            source: None,
+        }
+    }
+
+    /// Convenience function for when the request body is in an unsupported format.
+    #[track_caller]
+    pub fn unsupported_media_type() -> Self {
+        log::warn!(
+            "[{}] request had an unsupported media type",
+            Location::caller()
+        );
+        Self {
+            status: StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            problems: vec![],
+            source: None,
+        }
+    }
+
+    /// Convenience function for when the request headers are too many or too large.
+    #[track_caller]
+    pub fn request_header_fields_too_large() -> Self {
+        log::warn!("[{}] request headers were too large", Location::caller());
+        Self {
+            status: StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE,
+            problems: vec![],
+            source: None,
         }
     }
 }
 
 impl IntoResponse for ErrorResponse {
     fn into_response(self) -> axum::response::Response {
-        if self.problems.is_empty() {
+        let mut response = if self.problems.is_empty() {
             self.status.into_response()
         } else {
             (self.status, axum::Json(&self)).into_response()
+        };
+
+        if self.problems.iter().any(|problem| problem.code.is_some()) {
+            response
+                .extensions_mut()
+                .insert(LocalizableProblems(self.problems));
+        }
+
+        if let Some(source) = self.source {
+            response.extensions_mut().insert(source);
         }
+
+        response
+    }
+}
+
+/// The problems of an [`ErrorResponse`] with codes, in the extensions of the response so they can
+/// be localized by [`LocalizeProblemsLayer`](crate::LocalizeProblemsLayer).
+#[derive(Clone, Debug)]
+pub(crate) struct LocalizableProblems(pub(crate) Vec<Problem>);
+
+/// The error that caused an [`ErrorResponse`], in the extensions of the response so middleware,
+/// e.g., an error reporting layer, can report it.
+///
+/// It is never sent to the caller.
+#[derive(Clone, Debug)]
+pub struct ReportedError(pub Arc<dyn Error + Send + Sync + 'static>);
+impl fmt::Display for ReportedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
     }
 }
 
@@ -167,6 +559,61 @@ impl From<JsonRejection> for ErrorResponse {
             value.status(),
             value.body_text()
         );
-        Self::unprocessable_entity()
+
+        match value {
+            JsonRejection::MissingJsonContentType(_) => Self::unsupported_media_type(),
+            _ => Self::unprocessable_entity(),
+        }
+    }
+}
+
+#[cfg(feature = "anyhow")]
+impl ErrorResponse {
+    /// Convenience function for an internal server error caused by an [`anyhow::Error`], logging
+    /// the full chain of causes without exposing them to the client.
+    #[track_caller]
+    pub fn from_anyhow(error: &anyhow::Error) -> Self {
+        log::error!("[{}] {error:#}", Location::caller());
+        Self::internal_server_error()
+    }
+}
+
+#[cfg(feature = "anyhow")]
+impl From<anyhow::Error> for ErrorResponse {
+    #[track_caller]
+    fn from(value: anyhow::Error) -> Self {
+        Self::from_anyhow(&value)
+    }
+}
+
+impl From<base64ct::Error> for ErrorResponse {
+    fn from(value: base64ct::Error) -> Self {
+        log::warn!("request contained invalid base64: {value}");
+        Self::bad_request(vec![Problem::new(
+            Pointer::root(),
+            "must be valid unpadded base64url",
+        )])
+    }
+}
+
+impl From<tokio_postgres::Error> for ErrorResponse {
+    #[track_caller]
+    fn from(value: tokio_postgres::Error) -> Self {
+        Self::internal_server_error_from(value)
+    }
+}
+
+impl From<bb8::RunError<tokio_postgres::Error>> for ErrorResponse {
+    #[track_caller]
+    fn from(value: bb8::RunError<tokio_postgres::Error>) -> Self {
+        Self::internal_server_error_from(value)
+    }
+}
+
+impl From<uuid::Error> for ErrorResponse {
+    #[track_caller]
+    fn from(value: uuid::Error) -> Self {
+        log::warn!("request contained an invalid UUID: {value}");
+        Self::bad_request(vec![Problem::new(Pointer::root(), "must be a valid UUID")])
     }
 }
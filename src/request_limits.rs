@@ -0,0 +1,100 @@
+use core::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use axum::response::{IntoResponse, Response};
+use http::Request;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::ErrorResponse;
+
+/// Limits on the headers of a request.
+#[derive(Debug, Clone, Copy)]
+pub struct RequestLimits {
+    /// The maximum number of headers.
+    pub max_headers: usize,
+    /// The maximum combined size of the header names and values in bytes.
+    pub max_header_bytes: usize,
+}
+impl Default for RequestLimits {
+    fn default() -> Self {
+        Self {
+            max_headers: 100,
+            max_header_bytes: 16 * 1024,
+        }
+    }
+}
+impl RequestLimits {
+    /// Returns if the request is within the limits.
+    fn allows<B>(&self, request: &Request<B>) -> bool {
+        let headers = request.headers();
+        if headers.len() > self.max_headers {
+            return false;
+        }
+
+        let header_bytes: usize = headers
+            .iter()
+            .map(|(name, value)| name.as_str().len() + value.len())
+            .sum();
+
+        header_bytes <= self.max_header_bytes
+    }
+}
+
+/// Layer that rejects requests exceeding the header limits with a `431 Request Header Fields Too
+/// Large` before any extractors run.
+///
+/// This protects extractors that parse headers, such as the `Authorization` header, from
+/// pathological input. It complements, but does not replace, the limits of the HTTP server.
+pub fn request_limits_layer(limits: RequestLimits) -> RequestLimitsLayer {
+    RequestLimitsLayer { limits }
+}
+
+/// Layer that rejects requests exceeding the header limits.
+#[derive(Debug, Clone, Copy)]
+#[must_use]
+pub struct RequestLimitsLayer {
+    limits: RequestLimits,
+}
+impl<S> Layer<S> for RequestLimitsLayer {
+    type Service = RequestLimitsService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequestLimitsService {
+            inner,
+            limits: self.limits,
+        }
+    }
+}
+
+/// Middleware that rejects requests exceeding the header limits.
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct RequestLimitsService<S> {
+    inner: S,
+    limits: RequestLimits,
+}
+impl<S, ReqBody> Service<Request<ReqBody>> for RequestLimitsService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response>,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        if !self.limits.allows(&request) {
+            let response = ErrorResponse::request_header_fields_too_large().into_response();
+            return Box::pin(async { Ok(response) });
+        }
+
+        Box::pin(self.inner.call(request))
+    }
+}
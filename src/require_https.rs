@@ -0,0 +1,62 @@
+use axum::extract::FromRequestParts;
+use http::{HeaderName, request::Parts};
+
+use crate::ErrorResponse;
+
+/// The `X-Forwarded-Proto` header.
+static X_FORWARDED_PROTO: HeaderName = HeaderName::from_static("x-forwarded-proto");
+
+/// Trait for if some state configures the header a TLS-terminating proxy forwards the protocol of
+/// the original request in.
+pub trait HasForwardedProtoHeader {
+    /// The header the protocol of the original request is forwarded in, e.g., `X-Forwarded-Proto`
+    /// or `X-Forwarded-Ssl`.
+    fn forwarded_proto_header(&self) -> &HeaderName {
+        &X_FORWARDED_PROTO
+    }
+}
+
+/// Extractor that rejects requests that did not arrive over HTTPS as forbidden.
+///
+/// The protocol is read from the configured forwarded header, which must be `https`, or `on` for
+/// `X-Forwarded-Ssl`. If a chain of proxies forwarded a list of protocols, the first, i.e., the
+/// protocol of the client's request, is used. Requests without the header are rejected.
+///
+/// The forwarded header can be set by the client, so this must only be used behind a trusted proxy
+/// that overwrites the header on every request.
+pub struct RequireHttps;
+
+impl<S> FromRequestParts<S> for RequireHttps
+where
+    S: Send + Sync + HasForwardedProtoHeader,
+{
+    type Rejection = ErrorResponse;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let header = state.forwarded_proto_header();
+
+        let protocol = parts
+            .headers
+            .get(header)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(',').next())
+            .map(str::trim);
+
+        match protocol {
+            Some(protocol)
+                if protocol.eq_ignore_ascii_case("https")
+                    || protocol.eq_ignore_ascii_case("on") =>
+            {
+                Ok(Self)
+            }
+            Some(protocol) => {
+                log::warn!("request arrived over `{protocol}` according to `{header}`");
+                Err(ErrorResponse::forbidden())
+            }
+            None => {
+                log::warn!("request has no valid `{header}` header");
+                Err(ErrorResponse::forbidden())
+            }
+        }
+    }
+}
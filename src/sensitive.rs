@@ -0,0 +1,24 @@
+use axum::response::{IntoResponse, Response};
+use http::{
+    HeaderValue,
+    header::{CACHE_CONTROL, PRAGMA},
+};
+
+/// Response wrapper that prevents browsers and intermediaries from storing the response.
+///
+/// Sets `Cache-Control: no-store` and `Pragma: no-cache` on the wrapped response, replacing any
+/// existing values. Wrap responses containing secrets, such as tokens or credentials.
+pub struct Sensitive<R>(pub R);
+
+impl<R: IntoResponse> IntoResponse for Sensitive<R> {
+    fn into_response(self) -> Response {
+        let Self(response) = self;
+        let mut response = response.into_response();
+
+        let headers = response.headers_mut();
+        headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-store"));
+        headers.insert(PRAGMA, HeaderValue::from_static("no-cache"));
+
+        response
+    }
+}
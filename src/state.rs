@@ -1,4 +1,4 @@
-use core::str::FromStr;
+use core::{fmt, str::FromStr};
 
 use http::header::{HeaderMap, HeaderName, HeaderValue, InvalidHeaderName, InvalidHeaderValue};
 use reqwest::Client;
@@ -11,13 +11,21 @@ pub trait HasHttpClient {
     fn http_client(&self) -> &Client;
 }
 
-#[derive(Debug, JsonSchema, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[derive(JsonSchema, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 /// The config for an HTTP client.
 pub struct HttpClientConfig {
     api_key_header: String,
     api_key: String,
 }
+impl fmt::Debug for HttpClientConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HttpClientConfig")
+            .field("api_key_header", &self.api_key_header)
+            .field("api_key", &"***")
+            .finish()
+    }
+}
 impl Default for HttpClientConfig {
     fn default() -> Self {
         Self {
@@ -66,8 +74,8 @@ pub enum CreateHttpClientError {
     #[non_exhaustive]
     BuildClient { source: reqwest::Error },
 }
-impl core::fmt::Display for CreateHttpClientError {
-    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+impl fmt::Display for CreateHttpClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match &self {
             Self::InvalidHeaderValue { value, .. } => {
                 write!(f, "`{value}` is not a valid header value")
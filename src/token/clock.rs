@@ -0,0 +1,49 @@
+//! Sources of the current time for issuing and validating tokens.
+use core::fmt::Debug;
+use std::sync::{Arc, Mutex};
+
+use jiff::{SignedDuration, Timestamp};
+
+/// A source of the current time.
+pub trait Clock: Debug + Send + Sync {
+    /// Get the current time.
+    fn now(&self) -> Timestamp;
+}
+
+/// The system clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+impl Clock for SystemClock {
+    fn now(&self) -> Timestamp {
+        Timestamp::now()
+    }
+}
+
+/// A clock that only changes when it is set or advanced, so tests can control time.
+///
+/// Clones share the same time, so a clone can be given to the code under test and advanced by the
+/// test.
+#[derive(Debug, Clone)]
+pub struct TestClock(Arc<Mutex<Timestamp>>);
+impl TestClock {
+    /// Create a new clock starting at a time.
+    pub fn new(now: Timestamp) -> Self {
+        Self(Arc::new(Mutex::new(now)))
+    }
+
+    /// Set the current time.
+    pub fn set(&self, now: Timestamp) {
+        *self.0.lock().unwrap_or_else(|error| error.into_inner()) = now;
+    }
+
+    /// Move the current time forwards, or backwards for a negative duration.
+    pub fn advance(&self, duration: SignedDuration) {
+        let mut now = self.0.lock().unwrap_or_else(|error| error.into_inner());
+        *now = now.saturating_add(duration).unwrap_or(*now);
+    }
+}
+impl Clock for TestClock {
+    fn now(&self) -> Timestamp {
+        *self.0.lock().unwrap_or_else(|error| error.into_inner())
+    }
+}
@@ -2,44 +2,109 @@
 //!
 
 use core::{error::Error, fmt};
-use std::{fs, io, path::PathBuf};
+use std::{fs, io, path::PathBuf, sync::Arc};
 
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::token::{
     Algorithm, JsonWebKey, JsonWebKeySetCache, SigningJsonWebKey,
-    json_web_key::{Curve, JsonWebKeyParameters, JsonWebKeySet, signing::FromPemError},
+    json_web_key::{
+        Curve, JsonWebKeyParameters, JsonWebKeySet, JwksFetcher, signing::FromPemError,
+    },
 };
+#[cfg(feature = "reqwest")]
+use crate::{CreateHttpClientError, HttpClientConfig};
 
 /// The config for validating tokens.
 #[derive(Debug, JsonSchema, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct TokenValidationConfig {
     /// The endpoint that serves the key sets used to validate a token.
     jwks_endpoint: String,
+    /// The `iss` values the keys from `jwks_endpoint` may authenticate. If this is empty, the
+    /// issuer is not checked.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    issuers: Vec<String>,
+    /// Other trusted key sets, each bound to the issuers it may authenticate.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    additional_key_sets: Vec<KeySetSourceConfig>,
     /// The endpoint to check if a token has been revoked.
     /// This will have `/{token.claims.tid}` appended to it.
     pub revocation_endpoint: String,
+    /// The config for a dedicated HTTP client to check revocation with, so the revocation service
+    /// can use its own credentials instead of the shared client's.
+    #[cfg(feature = "reqwest")]
+    pub revocation_http_client: Option<HttpClientConfig>,
 }
 impl Default for TokenValidationConfig {
     fn default() -> Self {
         Self {
             jwks_endpoint: "http://localhost:8081/.well-known/jwks.json".to_string(),
+            issuers: Vec::new(),
+            additional_key_sets: Vec::new(),
             revocation_endpoint: "http://localhost:8081/revoked-tokens".to_string(),
+            #[cfg(feature = "reqwest")]
+            revocation_http_client: None,
         }
     }
 }
 impl TokenValidationConfig {
-    /// Create the cache for the JWKS.
-    pub fn jwks_cache(&self) -> JsonWebKeySetCache {
-        JsonWebKeySetCache::new(self.jwks_endpoint.clone())
+    /// Create the dedicated HTTP client for checking revocation, if one is configured.
+    #[cfg(feature = "reqwest")]
+    pub fn revocation_client(&self) -> Result<Option<reqwest::Client>, CreateHttpClientError> {
+        self.revocation_http_client
+            .as_ref()
+            .map(HttpClientConfig::http_client)
+            .transpose()
     }
+
+    /// Create the cache for the JWKS, fetching the key set with the given client.
+    #[cfg(feature = "reqwest")]
+    pub fn jwks_cache(&self, client: reqwest::Client) -> JsonWebKeySetCache {
+        self.jwks_cache_with(Arc::new(client))
+    }
+
+    /// Create the caches for the JWKS and every additional key set, fetching the key sets with the
+    /// given client.
+    #[cfg(feature = "reqwest")]
+    pub fn jwks_caches(&self, client: reqwest::Client) -> Vec<JsonWebKeySetCache> {
+        self.jwks_caches_with(Arc::new(client))
+    }
+
+    /// Create the cache for the JWKS, fetching the key set with the given fetcher.
+    pub fn jwks_cache_with(&self, fetcher: Arc<dyn JwksFetcher>) -> JsonWebKeySetCache {
+        JsonWebKeySetCache::from_fetcher(self.jwks_endpoint.clone(), fetcher)
+            .with_issuers(self.issuers.clone())
+    }
+
+    /// Create the caches for the JWKS and every additional key set, fetching the key sets with the
+    /// given fetcher.
+    pub fn jwks_caches_with(&self, fetcher: Arc<dyn JwksFetcher>) -> Vec<JsonWebKeySetCache> {
+        let additional = self.additional_key_sets.iter().map(|source| {
+            JsonWebKeySetCache::from_fetcher(source.jwks_endpoint.clone(), Arc::clone(&fetcher))
+                .with_issuers(source.issuers.clone())
+        });
+
+        core::iter::once(self.jwks_cache_with(Arc::clone(&fetcher)))
+            .chain(additional)
+            .collect()
+    }
+}
+
+/// The config for a trusted key set bound to the issuers it may authenticate.
+#[derive(Debug, JsonSchema, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct KeySetSourceConfig {
+    /// The endpoint that serves the key set.
+    pub jwks_endpoint: String,
+    /// The `iss` values the keys may authenticate.
+    pub issuers: Vec<String>,
 }
 
 /// The config for issuing tokens.
 #[derive(Debug, JsonSchema, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct TokenIssuingConfig {
     /// The path to the JWKS file.
     jwks_file_path: PathBuf,
@@ -61,6 +126,8 @@ impl Default for TokenIssuingConfig {
                     x: "x".to_string(),
                     y: "y".to_string(),
                 },
+                x5t: None,
+                x5t_s256: None,
             },
             signing_key_path: "path/to/private.pem".into(),
         }
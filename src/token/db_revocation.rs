@@ -0,0 +1,156 @@
+//! Token revocation backed by a Postgres table, for services that share a database with the
+//! issuer.
+
+use core::{error::Error, fmt};
+
+use jiff::{SignedDuration, Timestamp};
+use ts_sql_helper_lib::SqlTimestamp;
+
+use crate::ConnectionPool;
+
+const INSERT_REVOKED_TOKEN: &str = "
+INSERT INTO revoked_tokens (tid, exp) VALUES ($1, $2)
+ON CONFLICT (tid) DO NOTHING";
+
+const IS_REVOKED: &str = "SELECT EXISTS (SELECT 1 FROM revoked_tokens WHERE tid = $1)";
+
+const PRUNE_REVOKED_TOKENS: &str = "DELETE FROM revoked_tokens WHERE exp < $1";
+
+/// Insert a revoked token into the `revoked_tokens` table.
+///
+/// The table is expected to have the columns `tid TEXT PRIMARY KEY` and `exp TIMESTAMPTZ`, and
+/// should have an index on `exp`. `exp` is the expiry of the token, after which the token no
+/// longer needs to be tracked, see [`prune_revoked_tokens`].
+///
+/// Revoking a token that is already revoked does nothing.
+pub async fn insert_revoked_token(
+    pool: &ConnectionPool,
+    tid: &str,
+    exp: SqlTimestamp,
+) -> Result<(), DbRevocationError> {
+    let connection = pool
+        .get()
+        .await
+        .map_err(|source| DbRevocationError::GetConnection { source })?;
+
+    connection
+        .execute(INSERT_REVOKED_TOKEN, &[&tid, &exp])
+        .await
+        .map_err(|source| DbRevocationError::Query { source })?;
+
+    Ok(())
+}
+
+/// Returns if a token is in the `revoked_tokens` table.
+///
+/// See [`insert_revoked_token`] for the expected table.
+pub async fn is_revoked_in_db(pool: &ConnectionPool, tid: &str) -> Result<bool, DbRevocationError> {
+    let connection = pool
+        .get()
+        .await
+        .map_err(|source| DbRevocationError::GetConnection { source })?;
+
+    let row = connection
+        .query_one(IS_REVOKED, &[&tid])
+        .await
+        .map_err(|source| DbRevocationError::Query { source })?;
+
+    row.try_get(0)
+        .map_err(|source| DbRevocationError::Query { source })
+}
+
+/// Delete the tokens that expired more than `leeway` before `now` from the `revoked_tokens` table,
+/// returning how many were deleted.
+///
+/// Tokens up to the expiry leeway past their expiry are still accepted, so `leeway` must be at
+/// least the
+/// [`TokenValidationOptions::leeway`](crate::token::extractor::TokenValidationOptions::leeway),
+/// otherwise pruning would un-revoke those tokens until the leeway passes. Tokens further past their expiry are rejected regardless of revocation, so
+/// they no longer need to be tracked. See [`insert_revoked_token`] for the expected table.
+pub async fn prune_revoked_tokens(
+    pool: &ConnectionPool,
+    now: Timestamp,
+    leeway: SignedDuration,
+) -> Result<u64, DbRevocationError> {
+    let cutoff = now.checked_sub(leeway).unwrap_or(Timestamp::MIN);
+
+    let connection = pool
+        .get()
+        .await
+        .map_err(|source| DbRevocationError::GetConnection { source })?;
+
+    connection
+        .execute(PRUNE_REVOKED_TOKENS, &[&SqlTimestamp(cutoff)])
+        .await
+        .map_err(|source| DbRevocationError::Query { source })
+}
+
+/// Token revocation checked against the `revoked_tokens` table instead of the HTTP revocation
+/// endpoint.
+///
+/// Return it from
+/// [`HasRevocationEndpoint::revocation_database`](crate::token::extractor::HasRevocationEndpoint::revocation_database)
+/// to have the token extractors use it.
+#[derive(Debug, Clone)]
+pub struct DbRevocation {
+    /// The connection pool.
+    pub pool: ConnectionPool,
+}
+
+impl DbRevocation {
+    /// Create a new database revocation check.
+    pub fn new(pool: ConnectionPool) -> Self {
+        Self { pool }
+    }
+
+    /// Revoke a token until it expires at `exp`.
+    pub async fn revoke(&self, tid: &str, exp: Timestamp) -> Result<(), DbRevocationError> {
+        insert_revoked_token(&self.pool, tid, SqlTimestamp(exp)).await
+    }
+
+    /// Returns if a token has been revoked.
+    pub async fn is_revoked(&self, tid: &str) -> Result<bool, DbRevocationError> {
+        is_revoked_in_db(&self.pool, tid).await
+    }
+
+    /// Delete the revoked tokens that expired more than `leeway` ago, where `leeway` is at least
+    /// the expiry leeway tokens are validated with, see [`prune_revoked_tokens`].
+    pub async fn prune_expired(&self, leeway: SignedDuration) -> Result<u64, DbRevocationError> {
+        prune_revoked_tokens(&self.pool, Timestamp::now(), leeway).await
+    }
+}
+
+/// Error variants for storing and checking revoked tokens.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum DbRevocationError {
+    /// A connection could not be retrieved from the pool.
+    #[non_exhaustive]
+    GetConnection {
+        /// The source of the error.
+        source: bb8::RunError<tokio_postgres::Error>,
+    },
+
+    /// The query failed.
+    #[non_exhaustive]
+    Query {
+        /// The source of the error.
+        source: tokio_postgres::Error,
+    },
+}
+impl fmt::Display for DbRevocationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self {
+            Self::GetConnection { .. } => write!(f, "could not get a connection from the pool"),
+            Self::Query { .. } => write!(f, "revoked token query failed"),
+        }
+    }
+}
+impl Error for DbRevocationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self {
+            Self::GetConnection { source } => Some(source),
+            Self::Query { source } => Some(source),
+        }
+    }
+}
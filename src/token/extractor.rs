@@ -1,16 +1,209 @@
 //! Extractor for extracting and verifying the JSON web token token from the request.
+//!
+//! Authentication failures, where the token is missing, malformed, expired, revoked, or not
+//! trusted, are rejected as unauthenticated (`401`), so the client knows to authenticate again.
+//! Authorization failures, where the token is valid but is not of the required type or does not
+//! grant the required action, are rejected as forbidden (`403`).
+use core::marker::PhantomData;
+use std::borrow::Cow;
+
 use axum::extract::{FromRequestParts, OptionalFromRequestParts};
-use http::{StatusCode, request::Parts};
+use http::{
+    HeaderName,
+    header::{AUTHORIZATION, COOKIE},
+    request::Parts,
+};
+use jiff::SignedDuration;
+use percent_encoding::percent_decode_str;
+use regex::Regex;
+use uuid::Uuid;
 
 use crate::{
-    ErrorResponse, HasHttpClient, InlineErrorResponse,
-    token::{JsonWebKeySetCache, JsonWebToken},
+    ErrorResponse, InlineReportedErrorResponse,
+    token::{
+        DbRevocation, JsonWebKeySetCache, JsonWebToken, VerifiedTokenCache,
+        json_web_key::key_set_cache::VerifyError,
+        json_web_token::{Claims, Subject, TokenType},
+    },
+    trace::in_span,
 };
 
 /// Marker trait for if some state has a JSON web key set cache.
 pub trait HasKeySetCache {
     /// Get the JSON web key set cache.
     fn jwks_cache(&self) -> &JsonWebKeySetCache;
+
+    /// Get the caches of every trusted JSON web key set.
+    ///
+    /// A token is verified by the first cache bound to its issuer with
+    /// [`JsonWebKeySetCache::with_issuers`], or else by the first cache that is not bound to any
+    /// issuer, so an unbound cache never verifies tokens from an issuer that has its own cache.
+    fn jwks_caches(&self) -> &[JsonWebKeySetCache] {
+        core::slice::from_ref(self.jwks_cache())
+    }
+
+    /// Get the cache of recently verified tokens.
+    /// If this is `None`, every request is fully verified.
+    fn verified_token_cache(&self) -> Option<&VerifiedTokenCache> {
+        None
+    }
+
+    /// Get the options for validating tokens.
+    fn token_validation_options(&self) -> &TokenValidationOptions {
+        &DEFAULT_TOKEN_VALIDATION_OPTIONS
+    }
+}
+
+static DEFAULT_TOKEN_VALIDATION_OPTIONS: TokenValidationOptions = TokenValidationOptions::DEFAULT;
+
+/// Options for validating tokens.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct TokenValidationOptions {
+    /// The maximum clock drift allowed between the issuer and this service when checking if a
+    /// token has expired or was issued in the future.
+    pub leeway: SignedDuration,
+    /// The audience tokens must be intended for. If this is `None`, the audience is not checked.
+    pub audience: Option<String>,
+    /// The format the subject of tokens must have.
+    pub subject_format: SubjectFormat,
+    /// The critical header parameters this service understands and processes. Tokens with any
+    /// other critical header parameter are rejected.
+    pub understood_critical: Vec<String>,
+    /// The request header the token is read from.
+    pub header: HeaderName,
+    /// If the token in the header must be preceded by the `Bearer` scheme.
+    pub expects_bearer_scheme: bool,
+    /// If tokens that claim to have been issued in the future, beyond the leeway, are rejected.
+    pub rejects_future_iat: bool,
+    /// The cookie [`CookieToken`] reads the token from.
+    pub cookie: Cow<'static, str>,
+    /// The maximum length of a serialized token in bytes. Longer tokens are rejected before they
+    /// are decoded, bounding the work an unauthenticated client can cause.
+    pub max_token_length: usize,
+    /// If ECDSA signatures that are DER encoded, rather than the fixed-size `r || s` encoding
+    /// RFC 7518 requires, are accepted.
+    pub accepts_der_signatures: bool,
+}
+impl TokenValidationOptions {
+    /// The default options.
+    pub const DEFAULT: Self = Self {
+        leeway: SignedDuration::ZERO,
+        audience: None,
+        subject_format: SubjectFormat::Any,
+        understood_critical: Vec::new(),
+        header: AUTHORIZATION,
+        expects_bearer_scheme: true,
+        rejects_future_iat: false,
+        cookie: Cow::Borrowed("access_token"),
+        max_token_length: 8 * 1024,
+        accepts_der_signatures: false,
+    };
+
+    /// Require tokens to be intended for an audience.
+    pub fn audience(mut self, audience: String) -> Self {
+        self.audience = Some(audience);
+        self
+    }
+
+    /// Set the maximum clock drift allowed when checking if a token has expired.
+    pub fn leeway(mut self, leeway: SignedDuration) -> Self {
+        self.leeway = leeway;
+        self
+    }
+
+    /// Reject tokens that claim to have been issued in the future, beyond the leeway.
+    ///
+    /// This is not checked by default, as some issuers legitimately pre-date tokens.
+    pub fn reject_future_iat(mut self) -> Self {
+        self.rejects_future_iat = true;
+        self
+    }
+
+    /// Read the token from a header, e.g., one set by an API gateway, and set if the token is
+    /// preceded by the `Bearer` scheme.
+    pub fn header(mut self, header: HeaderName, expects_bearer_scheme: bool) -> Self {
+        self.header = header;
+        self.expects_bearer_scheme = expects_bearer_scheme;
+        self
+    }
+
+    /// Read the token for [`CookieToken`] from a cookie, by default `access_token`.
+    pub fn cookie(mut self, cookie: String) -> Self {
+        self.cookie = Cow::Owned(cookie);
+        self
+    }
+
+    /// Set the maximum length of a serialized token in bytes, by default 8 KiB.
+    pub fn max_token_length(mut self, max_token_length: usize) -> Self {
+        self.max_token_length = max_token_length;
+        self
+    }
+
+    /// Accept ECDSA signatures that are DER encoded, as issued by earlier versions of this crate.
+    ///
+    /// This is off by default, as RFC 7518 requires the fixed-size `r || s` encoding.
+    pub fn accept_der_signatures(mut self) -> Self {
+        self.accepts_der_signatures = true;
+        self
+    }
+
+    /// Get the serialized token from the value of the token header.
+    fn serialized_token<'a>(&self, value: &'a str) -> Option<&'a str> {
+        if !self.expects_bearer_scheme {
+            return Some(value.trim());
+        }
+
+        let (scheme, token) = value.split_once(' ')?;
+        scheme
+            .eq_ignore_ascii_case("bearer")
+            .then_some(token.trim())
+    }
+
+    /// Accept tokens that mark a header parameter as critical, as this service processes it.
+    pub fn understand_critical(mut self, parameter: String) -> Self {
+        self.understood_critical.push(parameter);
+        self
+    }
+
+    /// Require the subject of tokens to have a format.
+    pub fn subject_format(mut self, subject_format: SubjectFormat) -> Self {
+        self.subject_format = subject_format;
+        self
+    }
+}
+impl Default for TokenValidationOptions {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// The format the subject of a token must have.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum SubjectFormat {
+    /// Any subject is accepted.
+    Any,
+    /// The subject must be a hyphenated UUID.
+    Uuid,
+    /// The subject must match the pattern. Use [`Self::pattern`] to require the whole subject to
+    /// match.
+    Pattern(Regex),
+}
+impl SubjectFormat {
+    /// Create a format where the whole subject must match a pattern.
+    pub fn pattern(pattern: &str) -> Result<Self, regex::Error> {
+        Regex::new(&format!("^(?:{pattern})$")).map(Self::Pattern)
+    }
+
+    /// Returns if a subject has this format.
+    pub fn matches(&self, subject: &str) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Uuid => subject.len() == 36 && Uuid::try_parse(subject).is_ok(),
+            Self::Pattern(pattern) => pattern.is_match(subject),
+        }
+    }
 }
 
 /// Marker trait for if some state has a token revocation endpoint.
@@ -18,14 +211,120 @@ pub trait HasRevocationEndpoint {
     /// The endpoint to check if a token has been revoked.
     /// Will have `/{jwt.claims.tid}` appended to it.
     fn revocation_endpoint(&self) -> &str;
+
+    /// The HTTP client to use when checking if a token has been revoked.
+    /// If this is `None`, the state's shared HTTP client is used.
+    #[cfg(feature = "reqwest")]
+    fn revocation_client(&self) -> Option<&reqwest::Client> {
+        None
+    }
+
+    /// The database to check if a token has been revoked.
+    /// If this is `Some`, it is checked instead of the revocation endpoint.
+    fn revocation_database(&self) -> Option<&DbRevocation> {
+        None
+    }
+}
+
+/// The traits some state must implement for the token extractors.
+///
+/// This is implemented for every state that implements them. With the `reqwest` feature, the
+/// state must also have an HTTP client to check the revocation endpoint with. Without it, the
+/// state must return a [`HasRevocationEndpoint::revocation_database`].
+pub trait TokenVerificationState:
+    HasKeySetCache + HasRevocationEndpoint + revocation::RevocationClient
+{
+}
+impl<S: HasKeySetCache + HasRevocationEndpoint + revocation::RevocationClient>
+    TokenVerificationState for S
+{
+}
+
+mod revocation {
+    /// The client the revocation endpoint is checked with, which only exists with the `reqwest`
+    /// feature.
+    #[cfg(feature = "reqwest")]
+    pub trait RevocationClient: crate::HasHttpClient {}
+    #[cfg(feature = "reqwest")]
+    impl<S: crate::HasHttpClient> RevocationClient for S {}
+
+    #[cfg(not(feature = "reqwest"))]
+    pub trait RevocationClient {}
+    #[cfg(not(feature = "reqwest"))]
+    impl<S> RevocationClient for S {}
 }
 
 /// Extractor for extracting and verifying the JSON web token token from the request.
 pub struct Token(pub JsonWebToken);
 
+/// Extractor for extracting and verifying the JSON web token from a cookie, e.g., an `HttpOnly`
+/// cookie set for a same-site web app.
+///
+/// The cookie is named by [`TokenValidationOptions::cookie`], and its value may be
+/// percent-encoded. Requests with multiple cookies of that name with different values are
+/// rejected, as which one the browser intended cannot be known. The token is verified the same as
+/// [`Token`].
+pub struct CookieToken(pub JsonWebToken);
+
+/// Extractor for the claims of the verified JSON web token from the request.
+pub struct TokenClaims(pub Claims);
+
+/// Extractor for the subject of the verified JSON web token from the request.
+pub struct SubjectClaim(pub Subject);
+
+/// Extractor for a verified JSON web token that must be of the token type `K`, e.g.,
+/// `RequireTokenType<CommonToken>`.
+///
+/// Tokens of any other type are rejected as forbidden, so a token scoped to one flow, such as a
+/// provisioning token, cannot be used on endpoints meant for another.
+pub struct RequireTokenType<K>(pub JsonWebToken, pub PhantomData<K>);
+
+/// Extractor for a verified consent token that must authorise the action `A`, e.g.,
+/// `RequireConsent<DeleteAccount>`.
+///
+/// Tokens that are not consent tokens, or that do not authorise the action, are rejected as
+/// forbidden, so a consent token for one action cannot be used on the endpoint for another.
+pub struct RequireConsent<A>(pub JsonWebToken, pub PhantomData<A>);
+
+/// An action that can be required by [`RequireConsent`].
+pub trait ConsentAction {
+    /// The action, as it appears in the `act` claim, e.g., `delete:account`.
+    const ACTION: &'static str;
+}
+
+/// A kind of token that can be required by [`RequireTokenType`].
+pub trait TokenKind {
+    /// Returns if a token type is of this kind.
+    fn matches(token_type: &TokenType) -> bool;
+}
+
+/// A [`TokenType::Common`] token.
+pub struct CommonToken;
+impl TokenKind for CommonToken {
+    fn matches(token_type: &TokenType) -> bool {
+        matches!(token_type, TokenType::Common)
+    }
+}
+
+/// A [`TokenType::Consent`] token for any action.
+pub struct ConsentToken;
+impl TokenKind for ConsentToken {
+    fn matches(token_type: &TokenType) -> bool {
+        matches!(token_type, TokenType::Consent { .. })
+    }
+}
+
+/// A [`TokenType::Provisioning`] token.
+pub struct ProvisioningToken;
+impl TokenKind for ProvisioningToken {
+    fn matches(token_type: &TokenType) -> bool {
+        matches!(token_type, TokenType::Provisioning)
+    }
+}
+
 impl<S> OptionalFromRequestParts<S> for Token
 where
-    S: Send + Sync + HasKeySetCache + HasRevocationEndpoint + HasHttpClient,
+    S: Send + Sync + TokenVerificationState,
 {
     type Rejection = ErrorResponse;
 
@@ -33,7 +332,9 @@ where
         parts: &mut Parts,
         state: &S,
     ) -> Result<Option<Self>, Self::Rejection> {
-        match parts.headers.get("Authorization") {
+        let header = &state.token_validation_options().header;
+
+        match parts.headers.get(header) {
             Some(_) => <Self as FromRequestParts<S>>::from_request_parts(parts, state)
                 .await
                 .map(Some),
@@ -44,78 +345,339 @@ where
 
 impl<S> FromRequestParts<S> for Token
 where
-    S: Send + Sync + HasKeySetCache + HasRevocationEndpoint + HasHttpClient,
+    S: Send + Sync + TokenVerificationState,
 {
     type Rejection = ErrorResponse;
 
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let options = state.token_validation_options();
+
         let header = parts
             .headers
-            .get("Authorization")
+            .get(&options.header)
             .ok_or_else(ErrorResponse::unauthenticated)?
             .to_str()
             .map_err(|_| ErrorResponse::unauthenticated())?;
 
-        if !header.starts_with("bearer ") {
+        let Some(serialized) = options.serialized_token(header) else {
             return Err(ErrorResponse::unauthenticated());
-        }
+        };
 
-        let token = &header[7..];
+        verify_serialized(serialized, state).await.map(Self)
+    }
+}
 
-        let token =
-            JsonWebToken::deserialize(token).ok_or_else(|| ErrorResponse::unauthenticated())?;
+impl<S> OptionalFromRequestParts<S> for CookieToken
+where
+    S: Send + Sync + TokenVerificationState,
+{
+    type Rejection = ErrorResponse;
 
-        let cache_contains_key = {
-            let cache_lock = state.jwks_cache().cache.read().await;
-            cache_lock.contains_key(&token.header.kid)
-        };
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &S,
+    ) -> Result<Option<Self>, Self::Rejection> {
+        let cookie = &state.token_validation_options().cookie;
 
-        if !cache_contains_key {
-            state
-                .jwks_cache()
-                .refresh(state.http_client())
-                .await
-                .internal_server_error()?;
+        if cookie_values(parts, cookie).next().is_none() {
+            return Ok(None);
         }
 
-        let cache_lock = state.jwks_cache().cache.read().await;
-        let decoding_jwk = cache_lock
-            .get(&token.header.kid)
-            .ok_or_else(ErrorResponse::unauthenticated)?;
+        <Self as FromRequestParts<S>>::from_request_parts(parts, state)
+            .await
+            .map(Some)
+    }
+}
+
+impl<S> FromRequestParts<S> for CookieToken
+where
+    S: Send + Sync + TokenVerificationState,
+{
+    type Rejection = ErrorResponse;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let cookie = &state.token_validation_options().cookie;
 
-        if !decoding_jwk.verify(&token).internal_server_error()? {
+        let mut values = cookie_values(parts, cookie);
+        let value = values.next().ok_or_else(ErrorResponse::unauthenticated)?;
+        if values.any(|other| other != value) {
+            log::warn!("request has multiple different `{cookie}` cookies");
             return Err(ErrorResponse::unauthenticated());
         }
 
-        if token.claims.is_expired() {
+        let Ok(serialized) = percent_decode_str(value).decode_utf8() else {
+            log::warn!("`{cookie}` cookie is not valid percent-encoded UTF-8");
+            return Err(ErrorResponse::unauthenticated());
+        };
+
+        verify_serialized(&serialized, state).await.map(Self)
+    }
+}
+
+/// Get the values of every cookie named `name` in the `Cookie` headers of the request, without
+/// surrounding quotes.
+fn cookie_values<'a>(parts: &'a Parts, name: &'a str) -> impl Iterator<Item = &'a str> {
+    parts
+        .headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|header| header.to_str().ok())
+        .flat_map(|header| header.split(';'))
+        .filter_map(move |cookie| {
+            let (cookie_name, value) = cookie.split_once('=')?;
+            (cookie_name.trim() == name).then_some(value.trim())
+        })
+        .map(|value| {
+            value
+                .strip_prefix('"')
+                .and_then(|value| value.strip_suffix('"'))
+                .unwrap_or(value)
+        })
+}
+
+/// Fully verify a serialized token, including checking if it has been revoked.
+async fn verify_serialized<S>(serialized: &str, state: &S) -> Result<JsonWebToken, ErrorResponse>
+where
+    S: TokenVerificationState,
+{
+    let options = state.token_validation_options();
+
+    if serialized.len() > options.max_token_length {
+        log::warn!(
+            "token is {} bytes, longer than the maximum of {}",
+            serialized.len(),
+            options.max_token_length
+        );
+        return Err(ErrorResponse::unauthenticated());
+    }
+
+    if let Some(verified_token_cache) = state.verified_token_cache()
+        && let Some(token) = verified_token_cache.get(serialized).await
+    {
+        return Ok(token);
+    }
+
+    let Some((token, signing_input)) = JsonWebToken::deserialize_with_signing_input(serialized)
+    else {
+        log::warn!("token was rejected: {}", VerifyError::Malformed);
+        return Err(ErrorResponse::unauthenticated());
+    };
+
+    let jwks_caches = state.jwks_caches();
+    let issuer = token.claims.iss.as_deref();
+    let jwks_cache = jwks_caches
+        .iter()
+        .find(|jwks_cache| {
+            issuer.is_some_and(|issuer| jwks_cache.issuers.iter().any(|allowed| allowed == issuer))
+        })
+        .or_else(|| {
+            jwks_caches
+                .iter()
+                .find(|jwks_cache| jwks_cache.issuers.is_empty())
+        })
+        .ok_or_else(|| {
+            log::warn!("no JWKS is trusted for the issuer {issuer:?}");
+            ErrorResponse::unauthenticated()
+        })?;
+
+    let verified = in_span!(
+        "token.verify",
+        jwks_cache.verify_decoded(token, signing_input, options)
+    )
+    .await;
+    let token = match verified {
+        Ok(token) => token,
+        Err(error @ (VerifyError::Refresh { .. } | VerifyError::Verify { .. })) => {
+            return Err(error).reported_internal_server_error();
+        }
+        Err(error) => {
+            log::warn!("token was rejected: {error}");
             return Err(ErrorResponse::unauthenticated());
         }
+    };
 
-        let is_revoked = {
-            let endpoint = format!("{}/{}", state.revocation_endpoint(), token.claims.tid);
+    if let Some(audience) = &options.audience
+        && !token.claims.has_audience(audience)
+    {
+        return Err(ErrorResponse::unauthenticated());
+    }
+
+    if !options.subject_format.matches(&token.claims.sub) {
+        log::warn!(
+            "token subject does not have the format {:?}",
+            options.subject_format
+        );
+        return Err(ErrorResponse::unauthenticated());
+    }
 
-            let status = state
-                .http_client()
-                .get(&endpoint)
-                .send()
+    let is_revoked = in_span!("token.revocation", async {
+        if let Some(database) = state.revocation_database() {
+            database
+                .is_revoked(&token.claims.tid)
                 .await
-                .internal_server_error()?
-                .status();
-
-            match status {
-                StatusCode::NOT_FOUND => false,
-                StatusCode::OK => true,
-                status => {
-                    log::error!("received status {status} from revocation endpoint");
-                    return Err(ErrorResponse::internal_server_error());
-                }
-            }
-        };
+                .reported_internal_server_error()
+        } else {
+            is_revoked_at_endpoint(state, &token.claims.tid).await
+        }
+    })
+    .await?;
 
-        if is_revoked {
-            return Err(ErrorResponse::unauthenticated());
+    if is_revoked {
+        return Err(ErrorResponse::unauthenticated());
+    }
+
+    if let Some(verified_token_cache) = state.verified_token_cache() {
+        verified_token_cache
+            .insert(serialized.to_owned(), token.clone())
+            .await;
+    }
+
+    Ok(token)
+}
+
+/// Check the revocation endpoint for if a token has been revoked.
+#[cfg(feature = "reqwest")]
+async fn is_revoked_at_endpoint<S>(state: &S, tid: &str) -> Result<bool, ErrorResponse>
+where
+    S: TokenVerificationState,
+{
+    use http::StatusCode;
+
+    let endpoint = format!("{}/{tid}", state.revocation_endpoint());
+
+    let client = state
+        .revocation_client()
+        .unwrap_or_else(|| state.http_client());
+
+    let status = client
+        .get(&endpoint)
+        .send()
+        .await
+        .reported_internal_server_error()?
+        .status();
+
+    match status {
+        StatusCode::NOT_FOUND => Ok(false),
+        StatusCode::OK => Ok(true),
+        status => {
+            log::error!("received status {status} from revocation endpoint");
+            Err(ErrorResponse::internal_server_error())
+        }
+    }
+}
+
+/// Check the revocation endpoint for if a token has been revoked.
+#[cfg(not(feature = "reqwest"))]
+async fn is_revoked_at_endpoint<S>(state: &S, _tid: &str) -> Result<bool, ErrorResponse>
+where
+    S: TokenVerificationState,
+{
+    log::error!(
+        "cannot check the revocation endpoint `{}` without the `reqwest` feature, return a revocation database instead",
+        state.revocation_endpoint()
+    );
+    Err(ErrorResponse::internal_server_error())
+}
+
+impl<S> OptionalFromRequestParts<S> for TokenClaims
+where
+    S: Send + Sync + TokenVerificationState,
+{
+    type Rejection = ErrorResponse;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &S,
+    ) -> Result<Option<Self>, Self::Rejection> {
+        <Token as OptionalFromRequestParts<S>>::from_request_parts(parts, state)
+            .await
+            .map(|token| token.map(|Token(token)| Self(token.claims)))
+    }
+}
+
+impl<S> FromRequestParts<S> for TokenClaims
+where
+    S: Send + Sync + TokenVerificationState,
+{
+    type Rejection = ErrorResponse;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        <Token as FromRequestParts<S>>::from_request_parts(parts, state)
+            .await
+            .map(|Token(token)| Self(token.claims))
+    }
+}
+
+impl<S> OptionalFromRequestParts<S> for SubjectClaim
+where
+    S: Send + Sync + TokenVerificationState,
+{
+    type Rejection = ErrorResponse;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &S,
+    ) -> Result<Option<Self>, Self::Rejection> {
+        <Token as OptionalFromRequestParts<S>>::from_request_parts(parts, state)
+            .await
+            .map(|token| token.map(|Token(token)| Self(token.claims.sub)))
+    }
+}
+
+impl<S> FromRequestParts<S> for SubjectClaim
+where
+    S: Send + Sync + TokenVerificationState,
+{
+    type Rejection = ErrorResponse;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        <Token as FromRequestParts<S>>::from_request_parts(parts, state)
+            .await
+            .map(|Token(token)| Self(token.claims.sub))
+    }
+}
+
+impl<S, K> FromRequestParts<S> for RequireTokenType<K>
+where
+    S: Send + Sync + TokenVerificationState,
+    K: TokenKind,
+{
+    type Rejection = ErrorResponse;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Token(token) = <Token as FromRequestParts<S>>::from_request_parts(parts, state).await?;
+
+        if !K::matches(&token.claims.typ) {
+            log::warn!(
+                "token of type {:?} is not of the required type",
+                token.claims.typ
+            );
+            return Err(ErrorResponse::forbidden());
+        }
+
+        Ok(Self(token, PhantomData))
+    }
+}
+
+impl<S, A> FromRequestParts<S> for RequireConsent<A>
+where
+    S: Send + Sync + TokenVerificationState,
+    A: ConsentAction,
+{
+    type Rejection = ErrorResponse;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Token(token) = <Token as FromRequestParts<S>>::from_request_parts(parts, state).await?;
+
+        if !token.claims.typ.grants_action(A::ACTION) {
+            log::warn!(
+                "token of type {:?} does not grant the action `{}`",
+                token.claims.typ,
+                A::ACTION
+            );
+            return Err(ErrorResponse::forbidden());
         }
 
-        Ok(Self(token))
+        Ok(Self(token, PhantomData))
     }
 }
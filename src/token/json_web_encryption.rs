@@ -0,0 +1,409 @@
+//! Encrypted JSON web tokens using `ECDH-ES` key agreement and `A256GCM` content encryption.
+use core::{error::Error, fmt};
+
+use base64ct::{Base64UrlUnpadded, Encoding};
+use openssl::{
+    bn::{BigNum, BigNumContext},
+    derive::Deriver,
+    ec::{EcGroup, EcKey},
+    nid::Nid,
+    pkey::{Id, PKey, Private, Public},
+    rand::rand_bytes,
+    sha::Sha256,
+    symm::{Cipher, decrypt_aead, encrypt_aead},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::token::json_web_key::{
+    Curve, JsonWebKey, JsonWebKeyParameters,
+    verifying::{FromJwkError, public_key_from_parameters},
+};
+
+/// The length of an `A256GCM` initialization vector in bytes.
+const IV_LENGTH: usize = 12;
+
+/// The length of an `A256GCM` authentication tag in bytes.
+const TAG_LENGTH: usize = 16;
+
+/// A JSON web encryption in compact form.
+#[derive(Debug, Clone)]
+pub struct JsonWebEncryption {
+    /// The JSON web encryption protected header.
+    pub header: Header,
+    /// The base-64 encoded protected header, used as the additional authenticated data.
+    pub encoded_header: String,
+    /// The encrypted key, empty when using direct key agreement.
+    pub encrypted_key: Vec<u8>,
+    /// The initialization vector.
+    pub iv: Vec<u8>,
+    /// The encrypted content.
+    pub ciphertext: Vec<u8>,
+    /// The authentication tag.
+    pub tag: Vec<u8>,
+}
+
+impl JsonWebEncryption {
+    /// Serialize the JSON web encryption in the compact form.
+    pub fn serialize(&self) -> String {
+        let encrypted_key = Base64UrlUnpadded::encode_string(&self.encrypted_key);
+        let iv = Base64UrlUnpadded::encode_string(&self.iv);
+        let ciphertext = Base64UrlUnpadded::encode_string(&self.ciphertext);
+        let tag = Base64UrlUnpadded::encode_string(&self.tag);
+
+        format!(
+            "{}.{encrypted_key}.{iv}.{ciphertext}.{tag}",
+            self.encoded_header
+        )
+    }
+
+    /// Deserialize the JSON web encryption from the compact form.
+    ///
+    /// Initialization vectors that are not 96 bits and authentication tags that are not 128 bits
+    /// are rejected, as OpenSSL would otherwise accept a truncated tag.
+    pub fn deserialize(value: &str) -> Option<Self> {
+        let mut parts = value.split(".");
+        let encoded_header = parts.next()?;
+        let encrypted_key = parts.next()?;
+        let iv = parts.next()?;
+        let ciphertext = parts.next()?;
+        let tag = parts.next()?;
+        if parts.next().is_some() {
+            return None;
+        }
+
+        let header =
+            serde_json::from_slice(&Base64UrlUnpadded::decode_vec(encoded_header).ok()?).ok()?;
+        let encrypted_key = Base64UrlUnpadded::decode_vec(encrypted_key).ok()?;
+        let iv = Base64UrlUnpadded::decode_vec(iv).ok()?;
+        let ciphertext = Base64UrlUnpadded::decode_vec(ciphertext).ok()?;
+        let tag = Base64UrlUnpadded::decode_vec(tag).ok()?;
+        if iv.len() != IV_LENGTH || tag.len() != TAG_LENGTH {
+            return None;
+        }
+
+        Some(Self {
+            header,
+            encoded_header: encoded_header.to_string(),
+            encrypted_key,
+            iv,
+            ciphertext,
+            tag,
+        })
+    }
+}
+
+/// The JSON web encryption protected header.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Header {
+    /// The key management algorithm.
+    pub alg: KeyManagementAlgorithm,
+    /// The content encryption algorithm.
+    pub enc: ContentEncryptionAlgorithm,
+    /// The ID of the key the content was encrypted for.
+    pub kid: String,
+    /// The ephemeral public key used for key agreement.
+    pub epk: JsonWebKeyParameters,
+}
+
+impl Header {
+    /// Encode the JSON representation of the header as URL base-64.
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_vec(&self).expect("serializing the header should never fail");
+        Base64UrlUnpadded::encode_string(&json)
+    }
+}
+
+/// Key management algorithms supported by this implementation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum KeyManagementAlgorithm {
+    /// Elliptic curve Diffie-Hellman ephemeral static key agreement.
+    #[serde(rename = "ECDH-ES")]
+    EcdhEs,
+}
+
+/// Content encryption algorithms supported by this implementation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum ContentEncryptionAlgorithm {
+    /// AES GCM using a 256-bit key.
+    A256GCM,
+}
+
+/// A JSON web key used to encrypt content for a recipient.
+#[derive(Debug)]
+pub struct EncryptingJsonWebKey {
+    /// The ID of the recipient's key.
+    pub kid: String,
+    /// The recipient's public key.
+    pub key: PKey<Public>,
+}
+
+impl EncryptingJsonWebKey {
+    /// Try create an encrypting JSON web key from the recipient's JSON web key parameters.
+    pub fn try_from_parameters(
+        kid: String,
+        parameters: &JsonWebKeyParameters,
+    ) -> Result<Self, FromJwkError> {
+        let key = public_key_from_parameters(parameters)?;
+
+        Ok(Self { kid, key })
+    }
+
+    /// Encrypt some content for the recipient.
+    pub fn encrypt(
+        &self,
+        plaintext: &[u8],
+    ) -> Result<JsonWebEncryption, openssl::error::ErrorStack> {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+        let ephemeral_key = EcKey::generate(&group)?;
+
+        let epk = {
+            let mut ctx = BigNumContext::new()?;
+            let mut x = BigNum::new()?;
+            let mut y = BigNum::new()?;
+            ephemeral_key
+                .public_key()
+                .affine_coordinates(&group, &mut x, &mut y, &mut ctx)?;
+
+            JsonWebKeyParameters::EC {
+                crv: Curve::P256,
+                x: Base64UrlUnpadded::encode_string(&x.to_vec_padded(32)?),
+                y: Base64UrlUnpadded::encode_string(&y.to_vec_padded(32)?),
+            }
+        };
+
+        let ephemeral_key = PKey::from_ec_key(ephemeral_key)?;
+        let content_key = derive_content_key(&ephemeral_key, &self.key)?;
+
+        let header = Header {
+            alg: KeyManagementAlgorithm::EcdhEs,
+            enc: ContentEncryptionAlgorithm::A256GCM,
+            kid: self.kid.clone(),
+            epk,
+        };
+        let encoded_header = header.encode();
+
+        let mut iv = vec![0u8; IV_LENGTH];
+        rand_bytes(&mut iv)?;
+
+        let mut tag = vec![0u8; TAG_LENGTH];
+        let ciphertext = encrypt_aead(
+            Cipher::aes_256_gcm(),
+            &content_key,
+            Some(&iv),
+            encoded_header.as_bytes(),
+            plaintext,
+            &mut tag,
+        )?;
+
+        Ok(JsonWebEncryption {
+            header,
+            encoded_header,
+            encrypted_key: vec![],
+            iv,
+            ciphertext,
+            tag,
+        })
+    }
+}
+
+impl TryFrom<JsonWebKey> for EncryptingJsonWebKey {
+    type Error = FromJwkError;
+
+    fn try_from(jwk: JsonWebKey) -> Result<Self, Self::Error> {
+        Self::try_from_parameters(jwk.kid, &jwk.parameters)
+    }
+}
+
+/// A JSON web key used to decrypt content encrypted for it.
+#[derive(Debug)]
+pub struct DecryptingJsonWebKey {
+    /// The ID of this key.
+    pub kid: String,
+    /// The private key.
+    pub key: PKey<Private>,
+}
+
+impl DecryptingJsonWebKey {
+    /// Try create a decrypting JSON web key from a PEM encoded private key.
+    pub fn try_from_pem(kid: String, pem: &[u8]) -> Result<Self, DecryptingKeyFromPemError> {
+        let key = PKey::private_key_from_pem(pem)
+            .map_err(|source| DecryptingKeyFromPemError::PemToPrivateKey { source })?;
+
+        let id = key.id();
+        if id != Id::EC {
+            return Err(DecryptingKeyFromPemError::UnsupportedKey { id });
+        }
+
+        Ok(Self { kid, key })
+    }
+
+    /// Decrypt content that was encrypted for this key.
+    pub fn decrypt(&self, jwe: &JsonWebEncryption) -> Result<Vec<u8>, DecryptError> {
+        if jwe.header.kid != self.kid {
+            return Err(DecryptError::KidMismatch {
+                kid: jwe.header.kid.clone(),
+            });
+        }
+
+        if !jwe.encrypted_key.is_empty() {
+            return Err(DecryptError::UnexpectedEncryptedKey);
+        }
+
+        if jwe.iv.len() != IV_LENGTH || jwe.tag.len() != TAG_LENGTH {
+            return Err(DecryptError::InvalidLength);
+        }
+
+        let ephemeral_key = public_key_from_parameters(&jwe.header.epk)
+            .map_err(|source| DecryptError::InvalidEphemeralKey { source })?;
+
+        let content_key = derive_content_key(&self.key, &ephemeral_key)
+            .map_err(|source| DecryptError::DeriveKey { source })?;
+
+        decrypt_aead(
+            Cipher::aes_256_gcm(),
+            &content_key,
+            Some(&jwe.iv),
+            jwe.encoded_header.as_bytes(),
+            &jwe.ciphertext,
+            &jwe.tag,
+        )
+        .map_err(|source| DecryptError::Decrypt { source })
+    }
+}
+
+/// Derive the `A256GCM` content key using ECDH and the Concat KDF from RFC 7518 section 4.6.2.
+fn derive_content_key(
+    private_key: &PKey<Private>,
+    public_key: &PKey<Public>,
+) -> Result<[u8; 32], openssl::error::ErrorStack> {
+    let mut deriver = Deriver::new(private_key)?;
+    deriver.set_peer(public_key)?;
+    let shared_secret = deriver.derive_to_vec()?;
+
+    let algorithm_id = b"A256GCM";
+    let algorithm_id_length =
+        u32::try_from(algorithm_id.len()).expect("algorithm ID length should fit in a u32");
+
+    // A single round of SHA-256 produces the full 256-bit key.
+    let mut hasher = Sha256::new();
+    hasher.update(&1u32.to_be_bytes());
+    hasher.update(&shared_secret);
+    hasher.update(&algorithm_id_length.to_be_bytes());
+    hasher.update(algorithm_id);
+    // Empty `apu` and `apv`.
+    hasher.update(&0u32.to_be_bytes());
+    hasher.update(&0u32.to_be_bytes());
+    // The key length in bits.
+    hasher.update(&256u32.to_be_bytes());
+
+    Ok(hasher.finish())
+}
+
+/// Error variants for creating a decrypting JSON web key from a PEM file.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum DecryptingKeyFromPemError {
+    /// The PEM to private key conversion failed.
+    #[non_exhaustive]
+    PemToPrivateKey {
+        /// The source of the failure.
+        source: openssl::error::ErrorStack,
+    },
+
+    /// The private key is not an elliptic curve key.
+    #[non_exhaustive]
+    UnsupportedKey {
+        /// The ID of the private key.
+        id: Id,
+    },
+}
+impl fmt::Display for DecryptingKeyFromPemError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self {
+            Self::PemToPrivateKey { .. } => {
+                write!(f, "PEM could not be converted to a private key")
+            }
+            Self::UnsupportedKey { id, .. } => {
+                write!(f, "private key type {id:?} is not supported")
+            }
+        }
+    }
+}
+impl Error for DecryptingKeyFromPemError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self {
+            Self::PemToPrivateKey { source, .. } => Some(source),
+            Self::UnsupportedKey { .. } => None,
+        }
+    }
+}
+
+/// Error variants for decrypting a JSON web encryption.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum DecryptError {
+    /// The content was not encrypted for this key.
+    #[non_exhaustive]
+    KidMismatch {
+        /// The ID of the key the content was encrypted for.
+        kid: String,
+    },
+
+    /// An encrypted key was present, but direct key agreement does not use one.
+    #[non_exhaustive]
+    UnexpectedEncryptedKey,
+
+    /// The initialization vector is not 96 bits or the authentication tag is not 128 bits.
+    #[non_exhaustive]
+    InvalidLength,
+
+    /// The ephemeral public key is invalid.
+    #[non_exhaustive]
+    InvalidEphemeralKey {
+        /// The source of the error.
+        source: FromJwkError,
+    },
+
+    /// Deriving the content key failed.
+    #[non_exhaustive]
+    DeriveKey {
+        /// The source of the error.
+        source: openssl::error::ErrorStack,
+    },
+
+    /// Decrypting the content failed, the content may have been tampered with.
+    #[non_exhaustive]
+    Decrypt {
+        /// The source of the error.
+        source: openssl::error::ErrorStack,
+    },
+}
+impl fmt::Display for DecryptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self {
+            Self::KidMismatch { kid, .. } => write!(f, "content was encrypted for key `{kid}`"),
+            Self::UnexpectedEncryptedKey => write!(f, "unexpected encrypted key"),
+            Self::InvalidLength => {
+                write!(
+                    f,
+                    "initialization vector or authentication tag has an invalid length"
+                )
+            }
+            Self::InvalidEphemeralKey { .. } => write!(f, "ephemeral public key is invalid"),
+            Self::DeriveKey { .. } => write!(f, "could not derive the content key"),
+            Self::Decrypt { .. } => write!(f, "could not decrypt the content"),
+        }
+    }
+}
+impl Error for DecryptError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self {
+            Self::KidMismatch { .. } | Self::UnexpectedEncryptedKey | Self::InvalidLength => None,
+            Self::InvalidEphemeralKey { source, .. } => Some(source),
+            Self::DeriveKey { source, .. } => Some(source),
+            Self::Decrypt { source, .. } => Some(source),
+        }
+    }
+}
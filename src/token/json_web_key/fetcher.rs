@@ -0,0 +1,114 @@
+//! Fetching a JSON web key set with any HTTP client.
+use core::{error::Error, fmt, pin::Pin};
+
+use http::StatusCode;
+
+/// The future returned by [`JwksFetcher::fetch`].
+pub type FetchFuture<'a> = Pin<Box<dyn Future<Output = Result<Vec<u8>, FetchError>> + Send + 'a>>;
+
+/// Fetches the body of a JSON web key set, so the cache is not tied to a specific HTTP client.
+///
+/// With the `reqwest` feature, this is implemented for [`reqwest::Client`]. Implement it to use
+/// another client, e.g., `hyper` or `ureq`, or to serve a fixed key set in tests.
+pub trait JwksFetcher: fmt::Debug + Send + Sync {
+    /// Fetch the body of the JSON web key set at a URL.
+    fn fetch<'a>(&'a self, url: &'a str) -> FetchFuture<'a>;
+}
+
+#[cfg(feature = "reqwest")]
+impl JwksFetcher for reqwest::Client {
+    fn fetch<'a>(&'a self, url: &'a str) -> FetchFuture<'a> {
+        Box::pin(async move {
+            let response = self.get(url).send().await?;
+
+            let status = response.status();
+            if !status.is_success() {
+                return Err(FetchError::error_response(status));
+            }
+
+            let body = response.bytes().await?;
+            Ok(body.to_vec())
+        })
+    }
+}
+
+/// Error variants from fetching a JSON web key set.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum FetchError {
+    /// The client could not connect to the JSON web key set server.
+    #[non_exhaustive]
+    CouldNotConnect {
+        /// The source of the error.
+        source: Box<dyn Error + Send + Sync>,
+    },
+
+    /// The JSON web key set server sent back an error response.
+    #[non_exhaustive]
+    ErrorResponse {
+        /// The response code.
+        status: StatusCode,
+    },
+
+    /// The JSON web key set server sent back an invalid response.
+    #[non_exhaustive]
+    InvalidResponse {
+        /// The source of the error.
+        source: Box<dyn Error + Send + Sync>,
+    },
+}
+impl FetchError {
+    /// The client could not connect to the server.
+    pub fn could_not_connect<E: Into<Box<dyn Error + Send + Sync>>>(source: E) -> Self {
+        Self::CouldNotConnect {
+            source: source.into(),
+        }
+    }
+
+    /// The server sent back an error response.
+    pub fn error_response(status: StatusCode) -> Self {
+        Self::ErrorResponse { status }
+    }
+
+    /// The server sent back an invalid response.
+    pub fn invalid_response<E: Into<Box<dyn Error + Send + Sync>>>(source: E) -> Self {
+        Self::InvalidResponse {
+            source: source.into(),
+        }
+    }
+}
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self {
+            Self::CouldNotConnect { .. } => write!(f, "failed to connect to JWKS"),
+            Self::ErrorResponse { status, .. } => {
+                write!(f, "JWKS response has error status: {status}")
+            }
+            Self::InvalidResponse { .. } => write!(f, "invalid response from JWKS"),
+        }
+    }
+}
+impl Error for FetchError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self {
+            Self::CouldNotConnect { source, .. } => Some(source.as_ref()),
+            Self::ErrorResponse { .. } => None,
+            Self::InvalidResponse { source, .. } => Some(source.as_ref()),
+        }
+    }
+}
+#[cfg(feature = "reqwest")]
+impl From<reqwest::Error> for FetchError {
+    fn from(source: reqwest::Error) -> Self {
+        if source.is_builder()
+            || source.is_request()
+            || source.is_connect()
+            || source.is_timeout()
+            || source.is_redirect()
+        {
+            Self::could_not_connect(source)
+        } else {
+            Self::invalid_response(source)
+        }
+    }
+}
@@ -1,10 +1,81 @@
-use serde::{Deserialize, Serialize};
+use core::{error::Error, fmt};
+
+use serde::{Deserialize, Deserializer, Serialize};
 
 use crate::token::json_web_key::JsonWebKey;
 
 /// A JSON web key set.
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub struct JsonWebKeySet {
     /// The set of keys.
+    ///
+    /// Keys that this implementation cannot model, e.g., an `RSA` or `oct` key, are skipped with a
+    /// warning when deserializing, so they do not prevent the other keys from being used.
+    #[serde(deserialize_with = "deserialize_supported_keys")]
     pub keys: Vec<JsonWebKey>,
 }
+
+impl JsonWebKeySet {
+    /// Merge the keys of another set into this set, e.g., to accept the keys of a primary issuer
+    /// and a migration issuer.
+    ///
+    /// Keys with the same ID that are identical are only kept once. Keys with the same ID that
+    /// differ, e.g., in their key material, conflict, as a token's `kid` would not identify which
+    /// key to verify it with.
+    pub fn merge(self, other: Self) -> Result<Self, MergeConflict> {
+        let mut keys: Vec<JsonWebKey> = Vec::with_capacity(self.keys.len() + other.keys.len());
+
+        for key in self.keys.into_iter().chain(other.keys) {
+            match keys.iter().find(|existing| existing.kid == key.kid) {
+                Some(existing) if *existing == key => {}
+                Some(_) => return Err(MergeConflict { kid: key.kid }),
+                None => keys.push(key),
+            }
+        }
+
+        Ok(Self { keys })
+    }
+}
+
+/// Two key sets being merged have different keys with the same ID.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct MergeConflict {
+    /// The ID of the conflicting keys.
+    pub kid: String,
+}
+impl fmt::Display for MergeConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "key sets have different keys with the ID `{}`", self.kid)
+    }
+}
+impl Error for MergeConflict {}
+
+/// Deserialize the keys of a set, skipping the keys that are not supported.
+fn deserialize_supported_keys<'de, D>(deserializer: D) -> Result<Vec<JsonWebKey>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let values: Vec<serde_json::Value> = Deserialize::deserialize(deserializer)?;
+
+    let keys = values
+        .into_iter()
+        .filter_map(|value| {
+            let kid = value
+                .get("kid")
+                .and_then(serde_json::Value::as_str)
+                .unwrap_or_default()
+                .to_string();
+
+            match serde_json::from_value(value) {
+                Ok(key) => Some(key),
+                Err(error) => {
+                    log::warn!("skipping unsupported JWK `{kid}`: {error}");
+                    None
+                }
+            }
+        })
+        .collect();
+
+    Ok(keys)
+}
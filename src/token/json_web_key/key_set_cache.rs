@@ -1,70 +1,229 @@
-//! A cache for a JWKS.
+//! A cache for the keys of a JWKS.
 use core::{error::Error, fmt};
 use std::{collections::HashMap, sync::Arc};
 
-use http::StatusCode;
 use jiff::{SignedDuration, Timestamp};
-use reqwest::Client;
 use tokio::sync::RwLock;
 
-use crate::token::json_web_key::{JsonWebKeySet, VerifyingJsonWebKey, verifying};
+use crate::{
+    token::{
+        Clock, JsonWebToken, SystemClock,
+        extractor::TokenValidationOptions,
+        json_web_key::{
+            JsonWebKey, JsonWebKeySet, VerifyingJsonWebKey,
+            fetcher::{FetchError, JwksFetcher},
+            verifying,
+        },
+        json_web_token::{Header, Issuer},
+    },
+    trace::in_span,
+};
+
+/// A cache for the verifying keys of a JSON web key set.
+pub type JsonWebKeySetCache = JwkCache<VerifyingJsonWebKey>;
 
-/// A cache for a JSON web key set.
-#[derive(Clone, Debug)]
-pub struct JsonWebKeySetCache {
+/// A cache for the keys of a JSON web key set, where `K` is the key each JSON web key is converted
+/// to, e.g., [`VerifyingJsonWebKey`].
+#[derive(Debug)]
+pub struct JwkCache<K> {
     /// The URL to the JSON web key set.
     pub endpoint: String,
-    /// The cached JSON web keys.
-    pub cache: Arc<RwLock<HashMap<String, VerifyingJsonWebKey>>>,
+    /// The fetcher used to fetch the JSON web key set.
+    pub fetcher: Arc<dyn JwksFetcher>,
+    /// The cached keys.
+    pub cache: Arc<RwLock<HashMap<String, K>>>,
     /// The time the cache was last refreshed.
     pub last_refresh: Arc<RwLock<Timestamp>>,
+    /// The backoff state from failed refreshes.
+    pub backoff: Arc<RwLock<RefreshBackoff>>,
+    /// The `iss` values the keys may authenticate. If this is empty, the issuer is not checked.
+    pub issuers: Vec<String>,
+    /// The time each cached key was last present in the JSON web key set.
+    retrieved: Arc<RwLock<HashMap<String, Timestamp>>>,
+    /// The source of the current time.
+    clock: Arc<dyn Clock>,
+}
+
+impl<K> Clone for JwkCache<K> {
+    fn clone(&self) -> Self {
+        Self {
+            endpoint: self.endpoint.clone(),
+            fetcher: Arc::clone(&self.fetcher),
+            cache: Arc::clone(&self.cache),
+            last_refresh: Arc::clone(&self.last_refresh),
+            backoff: Arc::clone(&self.backoff),
+            issuers: self.issuers.clone(),
+            retrieved: Arc::clone(&self.retrieved),
+            clock: Arc::clone(&self.clock),
+        }
+    }
 }
 
-impl JsonWebKeySetCache {
-    /// Create a new cache.
-    pub fn new(jwks_url: String) -> Self {
+impl<K> JwkCache<K>
+where
+    K: TryFrom<JsonWebKey, Error = verifying::FromJwkError>,
+{
+    /// Create a new cache that fetches the JSON web key set using the given client.
+    #[cfg(feature = "reqwest")]
+    pub fn new(jwks_url: String, client: reqwest::Client) -> Self {
+        Self::from_fetcher(jwks_url, Arc::new(client))
+    }
+
+    /// Create a new cache that fetches the JSON web key set using the given fetcher.
+    pub fn from_fetcher(jwks_url: String, fetcher: Arc<dyn JwksFetcher>) -> Self {
         Self {
             endpoint: jwks_url,
+            fetcher,
             cache: Arc::new(RwLock::new(HashMap::new())),
             last_refresh: Arc::new(RwLock::new(Timestamp::UNIX_EPOCH)),
+            backoff: Arc::new(RwLock::new(RefreshBackoff::default())),
+            issuers: Vec::new(),
+            retrieved: Arc::new(RwLock::new(HashMap::new())),
+            clock: Arc::new(SystemClock),
         }
     }
 
+    /// Only allow the keys to authenticate tokens from these issuers, so a key from one issuer can
+    /// never validate a token claiming to be from another.
+    pub fn with_issuers(mut self, issuers: Vec<String>) -> Self {
+        self.issuers = issuers;
+        self
+    }
+
+    /// Returns if the keys may authenticate a token from an issuer.
+    pub fn allows_issuer(&self, issuer: Option<&str>) -> bool {
+        self.issuers.is_empty()
+            || issuer.is_some_and(|issuer| self.issuers.iter().any(|allowed| allowed == issuer))
+    }
+
+    /// Use a clock other than the system clock for refreshing the cache and checking expiry.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// The minimum time between refreshes.
+    pub const REFRESH_INTERVAL: SignedDuration = SignedDuration::from_hours(4);
+
     /// Refresh the cache.
-    pub async fn refresh(&self, client: &Client) -> Result<(), RefreshCacheError> {
-        let now = Timestamp::now();
+    ///
+    /// After a failed refresh, further attempts are rejected with exponential backoff until a
+    /// refresh succeeds. Keys that are already cached remain usable while backing off.
+    pub async fn refresh(&self) -> Result<(), RefreshCacheError> {
+        let now = self.clock.now();
 
         let last_refresh = self.last_refresh.read().await;
-        if last_refresh.duration_until(now) < SignedDuration::from_hours(4) {
+        if last_refresh.duration_until(now) < Self::REFRESH_INTERVAL {
             return Ok(());
         }
         drop(last_refresh);
 
-        let jwks: JsonWebKeySet = client
-            .get(&self.endpoint)
-            .send()
-            .await?
-            .error_for_status()?
-            .json()
-            .await?;
+        let backoff = *self.backoff.read().await;
+        if backoff.is_backing_off(now) {
+            return Err(RefreshCacheError::BackingOff {
+                retry_after: backoff.retry_after,
+            });
+        }
+
+        let result = self.fetch(now).await;
+
+        let mut backoff = self.backoff.write().await;
+        match &result {
+            Ok(()) => *backoff = RefreshBackoff::default(),
+            Err(_) => backoff.record_failure(now),
+        }
+
+        result
+    }
+
+    /// Fetch the JSON web key set now, regardless of when the cache was last refreshed or any
+    /// backoff.
+    ///
+    /// Call this during application startup, before binding the server, so the first authenticated
+    /// request does not wait on the fetch. The error is returned so startup can decide whether to
+    /// fail fast or continue and let requests refresh the cache later. A failure does not start a
+    /// backoff.
+    pub async fn prime(&self) -> Result<(), RefreshCacheError> {
+        self.fetch(self.clock.now()).await?;
+
+        *self.backoff.write().await = RefreshBackoff::default();
+
+        Ok(())
+    }
+
+    /// Stop trusting a key immediately, instead of when it has been missing from the key set for
+    /// 24 hours.
+    ///
+    /// Call this in response to a key being revoked, e.g., a notification that it was compromised.
+    /// The next token for the key refreshes the cache, so the key must already be removed from the
+    /// key set, otherwise it is cached again.
+    pub async fn evict(&self, kid: &str) {
+        self.cache.write().await.remove(kid);
+        self.retrieved.write().await.remove(kid);
+        *self.last_refresh.write().await = Timestamp::UNIX_EPOCH;
+    }
+
+    /// Get the current backoff state.
+    pub async fn backoff(&self) -> RefreshBackoff {
+        *self.backoff.read().await
+    }
+
+    /// Get the earliest time [`Self::refresh`] will fetch the key set again, which is
+    /// [`Self::REFRESH_INTERVAL`] after the last refresh, or later while backing off.
+    pub async fn next_refresh_allowed_at(&self) -> Timestamp {
+        let last_refresh = *self.last_refresh.read().await;
+        let next_refresh = last_refresh
+            .checked_add(Self::REFRESH_INTERVAL)
+            .unwrap_or(Timestamp::MAX);
+
+        let backoff = self.backoff().await;
+        if backoff.failures > 0 {
+            next_refresh.max(backoff.retry_after)
+        } else {
+            next_refresh
+        }
+    }
+
+    /// Get the time until [`Self::refresh`] will fetch the key set again, or zero if it would
+    /// fetch now.
+    pub async fn time_until_refresh(&self) -> SignedDuration {
+        let next_refresh = self.next_refresh_allowed_at().await;
+
+        self.clock
+            .now()
+            .duration_until(next_refresh)
+            .max(SignedDuration::ZERO)
+    }
+
+    async fn fetch(&self, now: Timestamp) -> Result<(), RefreshCacheError> {
+        let body = self
+            .fetcher
+            .fetch(&self.endpoint)
+            .await
+            .map_err(|source| RefreshCacheError::Fetch { source })?;
+        let jwks: JsonWebKeySet = serde_json::from_slice(&body)
+            .map_err(|source| RefreshCacheError::InvalidJwks { source })?;
 
         let mut cache = self.cache.write().await;
+        let mut retrieved = self.retrieved.write().await;
 
         for jwk in jwks.keys {
             let kid = jwk.kid.clone();
-            let decoding_jwk = VerifyingJsonWebKey::try_from(jwk).map_err(|source| {
-                RefreshCacheError::InvalidJwk {
-                    kid: kid.clone(),
-                    source,
+            // Skip unusable keys, so they do not prevent the other keys from being used.
+            let key = match K::try_from(jwk) {
+                Ok(key) => key,
+                Err(error) => {
+                    log::warn!("skipping invalid JWK `{kid}`: {error}");
+                    continue;
                 }
-            })?;
-            cache.insert(kid, decoding_jwk);
+            };
+            cache.insert(kid.clone(), key);
+            retrieved.insert(kid, now);
         }
 
-        cache.retain(|_, key| {
-            let elapsed = key.retrieved.duration_until(now);
-            elapsed < SignedDuration::from_hours(24)
-        });
+        retrieved
+            .retain(|_, retrieved| retrieved.duration_until(now) < SignedDuration::from_hours(24));
+        cache.retain(|kid, _| retrieved.contains_key(kid));
 
         let mut last_refresh = self.last_refresh.write().await;
         *last_refresh = now;
@@ -73,76 +232,307 @@ impl JsonWebKeySetCache {
     }
 }
 
-/// Error variants from refreshing the cache.
+impl JwkCache<VerifyingJsonWebKey> {
+    /// Parse a compact serialized token, verify its signature with the key for its `kid`, and
+    /// check it has not expired.
+    ///
+    /// The cache is refreshed if it does not contain the token's key. Tokens with any critical
+    /// header parameters are rejected.
+    pub async fn verify_compact(&self, serialized: &str) -> Result<JsonWebToken, VerifyError> {
+        self.verify_compact_with(serialized, &TokenValidationOptions::DEFAULT)
+            .await
+    }
+
+    /// [`Self::verify_compact`] using the leeway, understood critical header parameters, `iat`
+    /// check, and accepted signature encodings from the options.
+    pub async fn verify_compact_with(
+        &self,
+        serialized: &str,
+        options: &TokenValidationOptions,
+    ) -> Result<JsonWebToken, VerifyError> {
+        let (token, signing_input) = JsonWebToken::deserialize_with_signing_input(serialized)
+            .ok_or(VerifyError::Malformed)?;
+
+        self.verify_decoded(token, signing_input, options).await
+    }
+
+    /// Verify a token decoded from a compact serialization, with the signature checked over the
+    /// header and claims exactly as they were serialized.
+    pub(crate) async fn verify_decoded(
+        &self,
+        token: JsonWebToken,
+        signing_input: &str,
+        options: &TokenValidationOptions,
+    ) -> Result<JsonWebToken, VerifyError> {
+        if !self.allows_issuer(token.claims.iss.as_deref()) {
+            return Err(VerifyError::UntrustedIssuer {
+                iss: token.claims.iss,
+            });
+        }
+
+        if let Some(parameter) = token.header.unknown_critical(&options.understood_critical) {
+            return Err(VerifyError::UnknownCritical {
+                parameter: parameter.to_string(),
+            });
+        }
+
+        let cache_contains_key = find_key(&*self.cache.read().await, &token.header).is_some();
+        if !cache_contains_key {
+            in_span!("token.jwks_refresh", self.refresh())
+                .await
+                .map_err(|source| VerifyError::Refresh { source })?;
+        }
+
+        {
+            let cache = self.cache.read().await;
+            let verifying_jwk =
+                find_key(&cache, &token.header).ok_or_else(|| VerifyError::UnknownKid {
+                    kid: token.header.kid.clone(),
+                })?;
+
+            let is_valid = verifying_jwk
+                .verify_signing_input(&token, signing_input, options.accepts_der_signatures)
+                .map_err(|source| VerifyError::Verify { source })?;
+            if !is_valid {
+                return Err(VerifyError::BadSignature);
+            }
+        }
+
+        if token.claims.is_expired_at(self.clock.now(), options.leeway) {
+            return Err(VerifyError::Expired {
+                exp: token.claims.exp,
+            });
+        }
+
+        if options.rejects_future_iat
+            && token
+                .claims
+                .is_issued_in_future_at(self.clock.now(), options.leeway)
+        {
+            return Err(VerifyError::IssuedInFuture {
+                iat: token.claims.iat,
+            });
+        }
+
+        Ok(token)
+    }
+}
+
+/// Find the key a token header references, by its `kid`, or else by its `x5t#S256` or `x5t`
+/// certificate thumbprint.
+///
+/// A thumbprint matches a key with the same thumbprint, or a key whose `kid` is the thumbprint, as
+/// some key sets use the thumbprint as the key ID.
+fn find_key<'a>(
+    cache: &'a HashMap<String, VerifyingJsonWebKey>,
+    header: &Header,
+) -> Option<&'a VerifyingJsonWebKey> {
+    if let Some(key) = cache.get(&header.kid) {
+        return Some(key);
+    }
+
+    let find_by_thumbprint =
+        |thumbprint: &String, key_thumbprint: fn(&JsonWebKey) -> &Option<String>| {
+            cache.get(thumbprint).or_else(|| {
+                cache
+                    .values()
+                    .find(|key| key_thumbprint(&key.jwk).as_ref() == Some(thumbprint))
+            })
+        };
+
+    header
+        .x5t_s256
+        .as_ref()
+        .and_then(|thumbprint| find_by_thumbprint(thumbprint, |jwk| &jwk.x5t_s256))
+        .or_else(|| {
+            header
+                .x5t
+                .as_ref()
+                .and_then(|thumbprint| find_by_thumbprint(thumbprint, |jwk| &jwk.x5t))
+        })
+}
+
+/// Error variants from verifying a compact serialized token.
 #[derive(Debug)]
 #[non_exhaustive]
-pub enum RefreshCacheError {
-    /// The client could not connect to the JSON web key set server.
+pub enum VerifyError {
+    /// The token is not a valid compact serialized JSON web token.
     #[non_exhaustive]
-    CouldNotConnect {
-        /// The source of the error.
-        source: reqwest::Error,
+    Malformed,
+
+    /// The key set is not trusted to authenticate tokens from the token's issuer.
+    #[non_exhaustive]
+    UntrustedIssuer {
+        /// The token's issuer.
+        iss: Option<Issuer>,
+    },
+
+    /// The token has a critical header parameter that is not understood.
+    #[non_exhaustive]
+    UnknownCritical {
+        /// The name of the header parameter.
+        parameter: String,
+    },
+
+    /// The key set has no key with the token's ID.
+    #[non_exhaustive]
+    UnknownKid {
+        /// The token's key ID.
+        kid: String,
     },
 
-    /// The JSON web key set sent back and invalid response.
+    /// Refreshing the key set failed.
     #[non_exhaustive]
-    InvalidResponse {
+    Refresh {
         /// The source of the error.
-        source: reqwest::Error,
+        source: RefreshCacheError,
     },
 
-    /// The client request was invalid.
+    /// Verifying the signature failed.
     #[non_exhaustive]
-    InvalidRequest {
+    Verify {
         /// The source of the error.
-        source: reqwest::Error,
+        source: openssl::error::ErrorStack,
+    },
+
+    /// The signature is not valid for the token.
+    #[non_exhaustive]
+    BadSignature,
+
+    /// The token has expired.
+    #[non_exhaustive]
+    Expired {
+        /// When the token expired.
+        exp: Timestamp,
     },
 
-    /// The JSON web key set sent back an error response.
+    /// The token claims to have been issued in the future.
     #[non_exhaustive]
-    ErrorResponse {
-        /// The response code.
-        status: StatusCode,
+    IssuedInFuture {
+        /// When the token claims to have been issued.
+        iat: Timestamp,
+    },
+}
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self {
+            Self::Malformed { .. } => write!(f, "token is malformed"),
+            Self::UntrustedIssuer { iss, .. } => {
+                write!(f, "JWKS is not trusted for the issuer {iss:?}")
+            }
+            Self::UnknownCritical { parameter, .. } => {
+                write!(
+                    f,
+                    "critical header parameter `{parameter}` is not understood"
+                )
+            }
+            Self::UnknownKid { kid, .. } => write!(f, "no JWK has the ID `{kid}`"),
+            Self::Refresh { .. } => write!(f, "failed to refresh the JWKS"),
+            Self::Verify { .. } => write!(f, "failed to verify the token signature"),
+            Self::BadSignature { .. } => write!(f, "token signature is invalid"),
+            Self::Expired { exp, .. } => write!(f, "token expired at {exp}"),
+            Self::IssuedInFuture { iat, .. } => {
+                write!(f, "token claims to have been issued in the future at {iat}")
+            }
+        }
+    }
+}
+impl Error for VerifyError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self {
+            Self::Refresh { source, .. } => Some(source),
+            Self::Verify { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// The backoff state of the cache after failed refreshes.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RefreshBackoff {
+    /// The number of consecutive failed refreshes.
+    pub failures: u32,
+    /// The earliest time the next refresh may be attempted.
+    pub retry_after: Timestamp,
+}
+
+impl RefreshBackoff {
+    /// The delay after the first failure.
+    pub const INITIAL_DELAY: SignedDuration = SignedDuration::from_secs(1);
+    /// The maximum delay between attempts.
+    pub const MAX_DELAY: SignedDuration = SignedDuration::from_mins(5);
+
+    /// Returns if refreshes should not be attempted at the given time.
+    pub fn is_backing_off(&self, now: Timestamp) -> bool {
+        self.failures > 0 && now < self.retry_after
+    }
+
+    /// Record a failed refresh, scheduling the next attempt with exponential backoff and jitter.
+    fn record_failure(&mut self, now: Timestamp) {
+        self.failures = self.failures.saturating_add(1);
+
+        let exponent = self.failures.saturating_sub(1).min(16);
+        let delay = Self::INITIAL_DELAY
+            .saturating_mul(1 << exponent)
+            .min(Self::MAX_DELAY);
+
+        // Half of the delay is fixed, the other half is random to spread out retries.
+        let half_delay = delay / 2;
+        let mut random = [0u8; 4];
+        let jitter = match openssl::rand::rand_bytes(&mut random) {
+            Ok(()) => {
+                let fraction = f64::from(u32::from_le_bytes(random)) / f64::from(u32::MAX);
+                half_delay.mul_f64(fraction)
+            }
+            Err(_) => SignedDuration::ZERO,
+        };
+
+        self.retry_after = now + half_delay + jitter;
+    }
+}
+
+/// Error variants from refreshing the cache.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum RefreshCacheError {
+    /// The JSON web key set could not be fetched.
+    #[non_exhaustive]
+    Fetch {
         /// The source of the error.
-        source: reqwest::Error,
+        source: FetchError,
     },
 
-    /// A JSON web key in the JSON web key set is invalid.
+    /// The JSON web key set is not valid JSON for a key set.
     #[non_exhaustive]
-    InvalidJwk {
-        /// The JSON web key's ID.
-        kid: String,
+    InvalidJwks {
         /// The source of the error.
-        source: verifying::FromJwkError,
+        source: serde_json::Error,
+    },
+
+    /// A previous refresh failed and the next attempt is being delayed.
+    #[non_exhaustive]
+    BackingOff {
+        /// The earliest time the next refresh may be attempted.
+        retry_after: Timestamp,
     },
 }
-impl Error for RefreshCacheError {}
+impl Error for RefreshCacheError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self {
+            Self::Fetch { source, .. } => Some(source),
+            Self::InvalidJwks { source, .. } => Some(source),
+            Self::BackingOff { .. } => None,
+        }
+    }
+}
 impl fmt::Display for RefreshCacheError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::CouldNotConnect { .. } => write!(f, "failed to connect to JWKS"),
-            Self::InvalidResponse { .. } => write!(f, "invalid response from JWKS"),
-            Self::InvalidRequest { .. } => write!(f, "invalid request to JWKS"),
-            Self::ErrorResponse { status, .. } => {
-                write!(f, "JWKS response has error status: {status}")
+            Self::Fetch { .. } => write!(f, "failed to fetch the JWKS"),
+            Self::InvalidJwks { .. } => write!(f, "JWKS is invalid"),
+            Self::BackingOff { retry_after, .. } => {
+                write!(f, "JWKS refresh is backing off until {retry_after}")
             }
-            Self::InvalidJwk { kid, .. } => write!(f, "JWK `{kid}` is invalid"),
-        }
-    }
-}
-impl From<reqwest::Error> for RefreshCacheError {
-    fn from(source: reqwest::Error) -> Self {
-        if source.is_body() || source.is_decode() {
-            Self::InvalidResponse { source }
-        } else if source.is_builder() || source.is_request() {
-            Self::InvalidRequest { source }
-        } else if source.is_connect() || source.is_timeout() || source.is_redirect() {
-            Self::CouldNotConnect { source }
-        } else if source.is_status() {
-            let status = source.status().unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
-            Self::ErrorResponse { status, source }
-        } else {
-            Self::InvalidResponse { source }
         }
     }
 }
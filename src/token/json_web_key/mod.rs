@@ -1,21 +1,26 @@
 //! A JSON web key used to verify signatures.
+pub mod fetcher;
 mod key_set;
 pub mod key_set_cache;
+#[cfg(feature = "ring")]
+mod ring_backend;
 pub mod signing;
 pub mod verifying;
 
-pub use key_set::JsonWebKeySet;
-pub use key_set_cache::JsonWebKeySetCache;
+pub use fetcher::{FetchError, JwksFetcher};
+pub use key_set::{JsonWebKeySet, MergeConflict};
+pub use key_set_cache::{JsonWebKeySetCache, JwkCache};
 pub use signing::SigningJsonWebKey;
 pub use verifying::VerifyingJsonWebKey;
 
+use openssl::{bn::BigNum, ecdsa::EcdsaSig, nid::Nid};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::token::Algorithm;
 
 /// A JSON web key used to verify signatures.
-#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
 pub struct JsonWebKey {
     /// The ID of this key.
     pub kid: String,
@@ -27,10 +32,16 @@ pub struct JsonWebKey {
     /// The parameters that make up the public key.
     #[serde(flatten)]
     pub parameters: JsonWebKeyParameters,
+    /// The URL base-64 SHA-1 thumbprint of the key's X.509 certificate.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub x5t: Option<String>,
+    /// The URL base-64 SHA-256 thumbprint of the key's X.509 certificate.
+    #[serde(rename = "x5t#S256", default, skip_serializing_if = "Option::is_none")]
+    pub x5t_s256: Option<String>,
 }
 
 /// The parameters that make up the key.
-#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "kty")]
 #[non_exhaustive]
 pub enum JsonWebKeyParameters {
@@ -43,13 +54,76 @@ pub enum JsonWebKeyParameters {
         /// The y coordinate.
         y: String,
     },
+
+    /// The octet key pair parameters, for Edwards curve keys.
+    OKP {
+        /// The curve type.
+        crv: Curve,
+        /// The public key.
+        x: String,
+    },
+}
+impl JsonWebKeyParameters {
+    /// The curve of the key.
+    pub fn curve(&self) -> Curve {
+        match self {
+            Self::EC { crv, .. } | Self::OKP { crv, .. } => *crv,
+        }
+    }
 }
 
 /// The curves supported by this implementation.
-#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
 #[non_exhaustive]
 pub enum Curve {
     /// The Prime 256 curve.
     #[serde(rename = "P-256")]
     P256,
+    /// The Prime 384 curve.
+    #[serde(rename = "P-384")]
+    P384,
+    /// The Ed25519 Edwards curve.
+    Ed25519,
+}
+impl Curve {
+    /// The OpenSSL identifier of the elliptic curve group, or `None` for Edwards curves, which
+    /// are not elliptic curve groups.
+    pub fn nid(&self) -> Option<Nid> {
+        match self {
+            Self::P256 => Some(Nid::X9_62_PRIME256V1),
+            Self::P384 => Some(Nid::SECP384R1),
+            Self::Ed25519 => None,
+        }
+    }
+
+    /// The size of a coordinate on the curve in bytes, or of the public key for Edwards curves.
+    pub fn coordinate_size(&self) -> i32 {
+        match self {
+            Self::P256 | Self::Ed25519 => 32,
+            Self::P384 => 48,
+        }
+    }
+}
+
+/// Convert a DER encoded ECDSA signature to the fixed-size `r || s` encoding used by JSON web
+/// signatures.
+pub(crate) fn der_to_fixed_signature(
+    der: &[u8],
+    crv: Curve,
+) -> Result<Vec<u8>, openssl::error::ErrorStack> {
+    let signature = EcdsaSig::from_der(der)?;
+
+    let mut fixed = signature.r().to_vec_padded(crv.coordinate_size())?;
+    fixed.extend(signature.s().to_vec_padded(crv.coordinate_size())?);
+
+    Ok(fixed)
+}
+
+/// Convert a fixed-size `r || s` ECDSA signature to the DER encoding used by OpenSSL.
+pub(crate) fn fixed_to_der_signature(fixed: &[u8]) -> Result<Vec<u8>, openssl::error::ErrorStack> {
+    let (r, s) = fixed.split_at(fixed.len() / 2);
+    let signature =
+        EcdsaSig::from_private_components(BigNum::from_slice(r)?, BigNum::from_slice(s)?)?;
+
+    signature.to_der()
 }
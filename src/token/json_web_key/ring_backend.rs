@@ -0,0 +1,69 @@
+//! ES256 signing and verification with `ring`, used instead of OpenSSL with the `ring` feature.
+//!
+//! Keys are still parsed and held as OpenSSL keys, so OpenSSL remains a dependency.
+use openssl::{
+    bn::BigNumContext,
+    ec::PointConversionForm,
+    error::ErrorStack,
+    pkey::{PKey, Private},
+};
+use ring::{
+    rand::SystemRandom,
+    signature::{
+        ECDSA_P256_SHA256_FIXED, ECDSA_P256_SHA256_FIXED_SIGNING, EcdsaKeyPair, UnparsedPublicKey,
+    },
+};
+use zeroize::Zeroizing;
+
+/// Verify a fixed-size `r || s` ES256 signature of `signing_input` with the P-256 public key at `x`
+/// and `y`.
+pub(crate) fn verify_es256(x: &[u8], y: &[u8], signing_input: &[u8], signature: &[u8]) -> bool {
+    let mut public_key = Vec::with_capacity(1 + x.len() + y.len());
+    public_key.push(0x04);
+    public_key.extend_from_slice(x);
+    public_key.extend_from_slice(y);
+
+    UnparsedPublicKey::new(&ECDSA_P256_SHA256_FIXED, public_key)
+        .verify(signing_input, signature)
+        .is_ok()
+}
+
+/// Sign `signing_input` with a P-256 private key, producing a fixed-size `r || s` ES256 signature.
+///
+/// The key is converted for `ring` on every signature, so a replaced key is always used. Returns
+/// `None` if `ring` rejects the key or fails to sign, so the caller can sign with OpenSSL instead.
+pub(crate) fn sign_es256(
+    key: &PKey<Private>,
+    signing_input: &[u8],
+) -> Result<Option<Vec<u8>>, ErrorStack> {
+    let ec_key = key.ec_key()?;
+    let private_key = Zeroizing::new(ec_key.private_key().to_vec_padded(32)?);
+    let mut context = BigNumContext::new()?;
+    let public_key = ec_key.public_key().to_bytes(
+        ec_key.group(),
+        PointConversionForm::UNCOMPRESSED,
+        &mut context,
+    )?;
+
+    let random = SystemRandom::new();
+    let key_pair = match EcdsaKeyPair::from_private_key_and_public_key(
+        &ECDSA_P256_SHA256_FIXED_SIGNING,
+        &private_key,
+        &public_key,
+        &random,
+    ) {
+        Ok(key_pair) => key_pair,
+        Err(error) => {
+            log::warn!("ring rejected the signing key, signing with OpenSSL: {error}");
+            return Ok(None);
+        }
+    };
+
+    match key_pair.sign(&random, signing_input) {
+        Ok(signature) => Ok(Some(signature.as_ref().to_vec())),
+        Err(error) => {
+            log::warn!("ring failed signing, signing with OpenSSL: {error}");
+            Ok(None)
+        }
+    }
+}
@@ -1,18 +1,23 @@
 //! A JSON web key used to sign a JSON web token.
 use core::{error::Error, fmt, time::Duration};
 
+use base64ct::{Base64UrlUnpadded, Encoding};
 use jiff::Timestamp;
 use openssl::{
+    bn::{BigNum, BigNumContext},
+    ec::{EcGroup, EcKey},
     hash::MessageDigest,
     pkey::{Id, PKey, Private},
     sign::Signer,
 };
 use uuid::Uuid;
 
+#[cfg(feature = "ring")]
+use crate::token::json_web_key::ring_backend;
 use crate::token::{
-    Algorithm, JsonWebKey, JsonWebToken, VerifyingJsonWebKey,
-    json_web_key::{JsonWebKeyParameters, verifying},
-    json_web_token::{Claims, Header, TokenType},
+    Algorithm, Clock, JsonWebKey, JsonWebToken, SystemClock, VerifyingJsonWebKey,
+    json_web_key::{Curve, JsonWebKeyParameters, der_to_fixed_signature, verifying},
+    json_web_token::{Claims, Header, Subject, TokenType},
 };
 
 /// A JSON web key used to sign a JSON web token.
@@ -25,33 +30,111 @@ pub struct SigningJsonWebKey {
 }
 
 impl SigningJsonWebKey {
+    /// Generate a new private key for an algorithm and build its JSON web key.
+    pub fn generate(kid: String, alg: Algorithm) -> Result<Self, GenerateError> {
+        let crv = alg
+            .curve()
+            .ok_or(GenerateError::UnsupportedAlgorithm { alg: alg.clone() })?;
+
+        if crv == Curve::Ed25519 {
+            return Self::generate_ed25519(kid);
+        }
+        let coordinate_size = crv.coordinate_size();
+
+        let nid = crv
+            .nid()
+            .ok_or(GenerateError::UnsupportedAlgorithm { alg: alg.clone() })?;
+        let group =
+            EcGroup::from_curve_name(nid).map_err(|source| GenerateError::GetEcGroup { source })?;
+        let ec_key =
+            EcKey::generate(&group).map_err(|source| GenerateError::GenerateKey { source })?;
+
+        let (x, y) = (|| {
+            let mut context = BigNumContext::new()?;
+            let mut x = BigNum::new()?;
+            let mut y = BigNum::new()?;
+            ec_key
+                .public_key()
+                .affine_coordinates(&group, &mut x, &mut y, &mut context)?;
+
+            Ok((
+                x.to_vec_padded(coordinate_size)?,
+                y.to_vec_padded(coordinate_size)?,
+            ))
+        })()
+        .map_err(|source| GenerateError::GetCoordinates { source })?;
+
+        let key =
+            PKey::from_ec_key(ec_key).map_err(|source| GenerateError::CreatePKey { source })?;
+
+        let jwk = JsonWebKey {
+            kid,
+            alg,
+            usage: "sig".to_string(),
+            parameters: JsonWebKeyParameters::EC {
+                crv,
+                x: Base64UrlUnpadded::encode_string(&x),
+                y: Base64UrlUnpadded::encode_string(&y),
+            },
+            x5t: None,
+            x5t_s256: None,
+        };
+
+        Ok(Self { jwk, key })
+    }
+
+    /// Generate a new Ed25519 private key and build its `EdDSA` JSON web key.
+    fn generate_ed25519(kid: String) -> Result<Self, GenerateError> {
+        let key =
+            PKey::generate_ed25519().map_err(|source| GenerateError::GenerateKey { source })?;
+        let x = key
+            .raw_public_key()
+            .map_err(|source| GenerateError::GetCoordinates { source })?;
+
+        let jwk = JsonWebKey {
+            kid,
+            alg: Algorithm::EdDSA,
+            usage: "sig".to_string(),
+            parameters: JsonWebKeyParameters::OKP {
+                crv: Curve::Ed25519,
+                x: Base64UrlUnpadded::encode_string(&x),
+            },
+            x5t: None,
+            x5t_s256: None,
+        };
+
+        Ok(Self { jwk, key })
+    }
+
+    /// Encode the private key as a PKCS#8 PEM that can be loaded by [`Self::try_from_pem`].
+    pub fn to_pem(&self) -> Result<Vec<u8>, openssl::error::ErrorStack> {
+        self.key.private_key_to_pem_pkcs8()
+    }
+
     /// Try create an encoding JSON web key from a JSON web key and a PEM encoded private key.
     pub fn try_from_pem(jwk: JsonWebKey, pem: &[u8]) -> Result<Self, FromPemError> {
         let private_key = PKey::private_key_from_pem(pem)
             .map_err(|source| FromPemError::PemToPrivateKey { source })?;
 
         // Validate private key for this JSON web key
-        match jwk.parameters {
-            JsonWebKeyParameters::EC { .. } => {
-                let id = private_key.id();
-                if id != Id::EC {
-                    return Err(FromPemError::PemJwkMismatch {
-                        kind: MismatchKind::Id {
-                            expected: Id::EC,
-                            real: id,
-                        },
-                    });
-                }
-
-                let decoding_jwk = VerifyingJsonWebKey::try_from(jwk.clone())
-                    .map_err(|source| FromPemError::InvalidJwk { source })?;
-
-                if !private_key.public_eq(&decoding_jwk.key) {
-                    return Err(FromPemError::PemJwkMismatch {
-                        kind: MismatchKind::PublicKey,
-                    });
-                }
-            }
+        let expected = match jwk.parameters {
+            JsonWebKeyParameters::EC { .. } => Id::EC,
+            JsonWebKeyParameters::OKP { .. } => Id::ED25519,
+        };
+        let id = private_key.id();
+        if id != expected {
+            return Err(FromPemError::PemJwkMismatch {
+                kind: MismatchKind::Id { expected, real: id },
+            });
+        }
+
+        let decoding_jwk = VerifyingJsonWebKey::try_from(jwk.clone())
+            .map_err(|source| FromPemError::InvalidJwk { source })?;
+
+        if !private_key.public_eq(&decoding_jwk.key) {
+            return Err(FromPemError::PemJwkMismatch {
+                kind: MismatchKind::PublicKey,
+            });
         }
 
         Ok(Self {
@@ -66,44 +149,172 @@ impl SigningJsonWebKey {
         subject: String,
         token_type: TokenType,
     ) -> Result<JsonWebToken, openssl::error::ErrorStack> {
-        let exp = match token_type {
-            TokenType::Common => Timestamp::now() + Duration::from_secs(60 * 60 * 24 * 30),
-            TokenType::Consent { .. } => Timestamp::now() + Duration::from_secs(60 * 5),
-            TokenType::Provisioning => Timestamp::now() + Duration::from_secs(60 * 60 * 4),
-        };
+        self.issue_with_clock(subject, token_type, &SystemClock)
+    }
 
-        let header = Header {
-            alg: self.jwk.alg.clone(),
-            typ: "JWT".to_string(),
-            kid: self.jwk.kid.clone(),
-        };
-        let claims = Claims {
-            tid: Uuid::new_v4().to_string(),
-            exp,
-            iat: Timestamp::now(),
-            sub: subject,
-            typ: token_type,
-        };
+    /// Issue a new token of the given type for a subject, taking the issue time from a clock.
+    ///
+    /// With the `ring` feature, ES256 tokens are signed with `ring` instead of OpenSSL.
+    pub fn issue_with_clock(
+        &self,
+        subject: String,
+        token_type: TokenType,
+        clock: &dyn Clock,
+    ) -> Result<JsonWebToken, openssl::error::ErrorStack> {
+        let mut token = unsigned_token(
+            self.jwk.alg.clone(),
+            self.jwk.kid.clone(),
+            subject,
+            token_type,
+            clock,
+        );
 
-        let mut signer = match self.jwk.alg {
-            Algorithm::ES256 => Signer::new(MessageDigest::sha256(), &self.key)?,
+        let crv = match self.jwk.parameters {
+            JsonWebKeyParameters::EC { crv, .. } => crv,
+            // EdDSA signs the message itself, so there is no digest.
+            JsonWebKeyParameters::OKP { .. } => {
+                token.signature = Signer::new_without_digest(&self.key)?
+                    .sign_oneshot_to_vec(token.signing_input().as_bytes())?;
+                return Ok(token);
+            }
         };
 
-        let contents = format!("{}.{}", header.encode(), claims.encode());
+        #[cfg(feature = "ring")]
+        if crv == Curve::P256
+            && let Some(signature) =
+                ring_backend::sign_es256(&self.key, token.signing_input().as_bytes())?
+        {
+            token.signature = signature;
+            return Ok(token);
+        }
 
-        let mut signature_buffer = vec![0u8; signer.len()?];
-        let signature_size = signer.sign_oneshot(&mut signature_buffer, contents.as_bytes())?;
+        let mut signer = Signer::new(MessageDigest::sha256(), &self.key)?;
 
-        let token = JsonWebToken {
-            header,
-            claims,
-            signature: signature_buffer[..signature_size].to_vec(),
-        };
+        let mut signature_buffer = vec![0u8; signer.len()?];
+        let signature_size =
+            signer.sign_oneshot(&mut signature_buffer, token.signing_input().as_bytes())?;
+        token.signature = der_to_fixed_signature(&signature_buffer[..signature_size], crv)?;
 
         Ok(token)
     }
 }
 
+/// Create a new unsigned token of the given type for a subject, taking the issue time from a
+/// clock.
+pub(crate) fn unsigned_token(
+    alg: Algorithm,
+    kid: String,
+    subject: String,
+    token_type: TokenType,
+    clock: &dyn Clock,
+) -> JsonWebToken {
+    // Tokens carry whole seconds, so truncate to keep the issued token equal to its encoding.
+    let now = clock.now();
+    let now = Timestamp::from_second(now.as_second()).unwrap_or(now);
+
+    let exp = match token_type {
+        TokenType::Common => now + Duration::from_secs(60 * 60 * 24 * 30),
+        TokenType::Consent { .. } => now + Duration::from_secs(60 * 5),
+        TokenType::Provisioning => now + Duration::from_secs(60 * 60 * 4),
+    };
+
+    let header = Header {
+        alg,
+        typ: "JWT".to_string(),
+        kid,
+        x5t: None,
+        x5t_s256: None,
+        crit: Vec::new(),
+    };
+    let claims = Claims {
+        tid: Uuid::new_v4().to_string(),
+        exp,
+        iat: now,
+        iss: None,
+        sub: Subject(subject),
+        aud: Vec::new(),
+        typ: token_type,
+    };
+
+    JsonWebToken {
+        header,
+        claims,
+        signature: Vec::new(),
+    }
+}
+
+/// Error variants for generating a signing JSON web key.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum GenerateError {
+    /// The algorithm does not use elliptic curve or Edwards curve keys.
+    #[non_exhaustive]
+    UnsupportedAlgorithm {
+        /// The algorithm.
+        alg: Algorithm,
+    },
+
+    /// Getting the elliptic curve group failed.
+    #[non_exhaustive]
+    GetEcGroup {
+        /// The source of the error.
+        source: openssl::error::ErrorStack,
+    },
+
+    /// Generating the private key failed.
+    #[non_exhaustive]
+    GenerateKey {
+        /// The source of the error.
+        source: openssl::error::ErrorStack,
+    },
+
+    /// Getting the coordinates of the public key failed.
+    #[non_exhaustive]
+    GetCoordinates {
+        /// The source of the error.
+        source: openssl::error::ErrorStack,
+    },
+
+    /// Failed to create the PKey from the EcKey.
+    #[non_exhaustive]
+    CreatePKey {
+        /// The source of the error.
+        source: openssl::error::ErrorStack,
+    },
+}
+impl fmt::Display for GenerateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self {
+            Self::UnsupportedAlgorithm { alg, .. } => {
+                write!(
+                    f,
+                    "algorithm {alg:?} does not use elliptic curve or Edwards curve keys"
+                )
+            }
+            Self::GetEcGroup { .. } => write!(f, "failed getting elliptic curve group for curve"),
+            Self::GenerateKey { .. } => write!(f, "failed generating a private key"),
+            Self::GetCoordinates { .. } => {
+                write!(f, "failed getting the coordinates of the public key")
+            }
+            Self::CreatePKey { .. } => write!(
+                f,
+                "failed converting the elliptic curve key to a private key"
+            ),
+        }
+    }
+}
+impl Error for GenerateError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self {
+            Self::UnsupportedAlgorithm { .. } => None,
+            Self::GetEcGroup { source, .. } => Some(source),
+            Self::GenerateKey { source, .. } => Some(source),
+            Self::GetCoordinates { source, .. } => Some(source),
+            Self::CreatePKey { source, .. } => Some(source),
+        }
+    }
+}
+
 /// Error variants for creating an Encoding JSON web key from a PEM file.
 #[derive(Debug)]
 #[non_exhaustive]
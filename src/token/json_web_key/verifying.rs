@@ -7,14 +7,16 @@ use openssl::{
     bn::BigNum,
     ec::{EcGroup, EcKey},
     hash::MessageDigest,
-    nid::Nid,
-    pkey::{PKey, Public},
+    pkey::{Id, PKey, Public},
     sign::Verifier,
 };
 
+#[cfg(feature = "ring")]
+use crate::token::json_web_key::ring_backend;
 use crate::token::{
     Algorithm, JsonWebKey, JsonWebToken,
-    json_web_key::{Curve, JsonWebKeyParameters},
+    json_web_key::{Curve, JsonWebKeyParameters, der_to_fixed_signature, fixed_to_der_signature},
+    json_web_token::verify_compact,
 };
 
 /// A JSON web key used to verify a signed token.
@@ -28,61 +30,161 @@ pub struct VerifyingJsonWebKey {
     pub key: PKey<Public>,
 }
 impl VerifyingJsonWebKey {
+    /// Create a verifying key for `EdDSA` tokens from a PEM encoded Ed25519 public key.
+    pub fn from_ed25519_pem(kid: String, pem: &[u8]) -> Result<Self, FromPublicKeyError> {
+        let key = PKey::public_key_from_pem(pem)
+            .map_err(|source| FromPublicKeyError::PemToPublicKey { source })?;
+
+        if key.id() != Id::ED25519 {
+            return Err(FromPublicKeyError::NotEd25519 { id: key.id() });
+        }
+
+        let raw = key
+            .raw_public_key()
+            .map_err(|source| FromPublicKeyError::RawPublicKey { source })?;
+
+        Self::from_ed25519_raw(kid, &raw)
+    }
+
+    /// Create a verifying key for `EdDSA` tokens from a raw 32-byte Ed25519 public key.
+    pub fn from_ed25519_raw(kid: String, raw: &[u8]) -> Result<Self, FromPublicKeyError> {
+        let jwk = JsonWebKey {
+            kid,
+            alg: Algorithm::EdDSA,
+            usage: "sig".to_string(),
+            parameters: JsonWebKeyParameters::OKP {
+                crv: Curve::Ed25519,
+                x: Base64UrlUnpadded::encode_string(raw),
+            },
+            x5t: None,
+            x5t_s256: None,
+        };
+
+        Self::try_from(jwk).map_err(|source| FromPublicKeyError::InvalidJwk { source })
+    }
+
     /// Verify a given token.
+    ///
+    /// The signing input is encoded from the token's header and claims, so use this for tokens
+    /// that have already been decoded, e.g., to look up the key by `kid`. Use [`Self::verify_str`]
+    /// when starting from the serialized token.
+    ///
+    /// ECDSA signatures must use the fixed-size `r || s` encoding from RFC 7518, signatures of any
+    /// other length are rejected. DER encoded signatures, as issued by earlier versions of this
+    /// crate, are only accepted by the extractor when
+    /// [`TokenValidationOptions::accepts_der_signatures`](crate::token::extractor::TokenValidationOptions::accepts_der_signatures)
+    /// is set.
+    ///
+    /// Tokens whose header claims a different algorithm from the key are rejected.
+    ///
+    /// With the `ring` feature, ES256 signatures are verified with `ring` instead of OpenSSL.
     pub fn verify(&self, token: &JsonWebToken) -> Result<bool, openssl::error::ErrorStack> {
-        let mut verifier = match self.jwk.alg {
-            Algorithm::ES256 => Verifier::new(MessageDigest::sha256(), &self.key)?,
+        self.verify_signing_input(token, &token.signing_input(), false)
+    }
+
+    /// Verify a decoded token's signature over the signing input it was decoded from, optionally
+    /// accepting DER encoded ECDSA signatures.
+    pub(crate) fn verify_signing_input(
+        &self,
+        token: &JsonWebToken,
+        signing_input: &str,
+        accepts_der_signatures: bool,
+    ) -> Result<bool, openssl::error::ErrorStack> {
+        if token.header.alg != self.jwk.alg {
+            return Ok(false);
+        }
+
+        self.verify_signature(
+            signing_input.as_bytes(),
+            &token.signature,
+            accepts_der_signatures,
+        )
+    }
+
+    /// Decode and verify a compact serialized token in one pass, returning the token if it is
+    /// valid, or `None` if it is malformed or its signature is invalid.
+    ///
+    /// The signature is verified against the header and claims exactly as they appear in the
+    /// serialized token, so this also verifies tokens whose JSON is formatted differently from
+    /// this crate's. Use this when the key is already known; the claims, e.g., expiry, are not
+    /// checked.
+    pub fn verify_str(
+        &self,
+        token: &str,
+    ) -> Result<Option<JsonWebToken>, openssl::error::ErrorStack> {
+        verify_compact(token, &self.jwk.alg, |signing_input, signature| {
+            self.verify_signature(signing_input, signature, false)
+        })
+    }
+
+    fn verify_signature(
+        &self,
+        signing_input: &[u8],
+        signature: &[u8],
+        accepts_der_signatures: bool,
+    ) -> Result<bool, openssl::error::ErrorStack> {
+        let crv = match self.jwk.parameters {
+            JsonWebKeyParameters::EC { crv, .. } => crv,
+            // EdDSA signs the message itself, so there is no digest.
+            JsonWebKeyParameters::OKP { .. } => {
+                return Verifier::new_without_digest(&self.key)?
+                    .verify_oneshot(signature, signing_input);
+            }
         };
 
-        let contents = format!("{}.{}", token.header.encode(), token.claims.encode());
-        let is_valid = verifier.verify_oneshot(&token.signature, contents.as_bytes())?;
+        let fixed_size = usize::try_from(crv.coordinate_size()).unwrap_or(0) * 2;
+        let converted;
+        let signature = if signature.len() == fixed_size {
+            signature
+        } else if !accepts_der_signatures {
+            return Ok(false);
+        } else {
+            // When opted in, signatures of any other length are DER encoded, as issued by earlier
+            // versions of this crate.
+            match der_to_fixed_signature(signature, crv) {
+                Ok(signature) => {
+                    converted = signature;
+                    &converted
+                }
+                Err(_) => return Ok(false),
+            }
+        };
+
+        #[cfg(feature = "ring")]
+        if let JsonWebKeyParameters::EC {
+            crv: Curve::P256,
+            x,
+            y,
+        } = &self.jwk.parameters
+        {
+            let (Ok(x), Ok(y)) = (
+                Base64UrlUnpadded::decode_vec(x),
+                Base64UrlUnpadded::decode_vec(y),
+            ) else {
+                return Ok(false);
+            };
+
+            return Ok(ring_backend::verify_es256(&x, &y, signing_input, signature));
+        }
+
+        let Ok(signature) = fixed_to_der_signature(signature) else {
+            return Ok(false);
+        };
+        let mut verifier = Verifier::new(MessageDigest::sha256(), &self.key)?;
 
-        Ok(is_valid)
+        verifier.verify_oneshot(&signature, signing_input)
     }
 }
 impl TryFrom<JsonWebKey> for VerifyingJsonWebKey {
     type Error = FromJwkError;
 
     fn try_from(jwk: JsonWebKey) -> Result<Self, Self::Error> {
-        let key = match &jwk.parameters {
-            JsonWebKeyParameters::EC { crv, x, y } => {
-                let group = match crv {
-                    Curve::P256 => EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)
-                        .map_err(|source| EcFromJwkError::GetEcGroup { source })?,
-                };
-
-                let x = Base64UrlUnpadded::decode_vec(x).map_err(|source| {
-                    EcFromJwkError::Base64DecodeCoordinate {
-                        source,
-                        coordinate: "x",
-                    }
-                })?;
-                let y = Base64UrlUnpadded::decode_vec(y).map_err(|source| {
-                    EcFromJwkError::Base64DecodeCoordinate {
-                        source,
-                        coordinate: "y",
-                    }
-                })?;
-
-                let x = BigNum::from_slice(&x).map_err(|source| {
-                    EcFromJwkError::BigNumFromCoordinate {
-                        source,
-                        coordinate: "x",
-                    }
-                })?;
-                let y = BigNum::from_slice(&y).map_err(|source| {
-                    EcFromJwkError::BigNumFromCoordinate {
-                        source,
-                        coordinate: "y",
-                    }
-                })?;
-
-                let ec_key = EcKey::from_public_key_affine_coordinates(&group, &x, &y)
-                    .map_err(|source| EcFromJwkError::CreateEcKey { source })?;
+        let crv = jwk.parameters.curve();
+        if Some(crv) != jwk.alg.curve() {
+            return Err(FromJwkError::AlgorithmCurveMismatch { alg: jwk.alg, crv });
+        }
 
-                PKey::from_ec_key(ec_key).map_err(|source| EcFromJwkError::CreatePKey { source })?
-            }
-        };
+        let key = public_key_from_parameters(&jwk.parameters)?;
 
         Ok(Self {
             jwk,
@@ -92,6 +194,63 @@ impl TryFrom<JsonWebKey> for VerifyingJsonWebKey {
     }
 }
 
+/// Convert the parameters of a JSON web key to a public key.
+pub(crate) fn public_key_from_parameters(
+    parameters: &JsonWebKeyParameters,
+) -> Result<PKey<Public>, FromJwkError> {
+    let key = match parameters {
+        JsonWebKeyParameters::EC { crv, x, y } => {
+            let nid = crv
+                .nid()
+                .ok_or(EcFromJwkError::UnsupportedCurve { crv: *crv })?;
+            let group = EcGroup::from_curve_name(nid)
+                .map_err(|source| EcFromJwkError::GetEcGroup { source })?;
+
+            let x = Base64UrlUnpadded::decode_vec(x).map_err(|source| {
+                EcFromJwkError::Base64DecodeCoordinate {
+                    source,
+                    coordinate: "x",
+                }
+            })?;
+            let y = Base64UrlUnpadded::decode_vec(y).map_err(|source| {
+                EcFromJwkError::Base64DecodeCoordinate {
+                    source,
+                    coordinate: "y",
+                }
+            })?;
+
+            let x =
+                BigNum::from_slice(&x).map_err(|source| EcFromJwkError::BigNumFromCoordinate {
+                    source,
+                    coordinate: "x",
+                })?;
+            let y =
+                BigNum::from_slice(&y).map_err(|source| EcFromJwkError::BigNumFromCoordinate {
+                    source,
+                    coordinate: "y",
+                })?;
+
+            let ec_key = EcKey::from_public_key_affine_coordinates(&group, &x, &y)
+                .map_err(|source| EcFromJwkError::CreateEcKey { source })?;
+
+            PKey::from_ec_key(ec_key).map_err(|source| EcFromJwkError::CreatePKey { source })?
+        }
+        JsonWebKeyParameters::OKP { crv, x } => {
+            if *crv != Curve::Ed25519 {
+                return Err(OkpFromJwkError::UnsupportedCurve { crv: *crv }.into());
+            }
+
+            let x = Base64UrlUnpadded::decode_vec(x)
+                .map_err(|source| OkpFromJwkError::Base64DecodePublicKey { source })?;
+
+            PKey::public_key_from_raw_bytes(&x, Id::ED25519)
+                .map_err(|source| OkpFromJwkError::CreatePKey { source })?
+        }
+    };
+
+    Ok(key)
+}
+
 /// Error variants for converting a JSON web key to a decoding key.
 #[derive(Debug)]
 #[non_exhaustive]
@@ -101,6 +260,22 @@ pub enum FromJwkError {
         /// The source of the failure.
         source: EcFromJwkError,
     },
+
+    /// Converting an octet key pair JSON web key to a decoding key failed.
+    #[non_exhaustive]
+    Okp {
+        /// The source of the failure.
+        source: OkpFromJwkError,
+    },
+
+    /// The curve of the key cannot be used with the key's algorithm.
+    #[non_exhaustive]
+    AlgorithmCurveMismatch {
+        /// The algorithm of the key.
+        alg: Algorithm,
+        /// The curve of the key.
+        crv: Curve,
+    },
 }
 impl fmt::Display for FromJwkError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -111,6 +286,15 @@ impl fmt::Display for FromJwkError {
                     "could not convert elliptic curve parameters to a public key"
                 )
             }
+            Self::Okp { .. } => {
+                write!(
+                    f,
+                    "could not convert octet key pair parameters to a public key"
+                )
+            }
+            Self::AlgorithmCurveMismatch { alg, crv } => {
+                write!(f, "algorithm {alg:?} cannot be used with curve {crv:?}")
+            }
         }
     }
 }
@@ -118,6 +302,8 @@ impl Error for FromJwkError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         match &self {
             Self::Ec { source, .. } => Some(source),
+            Self::Okp { source, .. } => Some(source),
+            Self::AlgorithmCurveMismatch { .. } => None,
         }
     }
 }
@@ -126,11 +312,23 @@ impl From<EcFromJwkError> for FromJwkError {
         Self::Ec { source }
     }
 }
+impl From<OkpFromJwkError> for FromJwkError {
+    fn from(source: OkpFromJwkError) -> Self {
+        Self::Okp { source }
+    }
+}
 
 /// Error variants for converting an elliptic curve JSON web key to a public key.
 #[derive(Debug)]
 #[non_exhaustive]
 pub enum EcFromJwkError {
+    /// The curve is not an elliptic curve group, e.g., an Edwards curve.
+    #[non_exhaustive]
+    UnsupportedCurve {
+        /// The curve.
+        crv: Curve,
+    },
+
     /// Getting the elliptic curve group failed.
     #[non_exhaustive]
     GetEcGroup {
@@ -173,6 +371,9 @@ pub enum EcFromJwkError {
 impl fmt::Display for EcFromJwkError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match &self {
+            Self::UnsupportedCurve { crv } => {
+                write!(f, "curve {crv:?} is not a supported elliptic curve")
+            }
             Self::GetEcGroup { .. } => write!(f, "failed getting elliptic curve group for curve"),
             Self::Base64DecodeCoordinate { coordinate, .. } => {
                 write!(f, "coordinate {coordinate} is invalid base64")
@@ -191,6 +392,7 @@ impl fmt::Display for EcFromJwkError {
 impl Error for EcFromJwkError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         match &self {
+            Self::UnsupportedCurve { .. } => None,
             Self::GetEcGroup { source, .. } => Some(source),
             Self::Base64DecodeCoordinate { source, .. } => Some(source),
             Self::BigNumFromCoordinate { source, .. } => Some(source),
@@ -199,3 +401,102 @@ impl Error for EcFromJwkError {
         }
     }
 }
+
+/// Error variants for converting an octet key pair JSON web key to a public key.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum OkpFromJwkError {
+    /// The curve is not an Edwards curve supported by this implementation.
+    #[non_exhaustive]
+    UnsupportedCurve {
+        /// The curve.
+        crv: Curve,
+    },
+
+    /// The public key failed base-64 decoding.
+    #[non_exhaustive]
+    Base64DecodePublicKey {
+        /// The source of the error.
+        source: base64ct::Error,
+    },
+
+    /// Failed to create the PKey from the public key.
+    #[non_exhaustive]
+    CreatePKey {
+        /// The source of the error.
+        source: openssl::error::ErrorStack,
+    },
+}
+impl fmt::Display for OkpFromJwkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self {
+            Self::UnsupportedCurve { crv, .. } => {
+                write!(f, "curve {crv:?} is not a supported octet key pair curve")
+            }
+            Self::Base64DecodePublicKey { .. } => write!(f, "public key is invalid base64"),
+            Self::CreatePKey { .. } => write!(f, "failed creating the public key"),
+        }
+    }
+}
+impl Error for OkpFromJwkError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self {
+            Self::UnsupportedCurve { .. } => None,
+            Self::Base64DecodePublicKey { source, .. } => Some(source),
+            Self::CreatePKey { source, .. } => Some(source),
+        }
+    }
+}
+
+/// Error variants for creating a verifying JSON web key from a public key.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum FromPublicKeyError {
+    /// The PEM to public key conversion failed.
+    #[non_exhaustive]
+    PemToPublicKey {
+        /// The source of the error.
+        source: openssl::error::ErrorStack,
+    },
+
+    /// The public key is not an Ed25519 key.
+    #[non_exhaustive]
+    NotEd25519 {
+        /// The type of the public key.
+        id: Id,
+    },
+
+    /// Getting the raw bytes of the public key failed.
+    #[non_exhaustive]
+    RawPublicKey {
+        /// The source of the error.
+        source: openssl::error::ErrorStack,
+    },
+
+    /// The JSON web key built from the public key is not valid.
+    #[non_exhaustive]
+    InvalidJwk {
+        /// The source of the error.
+        source: FromJwkError,
+    },
+}
+impl fmt::Display for FromPublicKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self {
+            Self::PemToPublicKey { .. } => write!(f, "PEM could not be converted to a public key"),
+            Self::NotEd25519 { id, .. } => write!(f, "public key {id:?} is not an Ed25519 key"),
+            Self::RawPublicKey { .. } => write!(f, "failed getting the raw public key"),
+            Self::InvalidJwk { .. } => write!(f, "public key is not a valid JWK"),
+        }
+    }
+}
+impl Error for FromPublicKeyError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self {
+            Self::PemToPublicKey { source, .. } => Some(source),
+            Self::NotEd25519 { .. } => None,
+            Self::RawPublicKey { source, .. } => Some(source),
+            Self::InvalidJwk { source, .. } => Some(source),
+        }
+    }
+}
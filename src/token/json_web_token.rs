@@ -1,12 +1,16 @@
 //! A decoded JSON web token.
 
+use core::{error::Error, fmt, str::FromStr};
+
 use base64ct::{Base64UrlUnpadded, Encoding};
-use jiff::Timestamp;
+use jiff::{SignedDuration, Timestamp};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::token::json_web_key::Curve;
+
 /// A decoded JSON web token.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct JsonWebToken {
     /// The JSON web token header.
     pub header: Header,
@@ -19,25 +23,101 @@ pub struct JsonWebToken {
 impl JsonWebToken {
     /// Serialize the token as a JSON web token string.
     pub fn serialize(&self) -> String {
-        let header = self.header.encode();
-        let claims = self.claims.encode();
+        let signing_input = self.signing_input();
         let signature = Base64UrlUnpadded::encode_string(&self.signature);
 
-        format!("{header}.{claims}.{signature}")
+        format!("{signing_input}.{signature}")
+    }
+
+    /// The input that is signed when issuing the token and verified when verifying it, i.e.,
+    /// `base64url(header).base64url(claims)`.
+    ///
+    /// The header and claims are encoded from their current values, so this can be compared
+    /// against the signing input of another implementation when a signature fails to verify.
+    pub fn signing_input(&self) -> String {
+        format!("{}.{}", self.header.encode(), self.claims.encode())
     }
 
     /// Deserialize the token from a JSON web token string.
     pub fn deserialize(value: &str) -> Option<Self> {
+        Self::try_deserialize(value).ok()
+    }
+
+    /// Deserialize the token from a JSON web token string, returning why it is malformed.
+    pub fn try_deserialize(value: &str) -> Result<Self, DeserializeTokenError> {
         let mut parts = value.split(".");
-        let header = parts.next()?;
-        let claims = parts.next()?;
-        let signature = parts.next()?;
+        let mut next_part = |part| {
+            parts
+                .next()
+                .ok_or(DeserializeTokenError::MissingPart { part })
+        };
+        let header = next_part(TokenPart::Header)?;
+        let claims = next_part(TokenPart::Claims)?;
+        let signature = next_part(TokenPart::Signature)?;
+        if parts.next().is_some() {
+            return Err(DeserializeTokenError::TrailingPart);
+        }
+
+        Self::try_decode_parts(header, claims, signature)
+    }
+
+    /// Deserialize the token from a JSON web token string, along with its signing input exactly as
+    /// it was serialized.
+    pub(crate) fn deserialize_with_signing_input(value: &str) -> Option<(Self, &str)> {
+        let token = Self::deserialize(value)?;
+        let (signing_input, _) = value.rsplit_once('.')?;
+
+        Some((token, signing_input))
+    }
+
+    /// Serialize the token using the flattened JWS JSON serialization.
+    pub fn to_json_serialization(&self) -> String {
+        let value = JsonSerialization {
+            protected: self.header.encode(),
+            payload: self.claims.encode(),
+            signature: Base64UrlUnpadded::encode_string(&self.signature),
+        };
+
+        serde_json::to_string(&value).expect("serializing the token should never fail")
+    }
+
+    /// Deserialize the token from the flattened JWS JSON serialization.
+    pub fn from_json_serialization(value: &str) -> Option<Self> {
+        let value: JsonSerialization = serde_json::from_str(value).ok()?;
 
-        let header = serde_json::from_slice(&Base64UrlUnpadded::decode_vec(header).ok()?).ok()?;
-        let claims = serde_json::from_slice(&Base64UrlUnpadded::decode_vec(claims).ok()?).ok()?;
-        let signature = Base64UrlUnpadded::decode_vec(signature).ok()?;
+        Self::decode_parts(&value.protected, &value.payload, &value.signature)
+    }
 
-        Some(Self {
+    fn decode_parts(header: &str, claims: &str, signature: &str) -> Option<Self> {
+        Self::try_decode_parts(header, claims, signature).ok()
+    }
+
+    fn try_decode_parts(
+        header: &str,
+        claims: &str,
+        signature: &str,
+    ) -> Result<Self, DeserializeTokenError> {
+        let decode = |value, part| {
+            Base64UrlUnpadded::decode_vec(value)
+                .map_err(|source| DeserializeTokenError::Base64 { part, source })
+        };
+        let header =
+            serde_json::from_slice(&decode(header, TokenPart::Header)?).map_err(|source| {
+                DeserializeTokenError::Json {
+                    part: TokenPart::Header,
+                    source,
+                }
+            })?;
+        let claims =
+            serde_json::from_slice(&decode(claims, TokenPart::Claims)?).map_err(|source| {
+                DeserializeTokenError::Json {
+                    part: TokenPart::Claims,
+                    source,
+                }
+            })?;
+        let signature = decode(signature, TokenPart::Signature)?;
+
+        Ok(Self {
             header,
             claims,
             signature,
@@ -45,18 +125,156 @@ impl JsonWebToken {
     }
 }
 
+/// Decode a compact serialized token and verify its signature over the header and claims exactly
+/// as they were serialized, returning `None` if it is malformed, claims an algorithm other than
+/// `alg`, or its signature is invalid.
+pub(crate) fn verify_compact<E>(
+    serialized: &str,
+    alg: &Algorithm,
+    verify_signature: impl FnOnce(&[u8], &[u8]) -> Result<bool, E>,
+) -> Result<Option<JsonWebToken>, E> {
+    let Some((token, signing_input)) = JsonWebToken::deserialize_with_signing_input(serialized)
+    else {
+        return Ok(None);
+    };
+    if token.header.alg != *alg {
+        return Ok(None);
+    }
+
+    if !verify_signature(signing_input.as_bytes(), &token.signature)? {
+        return Ok(None);
+    }
+
+    Ok(Some(token))
+}
+
+impl fmt::Display for JsonWebToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.serialize())
+    }
+}
+impl FromStr for JsonWebToken {
+    type Err = DeserializeTokenError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Self::try_deserialize(value)
+    }
+}
+
+/// A part of a compact serialized token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TokenPart {
+    /// The header.
+    Header,
+    /// The claims.
+    Claims,
+    /// The signature.
+    Signature,
+}
+impl fmt::Display for TokenPart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Header => write!(f, "header"),
+            Self::Claims => write!(f, "claims"),
+            Self::Signature => write!(f, "signature"),
+        }
+    }
+}
+
+/// Error variants for deserializing a compact serialized token.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum DeserializeTokenError {
+    /// The token is missing a part.
+    #[non_exhaustive]
+    MissingPart {
+        /// The missing part.
+        part: TokenPart,
+    },
+
+    /// The token has more parts after the signature, e.g., it is a JSON web encryption.
+    #[non_exhaustive]
+    TrailingPart,
+
+    /// A part is not valid URL base-64.
+    #[non_exhaustive]
+    Base64 {
+        /// The part that is not valid.
+        part: TokenPart,
+        /// The source of the error.
+        source: base64ct::Error,
+    },
+
+    /// A part is not valid JSON for the part.
+    #[non_exhaustive]
+    Json {
+        /// The part that is not valid.
+        part: TokenPart,
+        /// The source of the error.
+        source: serde_json::Error,
+    },
+}
+impl fmt::Display for DeserializeTokenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self {
+            Self::MissingPart { part, .. } => write!(f, "token has no {part}"),
+            Self::TrailingPart { .. } => write!(f, "token has parts after the signature"),
+            Self::Base64 { part, .. } => write!(f, "token {part} is not valid base64url"),
+            Self::Json { part, .. } => write!(f, "token {part} is not valid"),
+        }
+    }
+}
+impl Error for DeserializeTokenError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self {
+            Self::MissingPart { .. } | Self::TrailingPart { .. } => None,
+            Self::Base64 { source, .. } => Some(source),
+            Self::Json { source, .. } => Some(source),
+        }
+    }
+}
+
+/// The flattened JWS JSON serialization of a token.
+#[derive(Serialize, Deserialize)]
+struct JsonSerialization {
+    protected: String,
+    payload: String,
+    signature: String,
+}
+
 /// The JSON web token header.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Header {
     /// The algorithm used to sign the JSON web token.
     pub alg: Algorithm,
     /// The type of algorithm used to sign the JSON web token.
     pub typ: String,
     /// The ID of the key used to sign the JSON web token.
+    ///
+    /// This is empty if the token only references the key by a certificate thumbprint.
+    #[serde(default)]
     pub kid: String,
+    /// The URL base-64 SHA-1 thumbprint of the certificate of the key used to sign the token.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub x5t: Option<String>,
+    /// The URL base-64 SHA-256 thumbprint of the certificate of the key used to sign the token.
+    #[serde(rename = "x5t#S256", default, skip_serializing_if = "Option::is_none")]
+    pub x5t_s256: Option<String>,
+    /// The header parameters that are extensions the verifier must understand and process.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub crit: Vec<String>,
 }
 
 impl Header {
+    /// Returns the first critical header parameter that is not in `understood`.
+    pub fn unknown_critical(&self, understood: &[String]) -> Option<&str> {
+        self.crit
+            .iter()
+            .find(|parameter| !understood.contains(parameter))
+            .map(String::as_str)
+    }
+
     /// Encode the JSON representation of the header as URL base-64.
     pub fn encode(&self) -> String {
         let json = serde_json::to_vec(&self).expect("serializing the header should never fail");
@@ -65,7 +283,7 @@ impl Header {
 }
 
 /// The JSON web token claims.
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub struct Claims {
     /// The ID of this specific token.
     pub tid: String,
@@ -75,36 +293,151 @@ pub struct Claims {
     /// The time when the JSON web token was issued.
     #[serde(with = "serde_sec")]
     pub iat: Timestamp,
+    /// The issuer of the token.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iss: Option<Issuer>,
     /// The subject of the token.
-    pub sub: String,
+    pub sub: Subject,
+    /// The audiences the token is intended for.
+    ///
+    /// A single audience is serialized as a bare string, and a bare string deserializes into a
+    /// single audience.
+    #[serde(
+        default,
+        skip_serializing_if = "Vec::is_empty",
+        with = "serde_one_or_many"
+    )]
+    pub aud: Vec<Audience>,
     /// The type of the token.
     #[serde(flatten)]
     pub typ: TokenType,
 }
 
+/// Define a newtype for a string claim, so claims cannot be passed where another is expected.
+macro_rules! string_claim {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        ///
+        /// It is serialized as a plain string.
+        #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+        #[serde(transparent)]
+        pub struct $name(pub String);
+
+        impl $name {
+            /// The claim as a string slice.
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+        }
+        impl core::ops::Deref for $name {
+            type Target = str;
+
+            fn deref(&self) -> &Self::Target {
+                &self.0
+            }
+        }
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+        impl From<&str> for $name {
+            fn from(value: &str) -> Self {
+                Self(value.to_string())
+            }
+        }
+        impl From<String> for $name {
+            fn from(value: String) -> Self {
+                Self(value)
+            }
+        }
+        impl From<$name> for String {
+            fn from(value: $name) -> Self {
+                value.0
+            }
+        }
+        impl PartialEq<str> for $name {
+            fn eq(&self, other: &str) -> bool {
+                self.0 == other
+            }
+        }
+        impl PartialEq<&str> for $name {
+            fn eq(&self, other: &&str) -> bool {
+                self.0 == *other
+            }
+        }
+    };
+}
+
+string_claim!(
+    /// The issuer of a token, the `iss` claim.
+    Issuer
+);
+string_claim!(
+    /// The subject of a token, the `sub` claim.
+    Subject
+);
+string_claim!(
+    /// An audience of a token, an `aud` claim.
+    Audience
+);
+
 /// The type of token.
-#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 #[serde(tag = "typ")]
 #[non_exhaustive]
 pub enum TokenType {
     /// A common token that grants the bearer authorisation for common actions.
     Common,
-    /// A consent token that grants the bearer authorisation to perform a specific action.
+    /// A consent token that grants the bearer authorisation to perform specific actions.
     Consent {
-        /// The action the bearer is authorised to perform.
-        act: String,
+        /// The actions the bearer is authorised to perform.
+        ///
+        /// A single action is serialized as a bare string, and a bare string deserializes into a
+        /// single action.
+        #[serde(with = "serde_one_or_many")]
+        act: Vec<String>,
     },
     /// A token to granted when provisioning a new identity before any credentials have been added.
     Provisioning,
 }
+impl TokenType {
+    /// Returns if this is a consent token that authorises the bearer to perform `action`.
+    pub fn grants_action(&self, action: &str) -> bool {
+        match self {
+            Self::Consent { act } => act.iter().any(|granted| granted == action),
+            _ => false,
+        }
+    }
+}
 
 /// Algorithms supported by this implementation.
-#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize, JsonSchema)]
 #[non_exhaustive]
 pub enum Algorithm {
     /// ES256 algorithm.
     ES256,
+    /// HS256 algorithm, an HMAC using SHA-256 with a shared secret.
+    HS256,
+    /// EdDSA algorithm, using an Ed25519 key.
+    EdDSA,
+}
+impl Algorithm {
+    /// The curve of the keys used with this algorithm, or `None` if it does not use elliptic curve
+    /// keys.
+    pub fn curve(&self) -> Option<Curve> {
+        match self {
+            Self::ES256 => Some(Curve::P256),
+            Self::HS256 => None,
+            Self::EdDSA => Some(Curve::Ed25519),
+        }
+    }
 }
 
 impl Claims {
@@ -114,17 +447,111 @@ impl Claims {
         Base64UrlUnpadded::encode_string(&json)
     }
 
+    /// Returns if the token is intended for an audience.
+    pub fn has_audience(&self, audience: &str) -> bool {
+        self.aud.iter().any(|aud| aud == audience)
+    }
+
     /// Returns if the token is expired.
     pub fn is_expired(&self) -> bool {
-        let now = Timestamp::now();
-        self.exp < now
+        self.is_expired_at(Timestamp::now(), SignedDuration::ZERO)
+    }
+
+    /// Returns if the token is expired at `now`, allowing for `leeway` of clock drift between the
+    /// issuer and this service.
+    pub fn is_expired_at(&self, now: Timestamp, leeway: SignedDuration) -> bool {
+        let exp = self
+            .exp
+            .checked_add(leeway)
+            .unwrap_or(if leeway.is_negative() {
+                Timestamp::MIN
+            } else {
+                Timestamp::MAX
+            });
+
+        exp < now
+    }
+
+    /// Returns if the token claims to have been issued after `now`, allowing for `leeway` of clock
+    /// drift between the issuer and this service.
+    pub fn is_issued_in_future_at(&self, now: Timestamp, leeway: SignedDuration) -> bool {
+        let latest_iat = now.checked_add(leeway).unwrap_or(if leeway.is_negative() {
+            Timestamp::MIN
+        } else {
+            Timestamp::MAX
+        });
+
+        self.iat > latest_iat
     }
 }
 
+/// The claims of a token that are safe to return to the bearer, e.g., from a `/me` endpoint.
+///
+/// Internal claims, such as the token ID, are not included, so prefer serializing this over
+/// [`Claims`] in responses.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PublicClaims {
+    /// The subject of the token.
+    pub subject: String,
+    /// When the token was issued.
+    pub issued_at: Timestamp,
+    /// When the token expires.
+    pub expires_at: Timestamp,
+    /// The type of the token.
+    pub token_type: PublicTokenType,
+    /// The actions a consent token authorises the bearer to perform.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub actions: Vec<String>,
+}
+impl From<&Claims> for PublicClaims {
+    fn from(claims: &Claims) -> Self {
+        let (token_type, actions) = match &claims.typ {
+            TokenType::Common => (PublicTokenType::Common, Vec::new()),
+            TokenType::Consent { act } => (PublicTokenType::Consent, act.clone()),
+            TokenType::Provisioning => (PublicTokenType::Provisioning, Vec::new()),
+        };
+
+        Self {
+            subject: claims.sub.to_string(),
+            issued_at: claims.iat,
+            expires_at: claims.exp,
+            token_type,
+            actions,
+        }
+    }
+}
+impl From<&JsonWebToken> for PublicClaims {
+    fn from(token: &JsonWebToken) -> Self {
+        Self::from(&token.claims)
+    }
+}
+
+/// The type of a token, as returned to the bearer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub enum PublicTokenType {
+    /// A [`TokenType::Common`] token.
+    Common,
+    /// A [`TokenType::Consent`] token.
+    Consent,
+    /// A [`TokenType::Provisioning`] token.
+    Provisioning,
+}
+
 mod serde_sec {
-    use jiff::Timestamp;
+    use jiff::{SignedDuration, Timestamp};
     use serde::{Deserialize, Deserializer, Serializer, de};
 
+    /// An RFC 7519 NumericDate, which may have fractional seconds.
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum NumericDate {
+        Integer(i64),
+        Float(f64),
+    }
+
     pub fn serialize<S>(value: &Timestamp, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
@@ -136,9 +563,52 @@ mod serde_sec {
     where
         D: Deserializer<'de>,
     {
-        let value: i64 = Deserialize::deserialize(deserializer)?;
+        let out_of_range = |value: &dyn core::fmt::Display| {
+            de::Error::custom(format!("{value} does not fit in a `jiff::Timestamp`"))
+        };
 
-        Timestamp::from_second(value)
-            .map_err(|_| de::Error::custom(format!("{value} does not fit in a `jiff::Timestamp`")))
+        match NumericDate::deserialize(deserializer)? {
+            NumericDate::Integer(value) => {
+                Timestamp::from_second(value).map_err(|_| out_of_range(&value))
+            }
+            // Tokens carry whole seconds, so the fraction is truncated.
+            NumericDate::Float(value) => SignedDuration::try_from_secs_f64(value)
+                .ok()
+                .and_then(|duration| Timestamp::from_second(duration.as_secs()).ok())
+                .ok_or_else(|| out_of_range(&value)),
+        }
+    }
+}
+
+mod serde_one_or_many {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany<T> {
+        One(T),
+        Many(Vec<T>),
+    }
+
+    pub fn serialize<S, T>(values: &[T], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        T: Serialize,
+    {
+        match values {
+            [value] => value.serialize(serializer),
+            values => values.serialize(serializer),
+        }
+    }
+
+    pub fn deserialize<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: Deserialize<'de>,
+    {
+        match OneOrMany::deserialize(deserializer)? {
+            OneOrMany::One(value) => Ok(vec![value]),
+            OneOrMany::Many(values) => Ok(values),
+        }
     }
 }
@@ -0,0 +1,146 @@
+//! Interoperability with the [`jsonwebtoken`] crate.
+//!
+//! Tokens issued by a [`SigningJsonWebKey`](crate::token::SigningJsonWebKey) can be verified by
+//! [`jsonwebtoken`], and tokens issued by [`jsonwebtoken`] can be verified by a
+//! [`VerifyingJsonWebKey`], allowing consumers to migrate between the two incrementally:
+//!
+//! ```ignore
+//! let key = verifying_jwk.decoding_key()?;
+//! let validation = jsonwebtoken::Validation::new(verifying_jwk.jwk.alg.clone().into());
+//! let token = jsonwebtoken::decode::<Claims>(&token.serialize(), &key, &validation)?;
+//! ```
+//!
+//! A [`JsonWebToken`] can also be converted to and from a [`jsonwebtoken::TokenData`], so a token
+//! produced by one stack can be inspected by the other, e.g., in tests.
+
+use core::{error::Error, fmt};
+
+use jsonwebtoken::{DecodingKey, TokenData};
+
+use crate::token::{
+    Algorithm, JsonWebToken, VerifyingJsonWebKey,
+    json_web_key::JsonWebKeyParameters,
+    json_web_token::{Claims, Header},
+};
+
+impl From<Algorithm> for jsonwebtoken::Algorithm {
+    fn from(value: Algorithm) -> Self {
+        match value {
+            Algorithm::ES256 => Self::ES256,
+            Algorithm::HS256 => Self::HS256,
+            Algorithm::EdDSA => Self::EdDSA,
+        }
+    }
+}
+
+impl TryFrom<jsonwebtoken::Algorithm> for Algorithm {
+    type Error = UnsupportedAlgorithmError;
+
+    fn try_from(value: jsonwebtoken::Algorithm) -> Result<Self, Self::Error> {
+        match value {
+            jsonwebtoken::Algorithm::ES256 => Ok(Self::ES256),
+            jsonwebtoken::Algorithm::HS256 => Ok(Self::HS256),
+            jsonwebtoken::Algorithm::EdDSA => Ok(Self::EdDSA),
+            algorithm => Err(UnsupportedAlgorithmError(algorithm)),
+        }
+    }
+}
+
+/// The [`jsonwebtoken::Algorithm`] is not supported by this implementation.
+#[derive(Debug)]
+pub struct UnsupportedAlgorithmError(pub jsonwebtoken::Algorithm);
+impl fmt::Display for UnsupportedAlgorithmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "algorithm {:?} is not supported", self.0)
+    }
+}
+impl Error for UnsupportedAlgorithmError {}
+
+impl VerifyingJsonWebKey {
+    /// Create a [`jsonwebtoken`] decoding key from the JSON web key.
+    pub fn decoding_key(&self) -> Result<DecodingKey, jsonwebtoken::errors::Error> {
+        match &self.jwk.parameters {
+            JsonWebKeyParameters::EC { x, y, .. } => DecodingKey::from_ec_components(x, y),
+            JsonWebKeyParameters::OKP { x, .. } => DecodingKey::from_ed_components(x),
+        }
+    }
+}
+
+impl From<&JsonWebToken> for TokenData<serde_json::Value> {
+    /// Convert the header and claims of a token. The `crit` header parameter is not supported by
+    /// [`jsonwebtoken::Header`], so it is dropped.
+    fn from(token: &JsonWebToken) -> Self {
+        let header = jsonwebtoken::Header {
+            typ: Some(token.header.typ.clone()),
+            kid: (!token.header.kid.is_empty()).then(|| token.header.kid.clone()),
+            x5t: token.header.x5t.clone(),
+            x5t_s256: token.header.x5t_s256.clone(),
+            ..jsonwebtoken::Header::new(token.header.alg.clone().into())
+        };
+        let claims =
+            serde_json::to_value(&token.claims).expect("serializing the claims should never fail");
+
+        Self { header, claims }
+    }
+}
+
+impl TryFrom<TokenData<serde_json::Value>> for JsonWebToken {
+    type Error = FromTokenDataError;
+
+    /// Convert the header and claims of decoded token data. The token data does not include the
+    /// signature, so the signature is empty.
+    fn try_from(value: TokenData<serde_json::Value>) -> Result<Self, Self::Error> {
+        let header = Header {
+            alg: Algorithm::try_from(value.header.alg)
+                .map_err(|source| FromTokenDataError::UnsupportedAlgorithm { source })?,
+            typ: value.header.typ.unwrap_or_else(|| "JWT".to_string()),
+            kid: value.header.kid.unwrap_or_default(),
+            x5t: value.header.x5t,
+            x5t_s256: value.header.x5t_s256,
+            crit: Vec::new(),
+        };
+        let claims: Claims = serde_json::from_value(value.claims)
+            .map_err(|source| FromTokenDataError::InvalidClaims { source })?;
+
+        Ok(Self {
+            header,
+            claims,
+            signature: Vec::new(),
+        })
+    }
+}
+
+/// Error variants for converting [`jsonwebtoken::TokenData`] to a [`JsonWebToken`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum FromTokenDataError {
+    /// The algorithm is not supported by this implementation.
+    #[non_exhaustive]
+    UnsupportedAlgorithm {
+        /// The source of the error.
+        source: UnsupportedAlgorithmError,
+    },
+
+    /// The claims are not valid claims for this implementation.
+    #[non_exhaustive]
+    InvalidClaims {
+        /// The source of the error.
+        source: serde_json::Error,
+    },
+}
+impl fmt::Display for FromTokenDataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self {
+            Self::UnsupportedAlgorithm { .. } => write!(f, "token algorithm is not supported"),
+            Self::InvalidClaims { .. } => write!(f, "token claims are invalid"),
+        }
+    }
+}
+impl Error for FromTokenDataError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self {
+            Self::UnsupportedAlgorithm { source, .. } => Some(source),
+            Self::InvalidClaims { source, .. } => Some(source),
+        }
+    }
+}
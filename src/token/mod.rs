@@ -1,9 +1,21 @@
 //! Library module to handle JSON web tokens, JSON web keys, etc.
 
+pub mod clock;
 pub mod config;
+pub mod db_revocation;
 pub mod extractor;
+pub mod json_web_encryption;
 pub mod json_web_key;
 pub mod json_web_token;
+#[cfg(feature = "jsonwebtoken")]
+pub mod jsonwebtoken_compat;
+pub mod symmetric_key;
+pub mod verified_token_cache;
 
+pub use clock::{Clock, SystemClock, TestClock};
+pub use db_revocation::DbRevocation;
+pub use json_web_encryption::{DecryptingJsonWebKey, EncryptingJsonWebKey, JsonWebEncryption};
 pub use json_web_key::{JsonWebKey, JsonWebKeySetCache, SigningJsonWebKey, VerifyingJsonWebKey};
 pub use json_web_token::{Algorithm, JsonWebToken};
+pub use symmetric_key::SymmetricKey;
+pub use verified_token_cache::VerifiedTokenCache;
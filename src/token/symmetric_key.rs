@@ -0,0 +1,107 @@
+//! A shared secret used to sign and verify tokens with HMAC.
+use core::fmt;
+
+use openssl::{hash::MessageDigest, memcmp, pkey::PKey, sign::Signer};
+use zeroize::Zeroizing;
+
+use crate::token::{
+    Algorithm, Clock, JsonWebToken, SystemClock,
+    json_web_key::signing::unsigned_token,
+    json_web_token::{TokenType, verify_compact},
+};
+
+/// A shared secret used to sign and verify `HS256` tokens.
+///
+/// The secret is zeroed when the key is dropped.
+pub struct SymmetricKey {
+    /// The ID of the key, used as the `kid` of issued tokens.
+    pub kid: String,
+    secret: Zeroizing<Vec<u8>>,
+}
+impl fmt::Debug for SymmetricKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SymmetricKey")
+            .field("kid", &self.kid)
+            .field("secret", &"***")
+            .finish()
+    }
+}
+impl SymmetricKey {
+    /// Create a key from a shared secret, which should be at least 32 random bytes.
+    pub fn new(kid: String, secret: Vec<u8>) -> Self {
+        Self {
+            kid,
+            secret: Zeroizing::new(secret),
+        }
+    }
+
+    /// Issue a new token of the given type for a subject.
+    pub fn issue(
+        &self,
+        subject: String,
+        token_type: TokenType,
+    ) -> Result<JsonWebToken, openssl::error::ErrorStack> {
+        self.issue_with_clock(subject, token_type, &SystemClock)
+    }
+
+    /// Issue a new token of the given type for a subject, taking the issue time from a clock.
+    pub fn issue_with_clock(
+        &self,
+        subject: String,
+        token_type: TokenType,
+        clock: &dyn Clock,
+    ) -> Result<JsonWebToken, openssl::error::ErrorStack> {
+        let mut token = unsigned_token(
+            Algorithm::HS256,
+            self.kid.clone(),
+            subject,
+            token_type,
+            clock,
+        );
+        token.signature = self.sign(token.signing_input().as_bytes())?;
+
+        Ok(token)
+    }
+
+    /// Verify a given token.
+    ///
+    /// Tokens whose header claims any algorithm other than `HS256` are rejected, so a token signed
+    /// with a public key cannot be passed off as one signed with the secret, and vice versa.
+    pub fn verify(&self, token: &JsonWebToken) -> Result<bool, openssl::error::ErrorStack> {
+        if token.header.alg != Algorithm::HS256 {
+            return Ok(false);
+        }
+
+        self.verify_signature(token.signing_input().as_bytes(), &token.signature)
+    }
+
+    /// Decode and verify a compact serialized token in one pass, returning the token if it is
+    /// valid, or `None` if it is malformed or its signature is invalid.
+    ///
+    /// See [`VerifyingJsonWebKey::verify_str`](crate::token::VerifyingJsonWebKey::verify_str).
+    pub fn verify_str(
+        &self,
+        token: &str,
+    ) -> Result<Option<JsonWebToken>, openssl::error::ErrorStack> {
+        verify_compact(token, &Algorithm::HS256, |signing_input, signature| {
+            self.verify_signature(signing_input, signature)
+        })
+    }
+
+    fn sign(&self, signing_input: &[u8]) -> Result<Vec<u8>, openssl::error::ErrorStack> {
+        let key = PKey::hmac(&self.secret)?;
+        let mut signer = Signer::new(MessageDigest::sha256(), &key)?;
+
+        signer.sign_oneshot_to_vec(signing_input)
+    }
+
+    fn verify_signature(
+        &self,
+        signing_input: &[u8],
+        signature: &[u8],
+    ) -> Result<bool, openssl::error::ErrorStack> {
+        let expected = self.sign(signing_input)?;
+
+        Ok(expected.len() == signature.len() && memcmp::eq(&expected, signature))
+    }
+}
@@ -0,0 +1,142 @@
+//! A short-lived cache of tokens that have been verified.
+use std::{collections::HashMap, sync::Arc};
+
+use jiff::{SignedDuration, Timestamp};
+use tokio::sync::RwLock;
+
+use crate::token::{Clock, JsonWebToken, SystemClock};
+
+/// A short-lived cache of tokens that have passed verification and revocation checks, keyed by
+/// the serialized token.
+///
+/// A cache hit skips signature verification and the revocation check, so a token that is revoked
+/// may continue to be accepted for up to `ttl`.
+///
+/// The cache prunes itself: stale tokens, verified more than `ttl` ago or past their `exp`, are
+/// removed by an insert at most once every `ttl`, and when a lookup finds them. So the memory used
+/// is bounded by the number of distinct tokens presented within about twice `ttl`, not by every
+/// token ever seen, while most inserts do not scan the cache.
+#[derive(Clone, Debug)]
+pub struct VerifiedTokenCache {
+    /// How long a verified token is trusted without being verified again.
+    pub ttl: SignedDuration,
+    /// The cached tokens.
+    pub cache: Arc<RwLock<HashMap<String, CachedToken>>>,
+    /// The source of the current time.
+    clock: Arc<dyn Clock>,
+    /// When the cache was last pruned.
+    last_prune: Arc<RwLock<Timestamp>>,
+}
+
+/// A token in the verified token cache.
+#[derive(Clone, Debug)]
+pub struct CachedToken {
+    /// The verified token.
+    pub token: JsonWebToken,
+    /// When the token was verified.
+    pub verified: Timestamp,
+}
+
+impl CachedToken {
+    /// Returns if the cached token should no longer be trusted.
+    pub fn is_stale(&self, ttl: SignedDuration) -> bool {
+        self.is_stale_at(ttl, Timestamp::now())
+    }
+
+    /// Returns if the cached token should no longer be trusted at `now`, because it was verified
+    /// more than `ttl` ago or has expired.
+    pub fn is_stale_at(&self, ttl: SignedDuration, now: Timestamp) -> bool {
+        self.verified.duration_until(now) >= ttl
+            || self.token.claims.is_expired_at(now, SignedDuration::ZERO)
+    }
+}
+
+impl VerifiedTokenCache {
+    /// Create a new cache that trusts verified tokens for `ttl`.
+    pub fn new(ttl: SignedDuration) -> Self {
+        Self {
+            ttl,
+            cache: Arc::new(RwLock::new(HashMap::new())),
+            clock: Arc::new(SystemClock),
+            last_prune: Arc::new(RwLock::new(Timestamp::UNIX_EPOCH)),
+        }
+    }
+
+    /// Use a clock other than the system clock for checking if tokens are stale.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Get a verified token if it has been verified within the TTL and has not expired.
+    ///
+    /// A stale token is removed from the cache.
+    pub async fn get(&self, token: &str) -> Option<JsonWebToken> {
+        {
+            let cache = self.cache.read().await;
+            let cached = cache.get(token)?;
+
+            if !cached.is_stale_at(self.ttl, self.clock.now()) {
+                return Some(cached.token.clone());
+            }
+        }
+
+        let mut cache = self.cache.write().await;
+        if cache
+            .get(token)
+            .is_some_and(|cached| cached.is_stale_at(self.ttl, self.clock.now()))
+        {
+            cache.remove(token);
+        }
+
+        None
+    }
+
+    /// Insert a token that has been verified, pruning any stale tokens if the cache was last
+    /// pruned more than `ttl` ago.
+    pub async fn insert(&self, serialized: String, token: JsonWebToken) {
+        let now = self.clock.now();
+        let mut cache = self.cache.write().await;
+
+        let mut last_prune = self.last_prune.write().await;
+        if last_prune.duration_until(now) >= self.ttl {
+            cache.retain(|_, cached| !cached.is_stale_at(self.ttl, now));
+            *last_prune = now;
+        }
+        drop(last_prune);
+
+        cache.insert(
+            serialized,
+            CachedToken {
+                token,
+                verified: now,
+            },
+        );
+    }
+
+    /// Remove the stale tokens, returning how many were removed.
+    pub async fn prune(&self) -> usize {
+        let now = self.clock.now();
+        let mut cache = self.cache.write().await;
+
+        let before = cache.len();
+        cache.retain(|_, cached| !cached.is_stale_at(self.ttl, now));
+        *self.last_prune.write().await = now;
+        before - cache.len()
+    }
+
+    /// The number of cached tokens, including any stale tokens that have not been pruned.
+    pub async fn len(&self) -> usize {
+        self.cache.read().await.len()
+    }
+
+    /// Returns if no tokens are cached.
+    pub async fn is_empty(&self) -> bool {
+        self.cache.read().await.is_empty()
+    }
+
+    /// Remove every cached token.
+    pub async fn clear(&self) {
+        self.cache.write().await.clear();
+    }
+}
@@ -0,0 +1,12 @@
+/// Instrument a future with a span, if the `tracing` feature is enabled, otherwise return the
+/// future as is, so the spans cost nothing without it.
+macro_rules! in_span {
+    ($name:literal, $future:expr) => {{
+        #[cfg(feature = "tracing")]
+        let future = tracing::Instrument::instrument($future, tracing::info_span!($name));
+        #[cfg(not(feature = "tracing"))]
+        let future = $future;
+        future
+    }};
+}
+pub(crate) use in_span;
@@ -0,0 +1,48 @@
+use axum::extract::{FromRequestParts, Path};
+use http::request::Parts;
+use uuid::Uuid;
+
+use crate::{ErrorResponse, Pointer, Problem};
+
+/// Extractor for a single path parameter that must be a UUID.
+///
+/// If the parameter is not a valid UUID, the request is rejected as a bad request with a problem
+/// pointing at the parameter's name, e.g., `$.id` for the route `/{id}`.
+pub struct UuidPath(pub Uuid);
+
+impl<S> FromRequestParts<S> for UuidPath
+where
+    S: Send + Sync,
+{
+    type Rejection = ErrorResponse;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Path(parameters) =
+            <Path<Vec<(String, String)>> as FromRequestParts<S>>::from_request_parts(parts, state)
+                .await
+                .map_err(|rejection| {
+                    if rejection.status().is_server_error() {
+                        ErrorResponse::internal_server_error_from(rejection)
+                    } else {
+                        ErrorResponse::bad_request(vec![])
+                    }
+                })?;
+
+        let [(name, value)] = parameters.as_slice() else {
+            log::error!(
+                "`UuidPath` requires exactly one path parameter, found {}",
+                parameters.len()
+            );
+            return Err(ErrorResponse::internal_server_error());
+        };
+
+        let uuid = Uuid::try_parse(value).map_err(|_| {
+            ErrorResponse::bad_request(vec![Problem::new(
+                Pointer::root().field(name),
+                "must be a valid UUID",
+            )])
+        })?;
+
+        Ok(Self(uuid))
+    }
+}
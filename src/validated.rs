@@ -0,0 +1,73 @@
+use axum::extract::FromRequestParts;
+use http::{StatusCode, request::Parts};
+
+use crate::ErrorResponse;
+
+/// Extractor that runs every extractor in a tuple, e.g., `Validated<(A, B, C)>`, and rejects the
+/// request with the problems of all of them instead of only the first to fail.
+///
+/// If every failed extractor rejected the request as a bad request, the request is rejected as a
+/// bad request with their problems combined, in the order of the tuple, so the client can fix them
+/// all at once. If any failed for another reason, e.g., the request was unauthenticated, the first
+/// such rejection is returned as is.
+///
+/// Only extractors that do not consume the request body are supported, so a body extractor must be
+/// a separate argument of the handler after this.
+pub struct Validated<T>(pub T);
+
+macro_rules! impl_validated {
+    ($($extractor:ident),+) => {
+        impl<S, $($extractor),+> FromRequestParts<S> for Validated<($($extractor,)+)>
+        where
+            S: Send + Sync,
+            $($extractor: FromRequestParts<S, Rejection = ErrorResponse> + Send,)+
+        {
+            type Rejection = ErrorResponse;
+
+            #[allow(non_snake_case)]
+            async fn from_request_parts(
+                parts: &mut Parts,
+                state: &S,
+            ) -> Result<Self, Self::Rejection> {
+                $(let $extractor = $extractor::from_request_parts(parts, state).await;)+
+
+                let errors = [$($extractor.as_ref().err()),+];
+                if let Some(error) = combine_errors(errors.into_iter().flatten()) {
+                    return Err(error);
+                }
+
+                match ($($extractor,)+) {
+                    ($(Ok($extractor),)+) => Ok(Self(($($extractor,)+))),
+                    _ => unreachable!("every extractor succeeded"),
+                }
+            }
+        }
+    };
+}
+
+impl_validated!(T1);
+impl_validated!(T1, T2);
+impl_validated!(T1, T2, T3);
+impl_validated!(T1, T2, T3, T4);
+impl_validated!(T1, T2, T3, T4, T5);
+impl_validated!(T1, T2, T3, T4, T5, T6);
+impl_validated!(T1, T2, T3, T4, T5, T6, T7);
+impl_validated!(T1, T2, T3, T4, T5, T6, T7, T8);
+
+/// Combine the rejections of the failed extractors into a single rejection, or `None` if none
+/// failed.
+fn combine_errors<'a>(errors: impl Iterator<Item = &'a ErrorResponse>) -> Option<ErrorResponse> {
+    let mut problems = Vec::new();
+    let mut has_failed = false;
+
+    for error in errors {
+        if error.status != StatusCode::BAD_REQUEST {
+            return Some(error.clone());
+        }
+
+        has_failed = true;
+        problems.extend(error.problems.iter().cloned());
+    }
+
+    has_failed.then(|| ErrorResponse::bad_request(problems))
+}
@@ -0,0 +1,39 @@
+use http::{HeaderMap, HeaderName, HeaderValue, header::VARY};
+
+/// Add header names to the `Vary` header of a response without removing the existing values.
+///
+/// Names that are already present are skipped, and nothing is added if the response already varies
+/// on everything (`Vary: *`). Responses that differ by a request header, such as `Accept`,
+/// `Accept-Encoding`, or `Origin`, must list it so caches do not serve the wrong content.
+pub fn append_vary(headers: &mut HeaderMap, names: &[HeaderName]) {
+    let existing: Vec<String> = headers
+        .get_all(VARY)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|name| name.trim().to_ascii_lowercase())
+        .filter(|name| !name.is_empty())
+        .collect();
+
+    if existing.iter().any(|name| name == "*") {
+        return;
+    }
+
+    let mut missing: Vec<&str> = Vec::new();
+    for name in names {
+        // Header names are always lowercase.
+        if !existing.iter().any(|existing| existing == name.as_str())
+            && !missing.contains(&name.as_str())
+        {
+            missing.push(name.as_str());
+        }
+    }
+
+    if missing.is_empty() {
+        return;
+    }
+
+    if let Ok(value) = HeaderValue::from_str(&missing.join(", ")) {
+        headers.append(VARY, value);
+    }
+}
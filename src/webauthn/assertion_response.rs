@@ -1,9 +1,10 @@
 #![allow(missing_docs)]
 
-use base64ct::{Base64UrlUnpadded, Encoding};
+use core::ops::{BitAnd, BitOr};
+
 use serde::{Deserialize, de};
 
-use crate::webauthn::public_key_credential::ClientDataJson;
+use crate::{base64::decode_url_base64, webauthn::public_key_credential::ClientDataJson};
 
 /// https://developer.mozilla.org/en-US/docs/Web/API/AuthenticatorAttestationResponse
 #[derive(Debug, Deserialize)]
@@ -19,7 +20,9 @@ pub struct AssertionResponse {
     pub signature: Vec<u8>,
 
     /// Specified as the `user.id` in the options passed to the originating `PublicKeyCredentialCreationOptions`.
-    #[serde(with = "crate::maybe_serde_base64")]
+    ///
+    /// May be null or absent for credentials that are not discoverable.
+    #[serde(default, with = "crate::maybe_serde_base64")]
     pub user_handle: Option<Vec<u8>>,
 }
 
@@ -28,11 +31,61 @@ pub struct AuthenticatorData {
     pub relying_party_id_hash: [u8; 32],
     pub flags: Flags,
     pub signature_counter: u32,
+    /// The attested credential data, present if [`Flags::ATTESTED_CREDENTIAL_DATA`] is set.
+    pub attested_credential_data: Option<AttestedCredentialData>,
+    /// The CBOR encoded map of extension outputs, present if [`Flags::EXTENSION_DATA`] is set.
+    pub extensions: Option<Vec<u8>>,
     pub raw: Vec<u8>,
 }
 
+/// https://www.w3.org/TR/webauthn-3/#sctn-attested-credential-data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttestedCredentialData {
+    /// The AAGUID identifying the model of the authenticator.
+    pub aaguid: [u8; 16],
+    pub credential_id: Vec<u8>,
+    /// The credential public key, a CBOR encoded `COSE_Key`.
+    pub credential_public_key: Vec<u8>,
+}
+
+impl AttestedCredentialData {
+    /// The maximum length of a credential ID.
+    pub const MAX_CREDENTIAL_ID_LENGTH: usize = 1023;
+
+    /// Parse the attested credential data from the start of `bytes`, returning it and the number
+    /// of bytes it used.
+    fn parse(bytes: &[u8]) -> Result<(Self, usize), &'static str> {
+        let (aaguid, rest) = bytes
+            .split_first_chunk::<16>()
+            .ok_or("attested credential data is missing the AAGUID")?;
+        let (credential_id_length, rest) = rest
+            .split_first_chunk::<2>()
+            .ok_or("attested credential data is missing the credential ID length")?;
+
+        let credential_id_length = usize::from(u16::from_be_bytes(*credential_id_length));
+        if credential_id_length > Self::MAX_CREDENTIAL_ID_LENGTH {
+            return Err("credential ID is longer than 1023 bytes");
+        }
+        let (credential_id, rest) = rest
+            .split_at_checked(credential_id_length)
+            .ok_or("attested credential data is missing the credential ID")?;
+
+        let public_key_length =
+            cbor_item_length(rest, 0).ok_or("credential public key is invalid CBOR")?;
+        let credential_public_key = &rest[..public_key_length];
+
+        let data = Self {
+            aaguid: *aaguid,
+            credential_id: credential_id.to_vec(),
+            credential_public_key: credential_public_key.to_vec(),
+        };
+
+        Ok((data, 16 + 2 + credential_id_length + public_key_length))
+    }
+}
+
 #[repr(transparent)]
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
 pub struct Flags(pub u8);
 impl Flags {
     pub const USER_PRESENCE: Self = Self(1 << 0);
@@ -41,6 +94,55 @@ impl Flags {
     pub const BACKUP_STATE: Self = Self(1 << 4);
     pub const ATTESTED_CREDENTIAL_DATA: Self = Self(1 << 6);
     pub const EXTENSION_DATA: Self = Self(1 << 7);
+
+    /// Returns if all of the bits in `flag` are set.
+    pub fn contains(&self, flag: Self) -> bool {
+        self.0 & flag.0 == flag.0
+    }
+
+    /// Returns if the user was present.
+    pub fn user_present(&self) -> bool {
+        self.contains(Self::USER_PRESENCE)
+    }
+
+    /// Returns if the user was verified.
+    pub fn user_verified(&self) -> bool {
+        self.contains(Self::USER_VERIFICATION)
+    }
+
+    /// Returns if the credential is eligible to be backed up.
+    pub fn backup_eligible(&self) -> bool {
+        self.contains(Self::BACKUP_ELIGIBILITY)
+    }
+
+    /// Returns if the credential is currently backed up.
+    pub fn backup_state(&self) -> bool {
+        self.contains(Self::BACKUP_STATE)
+    }
+
+    /// Returns if the authenticator data includes attested credential data.
+    pub fn attested_credential_data(&self) -> bool {
+        self.contains(Self::ATTESTED_CREDENTIAL_DATA)
+    }
+
+    /// Returns if the authenticator data includes extension data.
+    pub fn extension_data(&self) -> bool {
+        self.contains(Self::EXTENSION_DATA)
+    }
+}
+impl BitAnd for Flags {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        Self(self.0 & rhs.0)
+    }
+}
+impl BitOr for Flags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
 }
 
 impl<'de> Deserialize<'de> for AuthenticatorData {
@@ -49,11 +151,18 @@ impl<'de> Deserialize<'de> for AuthenticatorData {
         D: serde::Deserializer<'de>,
     {
         let base64: &str = Deserialize::deserialize(deserializer)?;
-        let bytes = Base64UrlUnpadded::decode_vec(base64).map_err(de::Error::custom)?;
+        let bytes = decode_url_base64(base64).map_err(de::Error::custom)?;
+
+        Self::parse(bytes).map_err(de::Error::custom)
+    }
+}
+
+impl AuthenticatorData {
+    /// Parse the authenticator data, including the attested credential data and extensions if
+    /// their flags are set.
+    fn parse(bytes: Vec<u8>) -> Result<Self, &'static str> {
         if bytes.len() < 37 {
-            return Err(de::Error::custom(
-                "authenticator data must be at least 37 bytes",
-            ));
+            return Err("authenticator data must be at least 37 bytes");
         }
 
         let mut relying_party_id_hash = [0u8; 32];
@@ -65,11 +174,82 @@ impl<'de> Deserialize<'de> for AuthenticatorData {
         signature_counter_bytes.copy_from_slice(&bytes[33..37]);
         let signature_counter = u32::from_be_bytes(signature_counter_bytes);
 
+        let mut rest = &bytes[37..];
+
+        let attested_credential_data = if flags.attested_credential_data() {
+            let (data, length) = AttestedCredentialData::parse(rest)?;
+            rest = &rest[length..];
+            Some(data)
+        } else {
+            None
+        };
+
+        let extensions = if flags.extension_data() {
+            let length = cbor_item_length(rest, 0).ok_or("extension data is invalid CBOR")?;
+            let extensions = rest[..length].to_vec();
+            rest = &rest[length..];
+            Some(extensions)
+        } else {
+            None
+        };
+
+        if !rest.is_empty() {
+            return Err("authenticator data has trailing bytes");
+        }
+
         Ok(Self {
             relying_party_id_hash,
             flags,
             signature_counter,
+            attested_credential_data,
+            extensions,
             raw: bytes,
         })
     }
 }
+
+/// The maximum nesting of CBOR arrays, maps, and tags.
+const MAX_CBOR_DEPTH: usize = 16;
+
+/// Get the length of the definite-length CBOR item at the start of `bytes`, or `None` if it is
+/// invalid, truncated, or nested too deeply.
+fn cbor_item_length(bytes: &[u8], depth: usize) -> Option<usize> {
+    if depth > MAX_CBOR_DEPTH {
+        return None;
+    }
+
+    let (&initial, rest) = bytes.split_first()?;
+    let major_type = initial >> 5;
+
+    let (argument, header_length) = match initial & 0x1f {
+        additional @ 0..24 => (u64::from(additional), 1),
+        24 => (u64::from(*rest.first()?), 2),
+        25 => (u64::from(u16::from_be_bytes(*rest.first_chunk()?)), 3),
+        26 => (u64::from(u32::from_be_bytes(*rest.first_chunk()?)), 5),
+        27 => (u64::from_be_bytes(*rest.first_chunk()?), 9),
+        // Reserved, or an indefinite length, which is not used by authenticators.
+        _ => return None,
+    };
+
+    let child_items = match major_type {
+        // Integers and simple values have no content after their argument.
+        0 | 1 | 7 => return Some(header_length),
+        // Byte and text strings.
+        2 | 3 => {
+            let length = usize::try_from(argument).ok()?;
+            let total = header_length.checked_add(length)?;
+            return (total <= bytes.len()).then_some(total);
+        }
+        4 => argument,
+        5 => argument.checked_mul(2)?,
+        6 => 1,
+        _ => unreachable!("the major type is three bits"),
+    };
+
+    let mut length = header_length;
+    for _ in 0..child_items {
+        length += cbor_item_length(bytes.get(length..)?, depth + 1)?;
+    }
+
+    Some(length)
+}
@@ -1,6 +1,10 @@
 //! A challenge issued to a client.
 
-use jiff::Timestamp;
+use core::{error::Error, fmt};
+
+use http::Uri;
+use jiff::{SignedDuration, Timestamp};
+use openssl::{error::ErrorStack, rand::rand_bytes};
 use serde::{Deserialize, Serialize};
 use ts_sql_helper_lib::{FromRow, SqlTimestamp};
 
@@ -18,20 +22,107 @@ pub struct Challenge {
     /// When the challenge expires.
     pub expires: SqlTimestamp,
     /// The origin the challenge was issued to.
+    ///
+    /// Challenges from [`Self::generate`] store the origin normalized, with a lowercase scheme and
+    /// host, and without the default port of the scheme, e.g., `https://example.com`.
     pub origin: String,
 }
 
 impl Challenge {
+    /// How far in the future a challenge may have been issued and still be valid, to allow for clock
+    /// skew between the node that issued the challenge and the node verifying it.
+    pub const ISSUED_LEEWAY: SignedDuration = SignedDuration::from_secs(60);
+
+    /// The length in bytes of a challenge from [`Self::generate`].
+    pub const DEFAULT_LENGTH: usize = 32;
+
+    /// The minimum length in bytes of a challenge from [`Self::with_length`].
+    pub const MIN_LENGTH: usize = 16;
+
+    /// Generate a challenge of [`Self::DEFAULT_LENGTH`] random bytes, issued now and expiring after
+    /// `ttl`.
+    pub fn generate(
+        origin: String,
+        identity_id: Option<Vec<u8>>,
+        ttl: SignedDuration,
+    ) -> Result<Self, GenerateChallengeError> {
+        Self::with_length(origin, identity_id, ttl, Self::DEFAULT_LENGTH)
+    }
+
+    /// Generate a challenge of `length` random bytes, issued now and expiring after `ttl`.
+    ///
+    /// The length must be at least [`Self::MIN_LENGTH`].
+    pub fn with_length(
+        origin: String,
+        identity_id: Option<Vec<u8>>,
+        ttl: SignedDuration,
+        length: usize,
+    ) -> Result<Self, GenerateChallengeError> {
+        if length < Self::MIN_LENGTH {
+            return Err(GenerateChallengeError::TooShort { length });
+        }
+
+        let mut challenge = vec![0u8; length];
+        rand_bytes(&mut challenge).map_err(|source| GenerateChallengeError::Random { source })?;
+
+        let issued = Timestamp::now();
+        let origin = Uri::try_from(origin.as_str())
+            .ok()
+            .as_ref()
+            .and_then(normalized_origin)
+            .unwrap_or(origin);
+
+        Ok(Self {
+            challenge,
+            identity_id,
+            issued: SqlTimestamp(issued),
+            expires: SqlTimestamp(issued + ttl),
+            origin,
+        })
+    }
+
     /// Returns if the challenge is valid.
+    ///
+    /// A challenge is valid if it has not expired and was issued no later than
+    /// [`Self::ISSUED_LEEWAY`] from now.
     pub fn is_valid(&self) -> bool {
-        let now = Timestamp::now();
+        self.is_valid_at(Timestamp::now(), SignedDuration::ZERO)
+    }
+
+    /// Returns if the challenge is valid at a time, treating it as expiring `expiry_leeway` after
+    /// its expiry, so a ceremony completed in time is not rejected because of a slow round-trip.
+    ///
+    /// The expiry leeway is separate from [`Self::ISSUED_LEEWAY`].
+    pub fn is_valid_at(&self, now: Timestamp, expiry_leeway: SignedDuration) -> bool {
+        let is_expired = self
+            .expires
+            .0
+            .checked_add(expiry_leeway)
+            .is_ok_and(|expires| expires <= now);
 
-        self.expires.0 > now && self.issued.0 < now
+        !is_expired && self.issued.0 < now + Self::ISSUED_LEEWAY
     }
 
     /// Returns if the challenge is for a given origin.
-    pub fn is_for_origin(&self, origin: &str) -> bool {
-        self.origin == origin
+    ///
+    /// Origins match if their schemes, hosts, and ports are equal, ignoring the case of the host,
+    /// and treating the default port of the scheme as equal to no port.
+    pub fn is_for_origin(&self, origin: &Uri) -> bool {
+        let Some(origin) = normalized_origin(origin) else {
+            return false;
+        };
+
+        if self.origin == origin {
+            return true;
+        }
+
+        // The origin is only parsed if it was not stored normalized, e.g., the challenge was not
+        // generated.
+        Uri::try_from(self.origin.as_str())
+            .ok()
+            .as_ref()
+            .and_then(normalized_origin)
+            .is_some_and(|challenge_origin| challenge_origin == origin)
     }
 
     /// Returns if the challenge is for the given bearer.
@@ -39,3 +130,60 @@ impl Challenge {
         self.identity_id.as_deref() == bearer
     }
 }
+
+/// The origin of a URI with a lowercase scheme and host, and without the default port of the
+/// scheme, e.g., `https://example.com`.
+fn normalized_origin(uri: &Uri) -> Option<String> {
+    let scheme = uri.scheme_str()?.to_ascii_lowercase();
+    let host = uri.host()?.to_ascii_lowercase();
+
+    let default_port = match scheme.as_str() {
+        "https" => Some(443),
+        "http" => Some(80),
+        _ => None,
+    };
+
+    match uri.port_u16() {
+        Some(port) if Some(port) != default_port => Some(format!("{scheme}://{host}:{port}")),
+        _ => Some(format!("{scheme}://{host}")),
+    }
+}
+
+/// Error variants for generating a challenge.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum GenerateChallengeError {
+    /// The requested length is shorter than [`Challenge::MIN_LENGTH`].
+    #[non_exhaustive]
+    TooShort {
+        /// The requested length.
+        length: usize,
+    },
+
+    /// The random bytes could not be generated.
+    #[non_exhaustive]
+    Random {
+        /// The source of the error.
+        source: ErrorStack,
+    },
+}
+impl fmt::Display for GenerateChallengeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self {
+            Self::TooShort { length } => write!(
+                f,
+                "challenge length {length} is shorter than the minimum of {}",
+                Challenge::MIN_LENGTH
+            ),
+            Self::Random { .. } => write!(f, "failed to generate random bytes"),
+        }
+    }
+}
+impl Error for GenerateChallengeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self {
+            Self::TooShort { .. } => None,
+            Self::Random { source } => Some(source),
+        }
+    }
+}
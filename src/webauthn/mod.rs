@@ -5,6 +5,7 @@ pub mod assertion_response;
 pub mod attestation_response;
 pub mod challenge;
 pub mod persisted_public_key;
+pub mod postgres_verifier;
 pub mod public_key_credential;
 pub mod public_key_credential_creation_options;
 pub mod public_key_credential_request_options;
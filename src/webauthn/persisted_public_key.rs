@@ -1,9 +1,14 @@
 //! The public key details that the relying party should persist.
 
+use core::{error::Error, fmt};
+
 use serde::{Deserialize, Serialize};
-use ts_sql_helper_lib::{FromRow, SqlTimestamp};
+use ts_sql_helper_lib::{FromRow, ParseFromRow, SqlTimestamp};
 
-use crate::webauthn::public_key_credential::{Algorithm, Transports};
+use crate::{
+    ConnectionPool,
+    webauthn::public_key_credential::{Algorithm, Transports},
+};
 
 /// The public key details that the relying party should persist.
 #[derive(Debug, Deserialize, Serialize, FromRow)]
@@ -41,3 +46,152 @@ pub struct PersistedPublicKey {
     /// When this public key was last used for an assertion.
     pub last_used: Option<SqlTimestamp>,
 }
+
+const INSERT_PUBLIC_KEY: &str = "
+INSERT INTO public_keys (
+    raw_id,
+    identity_id,
+    display_name,
+    public_key,
+    public_key_algorithm,
+    transports,
+    signature_counter,
+    created,
+    last_used
+) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)";
+
+const GET_PUBLIC_KEY_BY_RAW_ID: &str = "
+SELECT
+    raw_id,
+    identity_id,
+    display_name,
+    public_key,
+    public_key_algorithm,
+    transports,
+    signature_counter,
+    created,
+    last_used
+FROM public_keys
+WHERE raw_id = $1";
+
+/// Insert a public key into the `public_keys` table.
+///
+/// The table is expected to have the columns `raw_id BYTEA`, `identity_id BYTEA`,
+/// `display_name TEXT`, `public_key BYTEA`, `public_key_algorithm INT4`, `transports TEXT[]`,
+/// `signature_counter INT8`, `created TIMESTAMPTZ`, and `last_used TIMESTAMPTZ NULL`.
+pub async fn insert_public_key(
+    pool: &ConnectionPool,
+    key: &PersistedPublicKey,
+) -> Result<(), PublicKeyStoreError> {
+    insert_public_keys(pool, core::slice::from_ref(key)).await
+}
+
+/// Insert a set of public keys into the `public_keys` table in a single transaction.
+///
+/// See [`insert_public_key`] for the expected table.
+pub async fn insert_public_keys(
+    pool: &ConnectionPool,
+    keys: &[PersistedPublicKey],
+) -> Result<(), PublicKeyStoreError> {
+    let mut connection = pool
+        .get()
+        .await
+        .map_err(|source| PublicKeyStoreError::GetConnection { source })?;
+
+    let transaction = connection
+        .transaction()
+        .await
+        .map_err(|source| PublicKeyStoreError::Query { source })?;
+
+    let statement = transaction
+        .prepare(INSERT_PUBLIC_KEY)
+        .await
+        .map_err(|source| PublicKeyStoreError::Query { source })?;
+
+    for key in keys {
+        let algorithm = key.public_key_algorithm.value();
+        let transports: Vec<String> = key.transports.iter().map(ToString::to_string).collect();
+
+        transaction
+            .execute(
+                &statement,
+                &[
+                    &key.raw_id,
+                    &key.identity_id,
+                    &key.display_name,
+                    &key.public_key,
+                    &algorithm,
+                    &transports,
+                    &key.signature_counter,
+                    &key.created,
+                    &key.last_used,
+                ],
+            )
+            .await
+            .map_err(|source| PublicKeyStoreError::Query { source })?;
+    }
+
+    transaction
+        .commit()
+        .await
+        .map_err(|source| PublicKeyStoreError::Query { source })?;
+
+    Ok(())
+}
+
+/// Get a public key from the `public_keys` table by its raw ID.
+///
+/// See [`insert_public_key`] for the expected table.
+pub async fn get_public_key_by_raw_id(
+    pool: &ConnectionPool,
+    raw_id: &[u8],
+) -> Result<Option<PersistedPublicKey>, PublicKeyStoreError> {
+    let connection = pool
+        .get()
+        .await
+        .map_err(|source| PublicKeyStoreError::GetConnection { source })?;
+
+    let row = connection
+        .query_opt(GET_PUBLIC_KEY_BY_RAW_ID, &[&raw_id])
+        .await
+        .map_err(|source| PublicKeyStoreError::Query { source })?;
+
+    row.map(|row| row.parse())
+        .transpose()
+        .map_err(|source| PublicKeyStoreError::Query { source })
+}
+
+/// Error variants for storing and retrieving public keys.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum PublicKeyStoreError {
+    /// A connection could not be retrieved from the pool.
+    #[non_exhaustive]
+    GetConnection {
+        /// The source of the error.
+        source: bb8::RunError<tokio_postgres::Error>,
+    },
+
+    /// The query failed.
+    #[non_exhaustive]
+    Query {
+        /// The source of the error.
+        source: tokio_postgres::Error,
+    },
+}
+impl fmt::Display for PublicKeyStoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self {
+            Self::GetConnection { .. } => write!(f, "could not get a connection from the pool"),
+            Self::Query { .. } => write!(f, "public key query failed"),
+        }
+    }
+}
+impl Error for PublicKeyStoreError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self {
+            Self::GetConnection { source } => Some(source),
+            Self::Query { source } => Some(source),
+        }
+    }
+}
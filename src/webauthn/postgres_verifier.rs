@@ -0,0 +1,227 @@
+//! A verifier backed by a Postgres connection pool.
+
+use core::{error::Error, fmt};
+
+use jiff::SignedDuration;
+use ts_sql_helper_lib::ParseFromRow;
+
+use crate::{
+    ConnectionPool,
+    webauthn::{
+        challenge::Challenge,
+        persisted_public_key::PersistedPublicKey,
+        verification::{CounterPolicy, Verifier},
+    },
+};
+
+/// A verifier that reads challenges and public keys from Postgres tables.
+///
+/// The challenges table is expected to have the columns `challenge BYTEA`,
+/// `identity_id BYTEA NULL`, `issued TIMESTAMPTZ`, `expires TIMESTAMPTZ`, and `origin TEXT`. The
+/// public keys table is expected to match
+/// [`insert_public_key`](crate::webauthn::persisted_public_key::insert_public_key).
+///
+/// Challenges are deleted when they are read, so each challenge can only be used once.
+///
+/// The signature counter of a verified assertion is persisted, never decreasing, along with when
+/// the public key was last used.
+///
+/// The table names are inserted into the queries as quoted identifiers, a name containing a `.`
+/// is treated as schema qualified. They must come from trusted configuration.
+#[derive(Debug, Clone)]
+pub struct PostgresVerifier {
+    /// The connection pool.
+    pub pool: ConnectionPool,
+    /// The relying party's ID.
+    pub relying_party_id: String,
+    /// Other IDs that assertions may be scoped to, see [`Verifier::relying_party_id_suffixes`].
+    pub relying_party_id_suffixes: Vec<String>,
+    /// The name of the table containing the challenges.
+    pub challenges_table: String,
+    /// The name of the table containing the public keys.
+    pub public_keys_table: String,
+    /// The policy for validating the signature counter of assertions.
+    pub counter_policy: CounterPolicy,
+    /// How long after a challenge expires it is still accepted.
+    pub challenge_expiry_leeway: SignedDuration,
+}
+
+impl PostgresVerifier {
+    /// Create a new verifier using the `challenges` and `public_keys` tables.
+    pub fn new(pool: ConnectionPool, relying_party_id: String) -> Self {
+        Self {
+            pool,
+            relying_party_id,
+            relying_party_id_suffixes: Vec::new(),
+            challenges_table: "challenges".to_string(),
+            public_keys_table: "public_keys".to_string(),
+            counter_policy: CounterPolicy::default(),
+            challenge_expiry_leeway: SignedDuration::ZERO,
+        }
+    }
+
+    /// Use a different table for the challenges.
+    pub fn with_challenges_table(mut self, table: String) -> Self {
+        self.challenges_table = table;
+        self
+    }
+
+    /// Use a different table for the public keys.
+    pub fn with_public_keys_table(mut self, table: String) -> Self {
+        self.public_keys_table = table;
+        self
+    }
+
+    /// Accept assertions scoped to registrable suffixes of the relying party's ID.
+    pub fn with_relying_party_id_suffixes(mut self, suffixes: Vec<String>) -> Self {
+        self.relying_party_id_suffixes = suffixes;
+        self
+    }
+
+    /// Use a different policy for validating the signature counter of assertions.
+    pub fn with_counter_policy(mut self, counter_policy: CounterPolicy) -> Self {
+        self.counter_policy = counter_policy;
+        self
+    }
+
+    /// Accept challenges for a time after they expire, e.g., 30 seconds.
+    pub fn with_challenge_expiry_leeway(mut self, leeway: SignedDuration) -> Self {
+        self.challenge_expiry_leeway = leeway;
+        self
+    }
+}
+
+/// Quote a possibly schema qualified table name as identifiers.
+fn quote_table(table: &str) -> String {
+    table
+        .split('.')
+        .map(|part| format!("\"{}\"", part.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+impl Verifier for PostgresVerifier {
+    type Error = PostgresVerifierError;
+
+    async fn get_challenge(&self, challenge: &[u8]) -> Result<Option<Challenge>, Self::Error> {
+        let query = format!(
+            "DELETE FROM {} WHERE challenge = $1 RETURNING challenge, identity_id, issued, expires, origin",
+            quote_table(&self.challenges_table)
+        );
+
+        let connection = self
+            .pool
+            .get()
+            .await
+            .map_err(|source| PostgresVerifierError::GetConnection { source })?;
+
+        let row = connection
+            .query_opt(&query, &[&challenge])
+            .await
+            .map_err(|source| PostgresVerifierError::Query { source })?;
+
+        row.map(|row| row.parse())
+            .transpose()
+            .map_err(|source| PostgresVerifierError::Query { source })
+    }
+
+    async fn get_public_key(
+        &self,
+        raw_id: &[u8],
+    ) -> Result<Option<PersistedPublicKey>, Self::Error> {
+        let query = format!(
+            "SELECT raw_id, identity_id, display_name, public_key, public_key_algorithm, transports, signature_counter, created, last_used FROM {} WHERE raw_id = $1",
+            quote_table(&self.public_keys_table)
+        );
+
+        let connection = self
+            .pool
+            .get()
+            .await
+            .map_err(|source| PostgresVerifierError::GetConnection { source })?;
+
+        let row = connection
+            .query_opt(&query, &[&raw_id])
+            .await
+            .map_err(|source| PostgresVerifierError::Query { source })?;
+
+        row.map(|row| row.parse())
+            .transpose()
+            .map_err(|source| PostgresVerifierError::Query { source })
+    }
+
+    async fn update_signature_counter(
+        &self,
+        raw_id: &[u8],
+        signature_counter: u32,
+    ) -> Result<(), Self::Error> {
+        let query = format!(
+            "UPDATE {} SET signature_counter = GREATEST(signature_counter, $2), last_used = now() WHERE raw_id = $1",
+            quote_table(&self.public_keys_table)
+        );
+
+        let connection = self
+            .pool
+            .get()
+            .await
+            .map_err(|source| PostgresVerifierError::GetConnection { source })?;
+
+        connection
+            .execute(&query, &[&raw_id, &i64::from(signature_counter)])
+            .await
+            .map_err(|source| PostgresVerifierError::Query { source })?;
+
+        Ok(())
+    }
+
+    fn relying_party_id(&self) -> &str {
+        &self.relying_party_id
+    }
+
+    fn relying_party_id_suffixes(&self) -> &[String] {
+        &self.relying_party_id_suffixes
+    }
+
+    fn challenge_expiry_leeway(&self) -> SignedDuration {
+        self.challenge_expiry_leeway
+    }
+
+    fn counter_policy(&self) -> CounterPolicy {
+        self.counter_policy
+    }
+}
+
+/// Error variants for the Postgres verifier.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum PostgresVerifierError {
+    /// A connection could not be retrieved from the pool.
+    #[non_exhaustive]
+    GetConnection {
+        /// The source of the error.
+        source: bb8::RunError<tokio_postgres::Error>,
+    },
+
+    /// The query failed.
+    #[non_exhaustive]
+    Query {
+        /// The source of the error.
+        source: tokio_postgres::Error,
+    },
+}
+impl fmt::Display for PostgresVerifierError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self {
+            Self::GetConnection { .. } => write!(f, "could not get a connection from the pool"),
+            Self::Query { .. } => write!(f, "verifier query failed"),
+        }
+    }
+}
+impl Error for PostgresVerifierError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self {
+            Self::GetConnection { source } => Some(source),
+            Self::Query { source } => Some(source),
+        }
+    }
+}
@@ -2,16 +2,46 @@
 
 use core::{error::Error, fmt};
 
-use base64ct::{Base64UrlUnpadded, Encoding};
+use http::Uri;
 use openssl::pkey::Id;
 use serde::{Deserialize, Serialize, de};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 use ts_sql_helper_lib::FromSql;
 
-use crate::webauthn::{
-    assertion_response::AssertionResponse, attestation_response::AttestationResponse,
+use crate::{
+    EncodeBase64, Pointer, Problem,
+    base64::decode_url_base64,
+    webauthn::{assertion_response::AssertionResponse, attestation_response::AttestationResponse},
 };
 
+/// The default timeout of a ceremony in milliseconds, as recommended by the specification for
+/// ceremonies that require user verification.
+pub const DEFAULT_TIMEOUT: u64 = 300_000;
+
+/// The shortest timeout of a ceremony in milliseconds that passes validation, the lower bound of
+/// the specification's recommended range.
+pub const MIN_TIMEOUT: u64 = 30_000;
+
+/// The longest timeout of a ceremony in milliseconds that passes validation, the upper bound of
+/// the specification's recommended range.
+pub const MAX_TIMEOUT: u64 = 600_000;
+
+pub(crate) fn default_timeout() -> u64 {
+    DEFAULT_TIMEOUT
+}
+
+/// Validate that a ceremony timeout is within the recommended range.
+pub(crate) fn validate_timeout(timeout: u64) -> Result<(), Problem> {
+    if !(MIN_TIMEOUT..=MAX_TIMEOUT).contains(&timeout) {
+        return Err(Problem::new(
+            Pointer::root().field("timeout"),
+            format!("must be between {MIN_TIMEOUT} and {MAX_TIMEOUT} milliseconds"),
+        ));
+    }
+
+    Ok(())
+}
+
 /// https://developer.mozilla.org/en-US/docs/Web/API/PublicKeyCredential
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -21,6 +51,102 @@ pub struct PublicKeyCredential {
     #[serde(with = "crate::serde_base64")]
     pub raw_id: Vec<u8>,
     pub response: Response,
+    /// The outputs of the extensions the client processed, from `getClientExtensionResults()`.
+    #[serde(default)]
+    pub client_extension_results: ClientExtensionResults,
+}
+
+impl PublicKeyCredential {
+    /// Validate that the credential is internally consistent.
+    ///
+    /// The `id` may be padded or unpadded URL base-64, like the `raw_id`.
+    pub fn validate(&self) -> Result<(), InvalidCredentialError> {
+        let id_matches = decode_url_base64(&self.id).is_ok_and(|id| id == self.raw_id);
+        if !id_matches {
+            return Err(InvalidCredentialError::IdMismatch {
+                id: self.id.clone(),
+                encoded_raw_id: self.raw_id.encode_base64(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Reasons a public key credential is not internally consistent.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum InvalidCredentialError {
+    /// The `id` is not the base-64 encoding of the `raw_id`.
+    #[non_exhaustive]
+    IdMismatch {
+        /// The `id` of the credential.
+        id: String,
+        /// The base-64 encoding of the `raw_id` of the credential.
+        encoded_raw_id: String,
+    },
+}
+impl fmt::Display for InvalidCredentialError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self {
+            Self::IdMismatch { id, encoded_raw_id } => {
+                write!(f, "id `{id}` does not match the raw id `{encoded_raw_id}`")
+            }
+        }
+    }
+}
+impl Error for InvalidCredentialError {}
+
+/// The outputs of the extensions the client processed.
+///
+/// Extensions that are not modelled are ignored.
+///
+/// https://www.w3.org/TR/webauthn-3/#dictdef-authenticationextensionsclientoutputs
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub struct ClientExtensionResults {
+    /// The output of the `credProps` extension, only present for registrations that requested it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cred_props: Option<CredentialProperties>,
+    /// The output of the `largeBlob` extension.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub large_blob: Option<LargeBlobOutputs>,
+}
+
+/// The properties of a newly created credential, from the `credProps` extension.
+///
+/// https://www.w3.org/TR/webauthn-3/#sctn-authenticator-credential-properties-extension
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub struct CredentialProperties {
+    /// If the credential is a client-side discoverable credential, i.e., a resident key. If this
+    /// is `None`, the client could not determine it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rk: Option<bool>,
+}
+
+/// The output of the `largeBlob` extension.
+///
+/// https://www.w3.org/TR/webauthn-3/#sctn-large-blob-extension
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub struct LargeBlobOutputs {
+    /// If the created credential supports storing large blobs, only present for registrations.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub supported: Option<bool>,
+    /// The blob that was read, only present for authentications that read it.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "crate::maybe_serde_base64"
+    )]
+    pub blob: Option<Vec<u8>>,
+    /// If the blob was written, only present for authentications that wrote it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub written: Option<bool>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -48,6 +174,8 @@ pub struct ClientDataJson {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cross_origin: Option<bool>,
     pub origin: String,
+    #[serde(skip)]
+    pub parsed_origin: Uri,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub top_origin: Option<String>,
     pub r#type: ClientDataType,
@@ -63,6 +191,35 @@ pub enum ClientDataType {
     WebAuthNGet,
 }
 
+impl fmt::Display for ClientDataType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self {
+            Self::WebAuthNCreate => f.write_str("webauthn.create"),
+            Self::WebAuthNGet => f.write_str("webauthn.get"),
+        }
+    }
+}
+impl TryFrom<&str> for ClientDataType {
+    type Error = InvalidClientDataTypeError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "webauthn.create" => Ok(Self::WebAuthNCreate),
+            "webauthn.get" => Ok(Self::WebAuthNGet),
+            _ => Err(InvalidClientDataTypeError(value.to_string())),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct InvalidClientDataTypeError(String);
+impl fmt::Display for InvalidClientDataTypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "value `{}` is not a valid ClientDataType", self.0)
+    }
+}
+impl Error for InvalidClientDataTypeError {}
+
 impl<'de> Deserialize<'de> for ClientDataJson {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -79,13 +236,19 @@ impl<'de> Deserialize<'de> for ClientDataJson {
         }
 
         let base64: &str = Deserialize::deserialize(deserializer)?;
-        let json_bytes = Base64UrlUnpadded::decode_vec(base64).map_err(de::Error::custom)?;
+        let json_bytes = decode_url_base64(base64).map_err(de::Error::custom)?;
         let value: RealData = serde_json::from_slice(&json_bytes).map_err(de::Error::custom)?;
 
+        let parsed_origin = Uri::try_from(value.origin.as_str()).map_err(de::Error::custom)?;
+        if parsed_origin.scheme().is_none() || parsed_origin.host().is_none() {
+            return Err(de::Error::custom("origin is not an absolute URL"));
+        }
+
         Ok(Self {
             challenge: value.challenge,
             cross_origin: value.cross_origin,
             origin: value.origin,
+            parsed_origin,
             top_origin: value.top_origin,
             r#type: value.r#type,
             raw: json_bytes,
@@ -184,6 +347,27 @@ pub enum Algorithm {
 }
 
 impl Algorithm {
+    /// The COSE algorithm identifier.
+    #[allow(clippy::as_conversions)]
+    pub fn value(&self) -> i32 {
+        *self as i32
+    }
+
+    /// Returns if the algorithm is recommended for new credentials, i.e., it is a fully specified
+    /// ECDSA, Ed25519, or RSASSA-PSS algorithm.
+    pub fn is_recommended(&self) -> bool {
+        matches!(
+            self,
+            Self::ESP256
+                | Self::ESP384
+                | Self::ESP512
+                | Self::ED25519
+                | Self::PS256
+                | Self::PS384
+                | Self::PS512
+        )
+    }
+
     pub fn id(&self) -> Id {
         match &self {
             Self::ED448 => Id::ED448,
@@ -218,9 +402,9 @@ impl TryFrom<i32> for Algorithm {
             -9 => Ok(Self::ESP256),
             -51 => Ok(Self::ESP384),
             -52 => Ok(Self::ESP512),
-            259 => Ok(Self::RS512),
-            258 => Ok(Self::RS384),
-            257 => Ok(Self::RS256),
+            -259 => Ok(Self::RS512),
+            -258 => Ok(Self::RS384),
+            -257 => Ok(Self::RS256),
             -8 => Ok(Self::EdDSA),
             -36 => Ok(Self::ES512),
             -35 => Ok(Self::ES384),
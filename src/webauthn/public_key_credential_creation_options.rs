@@ -3,8 +3,12 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::webauthn::public_key_credential::{
-    Algorithm, AuthenticatorAttachment, Hint, Transports, Type, UserVerification,
+use crate::{
+    Problem,
+    webauthn::public_key_credential::{
+        Algorithm, AuthenticatorAttachment, Hint, Transports, Type, UserVerification,
+        default_timeout, validate_timeout,
+    },
 };
 
 /// https://developer.mozilla.org/en-US/docs/Web/API/PublicKeyCredentialCreationOptions
@@ -14,10 +18,10 @@ pub struct PublicKeyCredentialCreationOptions {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub attestation: Option<Attestation>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub attestation_formats: Option<String>,
+    pub attestation_formats: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub authenticator_selection: Option<AuthenticatorSelection>,
-    #[serde(with = "crate::maybe_serde_base64")]
+    #[serde(default, with = "crate::maybe_serde_base64")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub challenge: Option<Vec<u8>>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -28,11 +32,22 @@ pub struct PublicKeyCredentialCreationOptions {
     pub public_key_parameters: Vec<PublicKeyParameters>,
     #[serde(rename = "rp")]
     pub relying_party: RelyingParty,
+    /// The time in milliseconds the caller is willing to wait for the ceremony to complete,
+    /// [`DEFAULT_TIMEOUT`](crate::webauthn::public_key_credential::DEFAULT_TIMEOUT) if absent.
+    #[serde(default = "default_timeout")]
     pub timeout: u64,
     pub user: User,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub hints: Option<Vec<Hint>>,
 }
+impl PublicKeyCredentialCreationOptions {
+    /// Validate that the options are within sensible bounds, i.e., the timeout is between
+    /// [`MIN_TIMEOUT`](crate::webauthn::public_key_credential::MIN_TIMEOUT) and
+    /// [`MAX_TIMEOUT`](crate::webauthn::public_key_credential::MAX_TIMEOUT).
+    pub fn validate(&self) -> Result<(), Problem> {
+        validate_timeout(self.timeout)
+    }
+}
 
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case")]
@@ -80,7 +95,11 @@ pub struct ExcludeCredentials {
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Extensions {
-    #[serde(rename = "credProp")]
+    /// Request the `credProps` extension, so the client reports if the credential is
+    /// discoverable in the credential's [`ClientExtensionResults`].
+    ///
+    /// [`ClientExtensionResults`]: crate::webauthn::public_key_credential::ClientExtensionResults
+    #[serde(rename = "credProps", alias = "credProp")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub return_credential_properties: Option<bool>,
 }
@@ -112,6 +131,10 @@ pub struct PublicKeyParameters {
 }
 
 impl PublicKeyParameters {
+    /// Every supported algorithm, in order of preference.
+    ///
+    /// This includes algorithms that are deprecated or not recommended, see [`Self::recommended`]
+    /// for the modern set.
     pub const ALL: [Self; 16] = [
         Self::new(Algorithm::ESP256),
         Self::new(Algorithm::ESP384),
@@ -131,6 +154,18 @@ impl PublicKeyParameters {
         Self::new(Algorithm::RS256),
     ];
 
+    /// The recommended algorithms, in order of preference, i.e., [`Self::ALL`] without the
+    /// deprecated and not recommended algorithms.
+    ///
+    /// Some authenticators only support the deprecated `ES256`, so relying parties that must
+    /// support them should add it to the end of this list.
+    pub fn recommended() -> Vec<Self> {
+        Self::ALL
+            .into_iter()
+            .filter(|parameters| parameters.algorithm.is_recommended())
+            .collect()
+    }
+
     pub const fn new(algorithm: Algorithm) -> Self {
         Self {
             algorithm,
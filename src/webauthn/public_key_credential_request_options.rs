@@ -2,7 +2,12 @@
 
 use serde::{Deserialize, Serialize};
 
-use crate::webauthn::public_key_credential::{Hint, Transports, Type, UserVerification};
+use crate::{
+    Problem,
+    webauthn::public_key_credential::{
+        Hint, Transports, Type, UserVerification, default_timeout, validate_timeout,
+    },
+};
 
 /// https://developer.mozilla.org/en-US/docs/Web/API/PublicKeyCredentialRequestOptions
 #[derive(Debug, Deserialize, Serialize)]
@@ -10,7 +15,7 @@ use crate::webauthn::public_key_credential::{Hint, Transports, Type, UserVerific
 pub struct PublicKeyCredentialRequestOptions {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub allow_credentials: Option<Vec<AllowCredentials>>,
-    #[serde(with = "crate::maybe_serde_base64")]
+    #[serde(default, with = "crate::maybe_serde_base64")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub challenge: Option<Vec<u8>>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -20,16 +25,28 @@ pub struct PublicKeyCredentialRequestOptions {
     #[serde(rename = "rpId")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub relying_party_id: Option<String>,
+    /// The time in milliseconds the caller is willing to wait for the ceremony to complete,
+    /// [`DEFAULT_TIMEOUT`](crate::webauthn::public_key_credential::DEFAULT_TIMEOUT) if absent.
+    #[serde(default = "default_timeout")]
     pub timeout: u64,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub user_verification: Option<UserVerification>,
 }
+impl PublicKeyCredentialRequestOptions {
+    /// Validate that the options are within sensible bounds, i.e., the timeout is between
+    /// [`MIN_TIMEOUT`](crate::webauthn::public_key_credential::MIN_TIMEOUT) and
+    /// [`MAX_TIMEOUT`](crate::webauthn::public_key_credential::MAX_TIMEOUT).
+    pub fn validate(&self) -> Result<(), Problem> {
+        validate_timeout(self.timeout)
+    }
+}
 
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AllowCredentials {
     #[serde(with = "crate::serde_base64")]
     pub id: Vec<u8>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub transports: Vec<Transports>,
     pub r#type: Type,
 }
@@ -2,12 +2,15 @@
 
 use core::{error::Error, fmt};
 
-use openssl::{hash::MessageDigest, pkey::PKey, sha::sha256};
+use jiff::{SignedDuration, Timestamp};
+use openssl::{hash::MessageDigest, memcmp, pkey::PKey, sha::sha256};
 
 use crate::webauthn::{
     challenge::Challenge,
     persisted_public_key::PersistedPublicKey,
-    public_key_credential::{Algorithm, ClientDataType, PublicKeyCredential, Response},
+    public_key_credential::{
+        Algorithm, ClientDataType, ClientExtensionResults, PublicKeyCredential, Response,
+    },
 };
 
 /// The result of verification
@@ -40,8 +43,106 @@ pub trait Verifier: fmt::Debug {
         raw_id: &[u8],
     ) -> impl Future<Output = Result<Option<PersistedPublicKey>, Self::Error>> + Send;
 
+    /// Persist the signature counter of a verified assertion, so [`CounterPolicy`] can detect a
+    /// cloned authenticator on the next assertion.
+    ///
+    /// Called after the signature is verified. Implementations must persist the counter for the
+    /// counter policy to be effective, and should not decrease it, so a regressed counter
+    /// accepted by [`CounterPolicy::WarnOnRegression`] does not hide later regressions. By
+    /// default, the counter is not persisted.
+    fn update_signature_counter(
+        &self,
+        _raw_id: &[u8],
+        _signature_counter: u32,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        async { Ok(()) }
+    }
+
     /// Return the relying party's ID.
     fn relying_party_id(&self) -> &str;
+
+    /// Return other IDs that assertions may be scoped to, for credentials registered to a parent
+    /// domain of the relying party's ID, e.g., `example.com` for `login.example.com`.
+    ///
+    /// Each ID must be a registrable domain suffix of [`Verifier::relying_party_id`], see
+    /// [`is_registrable_suffix`], other IDs are ignored. By default, there are none.
+    fn relying_party_id_suffixes(&self) -> &[String] {
+        &[]
+    }
+
+    /// Return how long after a challenge expires it is still accepted, to allow for ceremonies
+    /// completed in time but slowed by the network.
+    ///
+    /// By default, there is no leeway.
+    fn challenge_expiry_leeway(&self) -> SignedDuration {
+        SignedDuration::ZERO
+    }
+
+    /// Return the policy for validating the signature counter of assertions.
+    fn counter_policy(&self) -> CounterPolicy {
+        CounterPolicy::default()
+    }
+
+    /// Returns if the client extension results of a credential are acceptable, e.g., that
+    /// `credProps.rk` is `true` when registrations must create discoverable credentials.
+    ///
+    /// By default, every result is accepted.
+    fn accepts_extension_results(&self, _results: &ClientExtensionResults) -> bool {
+        true
+    }
+}
+
+/// Policy for validating the signature counter of an assertion against the persisted counter.
+///
+/// Authenticators that do not support counters always report `0`, which the FIDO specification
+/// permits. A counter that does not increase may indicate the authenticator has been cloned.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CounterPolicy {
+    /// Do not check the counter.
+    ///
+    /// Cloned authenticators will not be detected.
+    Ignore,
+
+    /// Log a warning if the counter did not increase, but accept the assertion.
+    ///
+    /// Cloned authenticators are only surfaced in the logs.
+    #[default]
+    WarnOnRegression,
+
+    /// Reject the assertion if the counter did not increase, unless both counters are zero.
+    ///
+    /// Cloned authenticators that support counters are rejected, while authenticators without
+    /// counter support remain usable.
+    RequireIncreasing,
+
+    /// Reject the assertion if the counter is zero or did not increase.
+    ///
+    /// Only authenticators that support counters are usable, suitable for high-security flows.
+    RequireNonZero,
+}
+
+impl CounterPolicy {
+    /// Returns if the received counter is acceptable given the persisted counter.
+    pub fn check(&self, persisted: i64, received: u32) -> bool {
+        let received = i64::from(received);
+        let is_increasing = received > persisted;
+        let is_unsupported = persisted == 0 && received == 0;
+
+        match self {
+            Self::Ignore => true,
+            Self::WarnOnRegression => {
+                if !is_increasing && !is_unsupported {
+                    log::warn!(
+                        "signature counter did not increase ({persisted} -> {received}), the authenticator may be cloned"
+                    );
+                }
+                true
+            }
+            Self::RequireIncreasing => is_increasing || is_unsupported,
+            Self::RequireNonZero => is_increasing,
+        }
+    }
 }
 
 impl PublicKeyCredential {
@@ -51,6 +152,16 @@ impl PublicKeyCredential {
         verifier: &V,
         bearer: Option<&[u8]>,
     ) -> Result<VerificationResult, VerificationError<V>> {
+        if let Err(error) = self.validate() {
+            log::warn!("credential is invalid: {error}");
+            return Ok(VerificationResult::Invalid);
+        }
+
+        if !verifier.accepts_extension_results(&self.client_extension_results) {
+            log::warn!("client extension results were not accepted");
+            return Ok(VerificationResult::Invalid);
+        }
+
         match &self.response {
             Response::AttestationResponse(_) => self.verify_attestation(verifier, bearer).await,
             Response::AssertionResponse(_) => self.verify_assertion(verifier, bearer).await,
@@ -85,8 +196,8 @@ impl PublicKeyCredential {
             .await
             .map_err(|source| VerificationError::GetChallenge { source })?
             .is_none_or(|challenge| {
-                !challenge.is_valid()
-                    || !challenge.is_for_origin(&response.client_data_json.origin)
+                !challenge.is_valid_at(Timestamp::now(), verifier.challenge_expiry_leeway())
+                    || !challenge.is_for_origin(&response.client_data_json.parsed_origin)
                     || challenge.identity_id.is_none()
                     || !challenge.is_for_bearer(Some(bearer))
             })
@@ -134,9 +245,25 @@ impl PublicKeyCredential {
             return Ok(VerificationResult::Invalid);
         }
 
-        // Check that the Relying Party ID is the one expected for this service.
-        let expected_hash = sha256(verifier.relying_party_id().as_bytes());
-        if response.authenticator_data.relying_party_id_hash != expected_hash {
+        // Check that the Relying Party ID is one expected for this service.
+        let relying_party_id = verifier.relying_party_id();
+        let suffixes = verifier
+            .relying_party_id_suffixes()
+            .iter()
+            .map(String::as_str)
+            .filter(|suffix| {
+                let is_registrable = is_registrable_suffix(relying_party_id, suffix);
+                if !is_registrable {
+                    log::warn!(
+                        "ignoring relying party ID suffix `{suffix}`, it is not a registrable suffix of `{relying_party_id}`"
+                    );
+                }
+                is_registrable
+            });
+        if !core::iter::once(relying_party_id)
+            .chain(suffixes)
+            .any(|id| response.authenticator_data.relying_party_id_hash == sha256(id.as_bytes()))
+        {
             return Ok(VerificationResult::Invalid);
         }
 
@@ -150,17 +277,17 @@ impl PublicKeyCredential {
         };
 
         // Verify the challenge is valid, and is for the origin.
-        if !challenge.is_valid()
-            || !challenge.is_for_origin(&response.client_data_json.origin)
+        if !challenge.is_valid_at(Timestamp::now(), verifier.challenge_expiry_leeway())
+            || !challenge.is_for_origin(&response.client_data_json.parsed_origin)
             || !challenge.is_for_bearer(bearer)
         {
             return Ok(VerificationResult::Invalid);
         };
 
         // If the challenge is associated with an identity, ensure it matches the assertion.
-        if let Some(identity_id) = challenge.identity_id
+        if let Some(identity_id) = challenge.identity_id.as_deref()
             && let Some(user_handle) = response.user_handle.as_deref()
-            && identity_id != user_handle
+            && !constant_time_eq(identity_id, user_handle)
         {
             return Ok(VerificationResult::Invalid);
         }
@@ -176,23 +303,28 @@ impl PublicKeyCredential {
 
         // Ensure key belongs to the asserted ID.
         if let Some(user_handle) = response.user_handle.as_deref()
-            && persisted_public_key.identity_id != user_handle
+            && !constant_time_eq(&persisted_public_key.identity_id, user_handle)
         {
             return Ok(VerificationResult::Invalid);
         }
 
-        // Get data to verify against
-        let contents = {
-            let authenticator_data_length = response.authenticator_data.raw.len();
-
-            let mut data = vec![0u8; authenticator_data_length + 32];
-
-            data[..authenticator_data_length].copy_from_slice(&response.authenticator_data.raw);
-            data[authenticator_data_length..]
-                .copy_from_slice(sha256(&response.client_data_json.raw).as_slice());
+        // Ensure key belongs to the challenge's identity, the user handle is optional so this must
+        // be checked even when it is absent.
+        if let Some(identity_id) = challenge.identity_id.as_deref()
+            && !constant_time_eq(&persisted_public_key.identity_id, identity_id)
+        {
+            return Ok(VerificationResult::Invalid);
+        }
 
-            data
-        };
+        // Ensure the signature counter is acceptable, before the more expensive signature
+        // verification.
+        if !verifier.counter_policy().check(
+            persisted_public_key.signature_counter,
+            response.authenticator_data.signature_counter,
+        ) {
+            log::warn!("signature counter rejected by counter policy");
+            return Ok(VerificationResult::Invalid);
+        }
 
         // Create the public key.
         let key = PKey::public_key_from_der(&persisted_public_key.public_key)
@@ -224,6 +356,19 @@ impl PublicKeyCredential {
             }
         };
 
+        // Get data to verify against
+        let contents = {
+            let authenticator_data_length = response.authenticator_data.raw.len();
+
+            let mut data = vec![0u8; authenticator_data_length + 32];
+
+            data[..authenticator_data_length].copy_from_slice(&response.authenticator_data.raw);
+            data[authenticator_data_length..]
+                .copy_from_slice(sha256(&response.client_data_json.raw).as_slice());
+
+            data
+        };
+
         // Verify the signature
         let is_valid = signature_verifier
             .verify_oneshot(&response.signature, &contents)
@@ -233,12 +378,52 @@ impl PublicKeyCredential {
             return Ok(VerificationResult::Invalid);
         }
 
+        verifier
+            .update_signature_counter(
+                &persisted_public_key.raw_id,
+                response.authenticator_data.signature_counter,
+            )
+            .await
+            .map_err(|source| VerificationError::UpdateSignatureCounter { source })?;
+
         Ok(VerificationResult::Valid {
             identity_id: persisted_public_key.identity_id,
         })
     }
 }
 
+/// Returns if `suffix` is the relying party's ID or a registrable domain suffix of it, so a
+/// credential scoped to `suffix` may be used by the relying party.
+///
+/// The suffix must end the ID at a label boundary and must not be a public suffix, e.g.,
+/// `example.com` is a registrable suffix of `login.example.com`, but `com`, `co.uk`, and
+/// `ample.com` are not.
+pub fn is_registrable_suffix(relying_party_id: &str, suffix: &str) -> bool {
+    let relying_party_id = relying_party_id.to_ascii_lowercase();
+    let suffix = suffix.to_ascii_lowercase();
+
+    if suffix == relying_party_id {
+        return true;
+    }
+
+    let is_label_suffix = relying_party_id
+        .strip_suffix(&suffix)
+        .is_some_and(|prefix| prefix.len() > 1 && prefix.ends_with('.'));
+
+    is_label_suffix && psl::domain_str(&suffix).is_some()
+}
+
+/// Compare two byte strings in time that depends only on their lengths, not on their contents.
+///
+/// Use this when comparing identifiers that an attacker could learn byte by byte from how long a
+/// comparison takes, e.g., the user handle of an assertion against an identity ID. The verifier
+/// uses it for those comparisons. The relying party ID hash and the signature counter are public,
+/// so they are compared normally. Credential IDs are looked up by [`Verifier::get_public_key`], so
+/// stores that match them in memory, rather than through an indexed query, should use this too.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && memcmp::eq(a, b)
+}
+
 /// Error variants from verification.
 #[derive(Debug)]
 #[non_exhaustive]
@@ -257,6 +442,13 @@ pub enum VerificationError<V: Verifier> {
         source: V::Error,
     },
 
+    /// The verifier failed to update the signature counter.
+    #[non_exhaustive]
+    UpdateSignatureCounter {
+        /// The source of the error.
+        source: V::Error,
+    },
+
     /// Failed to convert the DER bytes to an OpenSSL public key.
     #[non_exhaustive]
     PKeyFromDer {
@@ -283,6 +475,9 @@ impl<V: Verifier> fmt::Display for VerificationError<V> {
         match &self {
             Self::GetChallenge { .. } => write!(f, "the verifier failed to fetch the challenge"),
             Self::GetPublicKey { .. } => write!(f, "the verifier failed to fetch the public key"),
+            Self::UpdateSignatureCounter { .. } => {
+                write!(f, "the verifier failed to update the signature counter")
+            }
             Self::PKeyFromDer { .. } => write!(f, "OpenSSL failed to parse the public key"),
             Self::CreateSignatureVerifier { .. } => {
                 write!(f, "OpenSSL failed to create the signature verifier")
@@ -299,6 +494,7 @@ impl<V: Verifier> Error for VerificationError<V> {
         match &self {
             Self::GetChallenge { source, .. } => Some(source),
             Self::GetPublicKey { source, .. } => Some(source),
+            Self::UpdateSignatureCounter { source, .. } => Some(source),
             Self::PKeyFromDer { source, .. } => Some(source),
             Self::CreateSignatureVerifier { source, .. } => Some(source),
             Self::VerifierError { source, .. } => Some(source),
@@ -0,0 +1,222 @@
+#![allow(missing_docs, non_snake_case)]
+
+use axum::{
+    Extension, Router,
+    body::Body,
+    extract::{FromRequestParts, OptionalFromRequestParts},
+    routing::get,
+};
+use http::{Request, StatusCode, request::Parts};
+use std::{
+    env, fs,
+    time::{Duration, SystemTime},
+};
+
+use tower::ServiceExt;
+use ts_api_helper::{
+    ApiKey, ApiKeyValidationConfig, EmptyApiKeysPolicy, HasApiKeyValidationConfig, Lenient,
+    ReloadableApiKeys, api_key_layer,
+};
+
+struct State(ApiKeyValidationConfig);
+impl HasApiKeyValidationConfig for State {
+    fn api_key_config(&self) -> &ApiKeyValidationConfig {
+        &self.0
+    }
+}
+
+fn state() -> State {
+    State(ApiKeyValidationConfig {
+        allowed_api_keys: vec!["allowed-key".to_string()],
+        ..Default::default()
+    })
+}
+
+fn parts(api_key: Option<&str>) -> Parts {
+    let mut builder = Request::builder();
+    if let Some(api_key) = api_key {
+        builder = builder.header("X-TS-API-Key", api_key);
+    }
+    builder.body(()).unwrap().into_parts().0
+}
+
+#[tokio::test]
+async fn OptionalApiKey_Absent_IsNone() {
+    let result =
+        <ApiKey as OptionalFromRequestParts<_>>::from_request_parts(&mut parts(None), &state())
+            .await;
+    assert!(matches!(result, Ok(None)));
+}
+
+#[tokio::test]
+async fn OptionalApiKey_Valid_IsSome() {
+    let result = <ApiKey as OptionalFromRequestParts<_>>::from_request_parts(
+        &mut parts(Some("allowed-key")),
+        &state(),
+    )
+    .await;
+    assert!(matches!(result, Ok(Some(ApiKey(key))) if key == "allowed-key"));
+}
+
+#[tokio::test]
+async fn OptionalApiKey_Invalid_IsUnauthenticated() {
+    let result = <ApiKey as OptionalFromRequestParts<_>>::from_request_parts(
+        &mut parts(Some("other-key")),
+        &state(),
+    )
+    .await;
+    assert!(matches!(result, Err(error) if error.status == StatusCode::UNAUTHORIZED));
+}
+
+#[tokio::test]
+async fn LenientApiKey_Absent_IsNone() {
+    let result = Lenient::<ApiKey>::from_request_parts(&mut parts(None), &state()).await;
+    assert!(matches!(result, Ok(Lenient(None))));
+}
+
+#[tokio::test]
+async fn LenientApiKey_Valid_IsSome() {
+    let result =
+        Lenient::<ApiKey>::from_request_parts(&mut parts(Some("allowed-key")), &state()).await;
+    assert!(matches!(result, Ok(Lenient(Some(ApiKey(key)))) if key == "allowed-key"));
+}
+
+#[tokio::test]
+async fn LenientApiKey_Invalid_IsNone() {
+    let result =
+        Lenient::<ApiKey>::from_request_parts(&mut parts(Some("other-key")), &state()).await;
+    assert!(matches!(result, Ok(Lenient(None))));
+}
+
+#[test]
+fn ApiKeyValidationConfigValidate_EmptyAllowedApiKeys_IsError() {
+    let config: ApiKeyValidationConfig =
+        serde_json::from_str(r#"{"allowedApiKeys": [], "header": "X-TS-API-Key"}"#).unwrap();
+    assert!(config.validate().is_err());
+
+    assert!(state().0.validate().is_ok());
+
+    for when_empty in [EmptyApiKeysPolicy::DenyAll, EmptyApiKeysPolicy::AllowAll] {
+        let config = ApiKeyValidationConfig {
+            when_empty: Some(when_empty),
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok(), "{when_empty:?}");
+    }
+}
+
+#[tokio::test]
+async fn ApiKey_EmptyAllowedApiKeys_FollowsPolicy() {
+    let state = |when_empty| {
+        State(ApiKeyValidationConfig {
+            when_empty,
+            ..Default::default()
+        })
+    };
+
+    for when_empty in [None, Some(EmptyApiKeysPolicy::DenyAll)] {
+        let result = <ApiKey as FromRequestParts<_>>::from_request_parts(
+            &mut parts(Some("any-key")),
+            &state(when_empty),
+        )
+        .await;
+        assert!(
+            matches!(result, Err(error) if error.status == StatusCode::UNAUTHORIZED),
+            "{when_empty:?}"
+        );
+    }
+
+    let state = state(Some(EmptyApiKeysPolicy::AllowAll));
+    let result =
+        <ApiKey as FromRequestParts<_>>::from_request_parts(&mut parts(Some("any-key")), &state)
+            .await;
+    assert!(matches!(result, Ok(ApiKey(api_key)) if api_key == "any-key"));
+    let result =
+        <ApiKey as FromRequestParts<_>>::from_request_parts(&mut parts(None), &state).await;
+    assert!(matches!(result, Ok(ApiKey(api_key)) if api_key.is_empty()));
+}
+
+#[tokio::test]
+async fn ReloadableApiKeys_FileChanged_AcceptsNewKeys() {
+    let path = env::temp_dir().join(format!("api-keys-{}", uuid::Uuid::new_v4()));
+    fs::write(&path, "# rotated keys\nold-key\n").unwrap();
+
+    let state = ReloadableApiKeys::new(ApiKeyValidationConfig {
+        allowed_api_keys_file: Some(path.clone()),
+        ..Default::default()
+    })
+    .unwrap();
+
+    let result =
+        <ApiKey as FromRequestParts<_>>::from_request_parts(&mut parts(Some("old-key")), &state)
+            .await;
+    assert!(result.is_ok());
+
+    fs::write(&path, "new-key\n").unwrap();
+    // Ensure the change is visible on file systems with coarse modification times.
+    fs::File::options()
+        .write(true)
+        .open(&path)
+        .unwrap()
+        .set_modified(SystemTime::now() + Duration::from_secs(10))
+        .unwrap();
+
+    let reloader = state.spawn_reloader(Duration::from_millis(10));
+    let mut is_reloaded = false;
+    for _ in 0..100 {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let result = <ApiKey as FromRequestParts<_>>::from_request_parts(
+            &mut parts(Some("new-key")),
+            &state,
+        )
+        .await;
+        if result.is_ok() {
+            is_reloaded = true;
+            break;
+        }
+    }
+    assert!(is_reloaded);
+    let result =
+        <ApiKey as FromRequestParts<_>>::from_request_parts(&mut parts(Some("old-key")), &state)
+            .await;
+    assert!(matches!(result, Err(error) if error.status == StatusCode::UNAUTHORIZED));
+
+    drop(state);
+    reloader.await.unwrap();
+    fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn ApiKeyLayer_ProtectedRoutes_RequireTrustedKey() {
+    let router = Router::new()
+        .route("/a", get(|| async {}))
+        .route(
+            "/b",
+            get(|Extension(ApiKey(api_key)): Extension<ApiKey>| async move { api_key }),
+        )
+        .layer(api_key_layer(state().0));
+
+    for (path, api_key, status) in [
+        ("/a", None, StatusCode::UNAUTHORIZED),
+        ("/b", None, StatusCode::UNAUTHORIZED),
+        ("/a", Some("other-key"), StatusCode::UNAUTHORIZED),
+        ("/a", Some("allowed-key"), StatusCode::OK),
+        ("/b", Some("allowed-key"), StatusCode::OK),
+    ] {
+        let mut request = Request::builder().uri(path);
+        if let Some(api_key) = api_key {
+            request = request.header("X-TS-API-Key", api_key);
+        }
+        let request = request.body(Body::empty()).unwrap();
+
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), status, "{path} with {api_key:?}");
+
+        if path == "/b" && status == StatusCode::OK {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            assert_eq!(body, "allowed-key");
+        }
+    }
+}
@@ -0,0 +1,176 @@
+#![allow(missing_docs, non_snake_case)]
+
+use axum::extract::FromRequestParts;
+use http::{HeaderName, Request, StatusCode};
+use openssl::{
+    asn1::Asn1Time,
+    bn::BigNum,
+    ec::{EcGroup, EcKey},
+    hash::MessageDigest,
+    nid::Nid,
+    pkey::PKey,
+    x509::{X509, X509NameBuilder, extension::SubjectAlternativeName},
+};
+use ts_api_helper::{ClientCertificate, ClientCertificateFormat, HasClientCertificateHeader};
+
+struct Xfcc;
+impl HasClientCertificateHeader for Xfcc {}
+
+struct Pem(HeaderName);
+impl HasClientCertificateHeader for Pem {
+    fn client_certificate_header(&self) -> &HeaderName {
+        &self.0
+    }
+
+    fn client_certificate_format(&self) -> ClientCertificateFormat {
+        ClientCertificateFormat::Pem
+    }
+}
+
+async fn extract<S>(
+    state: &S,
+    header: Option<(&str, &str)>,
+) -> Result<ClientCertificate, StatusCode>
+where
+    S: Send + Sync + HasClientCertificateHeader,
+{
+    let mut request = Request::builder();
+    if let Some((name, value)) = header {
+        request = request.header(name, value);
+    }
+    let (mut parts, ()) = request.body(()).unwrap().into_parts();
+
+    ClientCertificate::from_request_parts(&mut parts, state)
+        .await
+        .map_err(|error| error.status)
+}
+
+/// A URL encoded, self-signed client certificate.
+fn escaped_certificate() -> String {
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+    let key = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+
+    let mut name = X509NameBuilder::new().unwrap();
+    name.append_entry_by_nid(Nid::ORGANIZATIONNAME, "Example, Inc.")
+        .unwrap();
+    name.append_entry_by_nid(Nid::COMMONNAME, "client").unwrap();
+    let name = name.build();
+
+    let mut builder = X509::builder().unwrap();
+    builder.set_version(2).unwrap();
+    builder
+        .set_serial_number(&BigNum::from_u32(1).unwrap().to_asn1_integer().unwrap())
+        .unwrap();
+    builder.set_subject_name(&name).unwrap();
+    builder.set_issuer_name(&name).unwrap();
+    builder.set_pubkey(&key).unwrap();
+    builder
+        .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+        .unwrap();
+    builder
+        .set_not_after(&Asn1Time::days_from_now(1).unwrap())
+        .unwrap();
+    let alt_names = SubjectAlternativeName::new()
+        .dns("client.example.com")
+        .uri("spiffe://cluster.local/ns/default/sa/client")
+        .build(&builder.x509v3_context(None, None))
+        .unwrap();
+    builder.append_extension(alt_names).unwrap();
+    builder.sign(&key, MessageDigest::sha256()).unwrap();
+
+    let pem = builder.build().to_pem().unwrap();
+    pem.iter()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                char::from(*byte).to_string()
+            }
+            byte => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn ClientCertificate_XfccSample_IsExtracted() {
+    let header = concat!(
+        "By=spiffe://cluster.local/ns/default/sa/backend;",
+        "Hash=468ed33be74eee6556d90c0149c1309e9ba61d6425303443c0748a02dd8de688;",
+        r#"Subject="CN=client,OU=Payments\, EMEA,O=Example";"#,
+        "URI=spiffe://cluster.local/ns/default/sa/client;",
+        "DNS=client.example.com;DNS=client.internal,",
+        "By=spiffe://cluster.local/ns/default/sa/frontend;URI=spiffe://cluster.local/ns/default/sa/proxy"
+    );
+
+    let certificate = extract(&Xfcc, Some(("x-forwarded-client-cert", header)))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        certificate,
+        ClientCertificate {
+            subject: Some(r"CN=client,OU=Payments\, EMEA,O=Example".to_string()),
+            dns_names: vec![
+                "client.example.com".to_string(),
+                "client.internal".to_string()
+            ],
+            uris: vec!["spiffe://cluster.local/ns/default/sa/client".to_string()],
+        }
+    );
+}
+
+#[tokio::test]
+async fn ClientCertificate_XfccWithCert_IsReadFromCertificate() {
+    let header = format!(
+        r#"Hash=468ed33be74eee6556d90c0149c1309e9ba61d6425303443c0748a02dd8de688;Cert="{}";Subject="""#,
+        escaped_certificate()
+    );
+
+    let certificate = extract(&Xfcc, Some(("x-forwarded-client-cert", &header)))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        certificate.subject.as_deref(),
+        Some(r"CN=client,O=Example\, Inc.")
+    );
+    assert_eq!(certificate.dns_names, ["client.example.com"]);
+    assert_eq!(
+        certificate.uris,
+        ["spiffe://cluster.local/ns/default/sa/client"]
+    );
+}
+
+#[tokio::test]
+async fn ClientCertificate_Pem_IsExtracted() {
+    let state = Pem(HeaderName::from_static("x-client-cert"));
+
+    let certificate = extract(&state, Some(("x-client-cert", &escaped_certificate())))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        certificate.subject.as_deref(),
+        Some(r"CN=client,O=Example\, Inc.")
+    );
+    assert_eq!(certificate.dns_names, ["client.example.com"]);
+}
+
+#[tokio::test]
+async fn ClientCertificate_MissingOrMalformed_IsForbidden() {
+    assert_eq!(extract(&Xfcc, None).await, Err(StatusCode::FORBIDDEN));
+
+    for value in [
+        "Hash=468ed33be74eee6556d90c0149c1309e9ba61d6425303443c0748a02dd8de688",
+        r#"Subject="CN=client"#,
+        "Subject",
+        r#"Cert="-----BEGIN%20CERTIFICATE-----""#,
+    ] {
+        let result = extract(&Xfcc, Some(("x-forwarded-client-cert", value))).await;
+        assert_eq!(result, Err(StatusCode::FORBIDDEN), "{value}");
+    }
+
+    let state = Pem(HeaderName::from_static("x-client-cert"));
+    assert_eq!(
+        extract(&state, Some(("x-client-cert", "not a certificate"))).await,
+        Err(StatusCode::FORBIDDEN)
+    );
+}
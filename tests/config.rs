@@ -0,0 +1,79 @@
+#![allow(missing_docs, non_snake_case)]
+
+use serde_json::json;
+#[cfg(feature = "reqwest")]
+use ts_api_helper::HttpClientConfig;
+use ts_api_helper::{
+    ApiKeyValidationConfig,
+    token::config::{TokenIssuingConfig, TokenValidationConfig},
+};
+
+#[test]
+fn TokenValidationConfig_MisspelledKey_IsRejected() {
+    let config = json!({
+        "jwksEndpont": "http://localhost:8081/.well-known/jwks.json",
+        "revocationEndpoint": "http://localhost:8081/revoked-tokens",
+    });
+
+    let error = serde_json::from_value::<TokenValidationConfig>(config).unwrap_err();
+
+    assert!(error.to_string().contains("jwksEndpont"));
+}
+
+#[test]
+fn Configs_Defaults_RoundTrip() {
+    let validation = serde_json::to_value(TokenValidationConfig::default()).unwrap();
+    let issuing = serde_json::to_value(TokenIssuingConfig::default()).unwrap();
+    let api_key = serde_json::to_value(ApiKeyValidationConfig::default()).unwrap();
+
+    assert!(serde_json::from_value::<TokenValidationConfig>(validation).is_ok());
+    assert!(serde_json::from_value::<TokenIssuingConfig>(issuing).is_ok());
+    assert!(serde_json::from_value::<ApiKeyValidationConfig>(api_key).is_ok());
+}
+
+#[cfg(feature = "reqwest")]
+#[test]
+fn HttpClientConfig_Default_RoundTrips() {
+    let http_client = serde_json::to_value(HttpClientConfig::default()).unwrap();
+
+    assert!(serde_json::from_value::<HttpClientConfig>(http_client).is_ok());
+}
+
+#[test]
+fn ApiKeyValidationConfig_UnknownKey_IsRejected() {
+    let config = json!({
+        "allowedApiKeys": ["key"],
+        "header": "X-API-Key",
+        "allowedApiKey": "key",
+    });
+
+    assert!(serde_json::from_value::<ApiKeyValidationConfig>(config).is_err());
+}
+
+#[cfg(feature = "reqwest")]
+#[test]
+fn HttpClientConfig_Debug_RedactsApiKey() {
+    let http_client: HttpClientConfig = serde_json::from_value(json!({
+        "apiKeyHeader": "X-TS-API-Key",
+        "apiKey": "secret-client-key",
+    }))
+    .unwrap();
+
+    let http_client = format!("{http_client:?}");
+
+    assert!(!http_client.contains("secret-client-key"));
+    assert!(http_client.contains("X-TS-API-Key"));
+}
+
+#[test]
+fn ApiKeyValidationConfig_Debug_RedactsKeys() {
+    let api_key = ApiKeyValidationConfig {
+        allowed_api_keys: vec!["secret-allowed-key".to_string()],
+        ..Default::default()
+    };
+
+    let api_key = format!("{api_key:?}");
+
+    assert!(!api_key.contains("secret-allowed-key"));
+    assert!(api_key.contains("X-TS-API-Key"));
+}
@@ -0,0 +1,126 @@
+#![allow(missing_docs, non_snake_case)]
+
+use axum::{Router, body::Body, extract::Request, routing::get};
+use http::{
+    HeaderMap, HeaderName, HeaderValue, Method, Response, Uri,
+    header::{
+        ACCEPT, ACCEPT_ENCODING, ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_ORIGIN,
+        ACCESS_CONTROL_REQUEST_HEADERS, ACCESS_CONTROL_REQUEST_METHOD, ORIGIN, VARY,
+    },
+};
+use tower::ServiceExt;
+use ts_api_helper::{PerOriginCorsLayer, append_vary, cors_layer};
+
+const ADMIN_ORIGIN: &str = "https://admin.example.com";
+const PUBLIC_ORIGIN: &str = "https://example.com";
+const ADMIN_HEADER: HeaderName = HeaderName::from_static("x-admin-action");
+
+fn router() -> Router {
+    let admin_origin = Uri::from_static(ADMIN_ORIGIN);
+    let public_origin = Uri::from_static(PUBLIC_ORIGIN);
+
+    let layer = PerOriginCorsLayer::new(cors_layer(vec![public_origin], &[], &[])).with_override(
+        vec![admin_origin.clone()],
+        cors_layer(vec![admin_origin], &[ADMIN_HEADER], &[]),
+    );
+
+    Router::new().route("/", get(|| async {})).layer(layer)
+}
+
+async fn preflight(origin: &str) -> Response<Body> {
+    let request = Request::builder()
+        .method(Method::OPTIONS)
+        .uri("/")
+        .header(ORIGIN, origin)
+        .header(ACCESS_CONTROL_REQUEST_METHOD, "GET")
+        .header(ACCESS_CONTROL_REQUEST_HEADERS, ADMIN_HEADER.as_str())
+        .body(Body::empty())
+        .unwrap();
+
+    router().oneshot(request).await.unwrap()
+}
+
+fn allows_admin_header(response: &Response<Body>) -> bool {
+    response
+        .headers()
+        .get(ACCESS_CONTROL_ALLOW_HEADERS)
+        .and_then(|headers| headers.to_str().ok())
+        .is_some_and(|headers| headers.contains(ADMIN_HEADER.as_str()))
+}
+
+#[tokio::test]
+async fn PerOriginCors_OverriddenOrigin_UsesOverridePolicy() {
+    let response = preflight(ADMIN_ORIGIN).await;
+
+    assert!(allows_admin_header(&response));
+    assert_eq!(
+        response.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+        ADMIN_ORIGIN
+    );
+}
+
+#[tokio::test]
+async fn PerOriginCors_OtherOrigin_UsesDefaultPolicy() {
+    let response = preflight(PUBLIC_ORIGIN).await;
+
+    assert!(!allows_admin_header(&response));
+    assert_eq!(
+        response.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+        PUBLIC_ORIGIN
+    );
+}
+
+#[tokio::test]
+async fn PerOriginCors_AdminOriginOnDefaultPolicy_IsNotAllowed() {
+    let layer =
+        PerOriginCorsLayer::new(cors_layer(vec![Uri::from_static(PUBLIC_ORIGIN)], &[], &[]));
+    let router = Router::new().route("/", get(|| async {})).layer(layer);
+
+    let request = Request::builder()
+        .uri("/")
+        .header(ORIGIN, ADMIN_ORIGIN)
+        .body(Body::empty())
+        .unwrap();
+    let response = router.oneshot(request).await.unwrap();
+
+    assert!(
+        response
+            .headers()
+            .get(ACCESS_CONTROL_ALLOW_ORIGIN)
+            .is_none()
+    );
+}
+
+#[tokio::test]
+async fn CorsLayer_Response_VariesOnOrigin() {
+    let request = Request::builder()
+        .uri("/")
+        .header(ORIGIN, PUBLIC_ORIGIN)
+        .body(Body::empty())
+        .unwrap();
+    let response = router().oneshot(request).await.unwrap();
+
+    let vary = response.headers().get(VARY).unwrap().to_str().unwrap();
+    assert!(vary.split(',').any(|name| name.trim() == "origin"));
+}
+
+#[test]
+fn AppendVary_ExistingValues_AreKeptAndNotDuplicated() {
+    let mut headers = HeaderMap::new();
+    headers.insert(VARY, HeaderValue::from_static("Origin"));
+
+    append_vary(&mut headers, &[ORIGIN, ACCEPT, ACCEPT_ENCODING, ACCEPT]);
+
+    let values: Vec<_> = headers.get_all(VARY).iter().collect();
+    assert_eq!(values, ["Origin", "accept, accept-encoding"]);
+}
+
+#[test]
+fn AppendVary_Wildcard_IsUnchanged() {
+    let mut headers = HeaderMap::new();
+    headers.insert(VARY, HeaderValue::from_static("*"));
+
+    append_vary(&mut headers, &[ACCEPT]);
+
+    assert_eq!(headers.get_all(VARY).iter().count(), 1);
+}
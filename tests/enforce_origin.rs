@@ -0,0 +1,69 @@
+#![allow(missing_docs, non_snake_case)]
+
+use axum::extract::FromRequestParts;
+use http::{
+    Request, StatusCode, Uri,
+    header::{ORIGIN, REFERER},
+};
+use ts_api_helper::{EnforceOrigin, HasAllowedOrigins};
+
+struct State {
+    allowed_origins: Vec<Uri>,
+}
+impl HasAllowedOrigins for State {
+    fn allowed_origins(&self) -> &[Uri] {
+        &self.allowed_origins
+    }
+}
+
+async fn extract(header: Option<(&str, &str)>) -> Result<EnforceOrigin, StatusCode> {
+    let state = State {
+        allowed_origins: vec![Uri::from_static("https://example.com")],
+    };
+
+    let mut request = Request::builder();
+    if let Some((name, value)) = header {
+        request = request.header(name, value);
+    }
+    let (mut parts, ()) = request.body(()).unwrap().into_parts();
+
+    EnforceOrigin::from_request_parts(&mut parts, &state)
+        .await
+        .map_err(|error| error.status)
+}
+
+#[tokio::test]
+async fn EnforceOrigin_AllowedOrigin_IsExtracted() {
+    let EnforceOrigin(origin) = extract(Some((ORIGIN.as_str(), "https://example.com")))
+        .await
+        .unwrap();
+
+    assert_eq!(origin.host(), Some("example.com"));
+}
+
+#[tokio::test]
+async fn EnforceOrigin_AllowedReferer_IsExtracted() {
+    let result = extract(Some((
+        REFERER.as_str(),
+        "https://example.com/account?tab=1",
+    )))
+    .await;
+
+    assert!(result.is_ok());
+}
+
+#[tokio::test]
+async fn EnforceOrigin_DisallowedOrigin_IsForbidden() {
+    let result = extract(Some((ORIGIN.as_str(), "https://evil.example.net"))).await;
+
+    assert_eq!(result.err(), Some(StatusCode::FORBIDDEN));
+}
+
+#[tokio::test]
+async fn EnforceOrigin_MissingOrigin_IsForbidden() {
+    assert_eq!(extract(None).await.err(), Some(StatusCode::FORBIDDEN));
+    assert_eq!(
+        extract(Some((ORIGIN.as_str(), "null"))).await.err(),
+        Some(StatusCode::FORBIDDEN)
+    );
+}
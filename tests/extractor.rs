@@ -0,0 +1,608 @@
+#![allow(missing_docs, non_snake_case)]
+#![cfg(feature = "reqwest")]
+
+use std::{
+    io::{Read, Write},
+    net::TcpListener,
+    thread,
+};
+
+use core::time::Duration;
+
+use axum::extract::{FromRequestParts, OptionalFromRequestParts};
+use bb8::Pool;
+use bb8_postgres::PostgresConnectionManager;
+use http::{
+    HeaderName, Request, StatusCode,
+    header::{AUTHORIZATION, COOKIE},
+    request::Parts,
+};
+use jiff::{SignedDuration, Timestamp};
+use openssl::{ecdsa::EcdsaSig, hash::MessageDigest, sign::Signer};
+use reqwest::Client;
+use tokio_postgres::NoTls;
+use ts_api_helper::{
+    HasHttpClient, Lenient,
+    token::{
+        Algorithm, DbRevocation, JsonWebKeySetCache, SigningJsonWebKey, TestClock,
+        VerifyingJsonWebKey,
+        extractor::{
+            CommonToken, ConsentAction, CookieToken, HasKeySetCache, HasRevocationEndpoint,
+            ProvisioningToken, RequireConsent, RequireTokenType, Token, TokenValidationOptions,
+        },
+        json_web_token::TokenType,
+    },
+};
+
+struct State {
+    jwks_caches: Vec<JsonWebKeySetCache>,
+    options: TokenValidationOptions,
+    revocation_endpoint: String,
+    revocation_database: Option<DbRevocation>,
+    client: Client,
+}
+impl HasKeySetCache for State {
+    fn jwks_cache(&self) -> &JsonWebKeySetCache {
+        &self.jwks_caches[0]
+    }
+
+    fn jwks_caches(&self) -> &[JsonWebKeySetCache] {
+        &self.jwks_caches
+    }
+
+    fn token_validation_options(&self) -> &TokenValidationOptions {
+        &self.options
+    }
+}
+impl HasRevocationEndpoint for State {
+    fn revocation_endpoint(&self) -> &str {
+        &self.revocation_endpoint
+    }
+
+    fn revocation_database(&self) -> Option<&DbRevocation> {
+        self.revocation_database.as_ref()
+    }
+}
+impl HasHttpClient for State {
+    fn http_client(&self) -> &Client {
+        &self.client
+    }
+}
+
+/// Serve `404 Not Found` to every request, so no token is revoked, returning the URL.
+fn serve_not_revoked() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/revoked-tokens", listener.local_addr().unwrap());
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else {
+                continue;
+            };
+            let mut request = [0u8; 4096];
+            let _ = stream.read(&mut request);
+            let _ = stream.write_all(
+                b"HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+            );
+        }
+    });
+
+    url
+}
+
+/// Create a cache holding a single key that will not be refreshed.
+async fn jwks_cache(signing_key: &SigningJsonWebKey, client: &Client) -> JsonWebKeySetCache {
+    let jwks_cache =
+        JsonWebKeySetCache::new("http://127.0.0.1:1/jwks.json".to_string(), client.clone());
+    jwks_cache.cache.write().await.insert(
+        signing_key.jwk.kid.clone(),
+        VerifyingJsonWebKey::try_from(signing_key.jwk.clone()).unwrap(),
+    );
+    *jwks_cache.last_refresh.write().await = Timestamp::now();
+    jwks_cache
+}
+
+/// Create a state that trusts the signing key.
+async fn state(signing_key: &SigningJsonWebKey) -> State {
+    let client = Client::new();
+
+    State {
+        jwks_caches: vec![jwks_cache(signing_key, &client).await],
+        options: TokenValidationOptions::default(),
+        revocation_endpoint: serve_not_revoked(),
+        revocation_database: None,
+        client,
+    }
+}
+
+fn parts(signing_key: &SigningJsonWebKey, token_type: TokenType) -> Parts {
+    let token = signing_key
+        .issue("subject".to_string(), token_type)
+        .unwrap();
+
+    Request::builder()
+        .header(AUTHORIZATION, format!("bearer {}", token.serialize()))
+        .body(())
+        .unwrap()
+        .into_parts()
+        .0
+}
+
+#[tokio::test]
+async fn RequireTokenType_MatchingType_IsExtracted() {
+    let signing_key = SigningJsonWebKey::generate("kid".to_string(), Algorithm::ES256).unwrap();
+    let state = state(&signing_key).await;
+
+    let result = RequireTokenType::<CommonToken>::from_request_parts(
+        &mut parts(&signing_key, TokenType::Common),
+        &state,
+    )
+    .await;
+
+    assert!(matches!(result, Ok(RequireTokenType(token, _)) if token.claims.sub == "subject"));
+}
+
+#[tokio::test]
+async fn RequireTokenType_OtherType_IsForbidden() {
+    let signing_key = SigningJsonWebKey::generate("kid".to_string(), Algorithm::ES256).unwrap();
+    let state = state(&signing_key).await;
+
+    let result = RequireTokenType::<CommonToken>::from_request_parts(
+        &mut parts(&signing_key, TokenType::Provisioning),
+        &state,
+    )
+    .await;
+    assert!(matches!(result, Err(error) if error.status == StatusCode::FORBIDDEN));
+
+    let result = RequireTokenType::<ProvisioningToken>::from_request_parts(
+        &mut parts(&signing_key, TokenType::Provisioning),
+        &state,
+    )
+    .await;
+    assert!(result.is_ok());
+}
+
+#[tokio::test]
+async fn RequireTokenType_AuthenticationFailures_AreUnauthenticated() {
+    let signing_key = SigningJsonWebKey::generate("kid".to_string(), Algorithm::ES256).unwrap();
+    let state = state(&signing_key).await;
+
+    let untrusted_key = SigningJsonWebKey::generate("kid".to_string(), Algorithm::ES256).unwrap();
+    let untrusted = untrusted_key
+        .issue("subject".to_string(), TokenType::Common)
+        .unwrap();
+    let expired = signing_key
+        .issue_with_clock(
+            "subject".to_string(),
+            TokenType::Common,
+            &TestClock::new(Timestamp::now() - SignedDuration::from_hours(365 * 24)),
+        )
+        .unwrap();
+
+    for authorization in [
+        None,
+        Some("Bearer not-a-token".to_string()),
+        Some(format!("Bearer {}", untrusted.serialize())),
+        Some(format!("Bearer {}", expired.serialize())),
+    ] {
+        let mut request = Request::builder();
+        if let Some(authorization) = &authorization {
+            request = request.header(AUTHORIZATION, authorization);
+        }
+        let mut parts = request.body(()).unwrap().into_parts().0;
+
+        let result = RequireTokenType::<CommonToken>::from_request_parts(&mut parts, &state).await;
+        assert!(
+            matches!(&result, Err(error) if error.status == StatusCode::UNAUTHORIZED),
+            "{authorization:?}"
+        );
+    }
+}
+
+struct TransferFunds;
+impl ConsentAction for TransferFunds {
+    const ACTION: &'static str = "transfer:funds";
+}
+
+fn consent(actions: &[&str]) -> TokenType {
+    TokenType::Consent {
+        act: actions.iter().map(ToString::to_string).collect(),
+    }
+}
+
+#[tokio::test]
+async fn RequireConsent_MatchingAction_IsExtracted() {
+    let signing_key = SigningJsonWebKey::generate("kid".to_string(), Algorithm::ES256).unwrap();
+    let state = state(&signing_key).await;
+
+    let result = RequireConsent::<TransferFunds>::from_request_parts(
+        &mut parts(&signing_key, consent(&["read:balance", "transfer:funds"])),
+        &state,
+    )
+    .await;
+
+    assert!(result.is_ok());
+}
+
+#[tokio::test]
+async fn RequireConsent_MismatchedAction_IsForbidden() {
+    let signing_key = SigningJsonWebKey::generate("kid".to_string(), Algorithm::ES256).unwrap();
+    let state = state(&signing_key).await;
+
+    let result = RequireConsent::<TransferFunds>::from_request_parts(
+        &mut parts(&signing_key, consent(&["delete:account"])),
+        &state,
+    )
+    .await;
+    assert!(matches!(result, Err(error) if error.status == StatusCode::FORBIDDEN));
+
+    let result = RequireConsent::<TransferFunds>::from_request_parts(
+        &mut parts(&signing_key, TokenType::Common),
+        &state,
+    )
+    .await;
+    assert!(matches!(result, Err(error) if error.status == StatusCode::FORBIDDEN));
+}
+
+fn token_in_header(signing_key: &SigningJsonWebKey, name: &str, value: &str) -> Parts {
+    let token = signing_key
+        .issue("subject".to_string(), TokenType::Common)
+        .unwrap();
+
+    Request::builder()
+        .header(name, value.replace("{token}", &token.serialize()))
+        .body(())
+        .unwrap()
+        .into_parts()
+        .0
+}
+
+#[tokio::test]
+async fn Token_BearerSchemeAnyCase_IsExtracted() {
+    let signing_key = SigningJsonWebKey::generate("kid".to_string(), Algorithm::ES256).unwrap();
+    let state = state(&signing_key).await;
+
+    for value in ["Bearer {token}", "bearer {token}"] {
+        let mut parts = token_in_header(&signing_key, AUTHORIZATION.as_str(), value);
+        let result = <Token as FromRequestParts<_>>::from_request_parts(&mut parts, &state).await;
+        assert!(result.is_ok());
+    }
+}
+
+#[tokio::test]
+async fn Token_CustomHeaderWithoutScheme_IsExtracted() {
+    let signing_key = SigningJsonWebKey::generate("kid".to_string(), Algorithm::ES256).unwrap();
+    let mut state = state(&signing_key).await;
+    state.options = TokenValidationOptions::default()
+        .header(HeaderName::from_static("x-forwarded-access-token"), false);
+
+    let mut parts = token_in_header(&signing_key, "x-forwarded-access-token", "{token}");
+    let result = <Token as FromRequestParts<_>>::from_request_parts(&mut parts, &state).await;
+    assert!(result.is_ok());
+
+    let mut parts = token_in_header(&signing_key, AUTHORIZATION.as_str(), "Bearer {token}");
+    let result = <Token as FromRequestParts<_>>::from_request_parts(&mut parts, &state).await;
+    assert!(matches!(result, Err(error) if error.status == StatusCode::UNAUTHORIZED));
+}
+
+/// Issue a token claiming an issuer, signed with the key.
+fn token_from_issuer(signing_key: &SigningJsonWebKey, issuer: &str) -> Parts {
+    let mut token = signing_key
+        .issue("subject".to_string(), TokenType::Common)
+        .unwrap();
+    token.claims.iss = Some(issuer.into());
+
+    let mut signer = Signer::new(MessageDigest::sha256(), &signing_key.key).unwrap();
+    let der = signer
+        .sign_oneshot_to_vec(token.signing_input().as_bytes())
+        .unwrap();
+    let signature = EcdsaSig::from_der(&der).unwrap();
+    token.signature = signature.r().to_vec_padded(32).unwrap();
+    token
+        .signature
+        .extend(signature.s().to_vec_padded(32).unwrap());
+
+    Request::builder()
+        .header(AUTHORIZATION, format!("Bearer {}", token.serialize()))
+        .body(())
+        .unwrap()
+        .into_parts()
+        .0
+}
+
+#[tokio::test]
+async fn Token_TwoIssuers_KeysOnlyAuthenticateTheirIssuer() {
+    let key_a = SigningJsonWebKey::generate("a".to_string(), Algorithm::ES256).unwrap();
+    let key_b = SigningJsonWebKey::generate("b".to_string(), Algorithm::ES256).unwrap();
+    let mut state = state(&key_a).await;
+    state.jwks_caches = vec![
+        jwks_cache(&key_a, &state.client)
+            .await
+            .with_issuers(vec!["https://a.example".to_string()]),
+        jwks_cache(&key_b, &state.client)
+            .await
+            .with_issuers(vec!["https://b.example".to_string()]),
+    ];
+
+    for (key, issuer) in [(&key_a, "https://a.example"), (&key_b, "https://b.example")] {
+        let mut parts = token_from_issuer(key, issuer);
+        let result = <Token as FromRequestParts<_>>::from_request_parts(&mut parts, &state).await;
+        assert!(result.is_ok());
+    }
+
+    for (key, issuer) in [
+        (&key_a, "https://b.example"),
+        (&key_b, "https://a.example"),
+        (&key_a, "https://c.example"),
+    ] {
+        let mut parts = token_from_issuer(key, issuer);
+        let result = <Token as FromRequestParts<_>>::from_request_parts(&mut parts, &state).await;
+        assert!(matches!(result, Err(error) if error.status == StatusCode::UNAUTHORIZED));
+    }
+}
+
+#[tokio::test]
+async fn Token_UnboundPrimaryKeySet_DoesNotAuthenticateBoundIssuer() {
+    let primary_key = SigningJsonWebKey::generate("primary".to_string(), Algorithm::ES256).unwrap();
+    let partner_key = SigningJsonWebKey::generate("partner".to_string(), Algorithm::ES256).unwrap();
+    let mut state = state(&primary_key).await;
+    state.jwks_caches = vec![
+        jwks_cache(&primary_key, &state.client).await,
+        jwks_cache(&partner_key, &state.client)
+            .await
+            .with_issuers(vec!["https://partner.example".to_string()]),
+    ];
+
+    for (key, issuer) in [
+        (&partner_key, "https://partner.example"),
+        (&primary_key, "https://primary.example"),
+    ] {
+        let mut parts = token_from_issuer(key, issuer);
+        let result = <Token as FromRequestParts<_>>::from_request_parts(&mut parts, &state).await;
+        assert!(result.is_ok(), "{issuer}");
+    }
+
+    let mut parts = token_from_issuer(&primary_key, "https://partner.example");
+    let result = <Token as FromRequestParts<_>>::from_request_parts(&mut parts, &state).await;
+    assert!(matches!(result, Err(error) if error.status == StatusCode::UNAUTHORIZED));
+}
+
+#[tokio::test]
+async fn Token_SingleBoundKeySet_RejectsOtherIssuer() {
+    let signing_key = SigningJsonWebKey::generate("kid".to_string(), Algorithm::ES256).unwrap();
+    let mut state = state(&signing_key).await;
+    state.jwks_caches = vec![
+        jwks_cache(&signing_key, &state.client)
+            .await
+            .with_issuers(vec!["https://a.example".to_string()]),
+    ];
+
+    let mut parts = token_from_issuer(&signing_key, "https://b.example");
+    let result = <Token as FromRequestParts<_>>::from_request_parts(&mut parts, &state).await;
+
+    assert!(matches!(result, Err(error) if error.status == StatusCode::UNAUTHORIZED));
+}
+
+#[tokio::test]
+async fn Token_RevocationDatabase_IsCheckedInsteadOfEndpoint() {
+    let signing_key = SigningJsonWebKey::generate("kid".to_string(), Algorithm::ES256).unwrap();
+    let mut state = state(&signing_key).await;
+
+    let manager =
+        PostgresConnectionManager::new_from_stringlike("postgres://127.0.0.1:1", NoTls).unwrap();
+    let pool = Pool::builder()
+        .connection_timeout(Duration::from_millis(200))
+        .build_unchecked(manager);
+    state.revocation_database = Some(DbRevocation::new(pool));
+
+    // The endpoint would report the token as not revoked, but the database is unreachable.
+    let mut parts = parts(&signing_key, TokenType::Common);
+    let result = <Token as FromRequestParts<_>>::from_request_parts(&mut parts, &state).await;
+
+    assert!(matches!(result, Err(error) if error.status == StatusCode::INTERNAL_SERVER_ERROR));
+}
+
+fn token_in_cookies(signing_key: &SigningJsonWebKey, cookies: &[&str]) -> Parts {
+    let token = signing_key
+        .issue("subject".to_string(), TokenType::Common)
+        .unwrap()
+        .serialize();
+
+    let mut request = Request::builder();
+    for cookie in cookies {
+        request = request.header(COOKIE, cookie.replace("{token}", &token));
+    }
+
+    request.body(()).unwrap().into_parts().0
+}
+
+#[tokio::test]
+async fn CookieToken_PresentCookie_IsExtracted() {
+    let signing_key = SigningJsonWebKey::generate("kid".to_string(), Algorithm::ES256).unwrap();
+    let state = state(&signing_key).await;
+
+    for cookies in [
+        &["theme=dark; access_token={token}"][..],
+        &["access_token=\"{token}\""],
+        &["theme=dark", "access_token={token}"],
+        &["access_token={token}; access_token={token}"],
+    ] {
+        let mut parts = token_in_cookies(&signing_key, cookies);
+        let result =
+            <CookieToken as FromRequestParts<_>>::from_request_parts(&mut parts, &state).await;
+        assert!(matches!(result, Ok(CookieToken(token)) if token.claims.sub == "subject"));
+    }
+}
+
+#[tokio::test]
+async fn CookieToken_PercentEncodedCookie_IsExtracted() {
+    let signing_key = SigningJsonWebKey::generate("kid".to_string(), Algorithm::ES256).unwrap();
+    let mut state = state(&signing_key).await;
+    state.options = TokenValidationOptions::default().cookie("session".to_string());
+
+    let token = signing_key
+        .issue("subject".to_string(), TokenType::Common)
+        .unwrap()
+        .serialize()
+        .replace('.', "%2E");
+    let mut parts = Request::builder()
+        .header(COOKIE, format!("session={token}"))
+        .body(())
+        .unwrap()
+        .into_parts()
+        .0;
+    let result = <CookieToken as FromRequestParts<_>>::from_request_parts(&mut parts, &state).await;
+
+    assert!(result.is_ok());
+}
+
+#[tokio::test]
+async fn CookieToken_AbsentCookie_IsUnauthenticated() {
+    let signing_key = SigningJsonWebKey::generate("kid".to_string(), Algorithm::ES256).unwrap();
+    let state = state(&signing_key).await;
+
+    for cookies in [&[][..], &["theme=dark"], &["session={token}"]] {
+        let mut parts = token_in_cookies(&signing_key, cookies);
+        let result =
+            <CookieToken as FromRequestParts<_>>::from_request_parts(&mut parts, &state).await;
+        assert!(matches!(result, Err(error) if error.status == StatusCode::UNAUTHORIZED));
+
+        let result =
+            <CookieToken as OptionalFromRequestParts<_>>::from_request_parts(&mut parts, &state)
+                .await;
+        assert!(matches!(result, Ok(None)));
+    }
+}
+
+#[tokio::test]
+async fn CookieToken_MalformedCookie_IsUnauthenticated() {
+    let signing_key = SigningJsonWebKey::generate("kid".to_string(), Algorithm::ES256).unwrap();
+    let state = state(&signing_key).await;
+
+    for cookies in [
+        &["access_token=not-a-token"][..],
+        &["access_token={token}%"],
+        &["access_token={token}%zz"],
+        &["access_token=%ff{token}"],
+        &["access_token={token}; access_token=other"],
+    ] {
+        let mut parts = token_in_cookies(&signing_key, cookies);
+        let result =
+            <CookieToken as FromRequestParts<_>>::from_request_parts(&mut parts, &state).await;
+        assert!(matches!(result, Err(error) if error.status == StatusCode::UNAUTHORIZED));
+    }
+}
+
+#[tokio::test]
+async fn Token_OversizedToken_IsUnauthenticated() {
+    let signing_key = SigningJsonWebKey::generate("kid".to_string(), Algorithm::ES256).unwrap();
+    let mut state = state(&signing_key).await;
+
+    let oversized = format!("Bearer {}", "a".repeat(1024 * 1024));
+    let mut parts = token_in_header(&signing_key, AUTHORIZATION.as_str(), &oversized);
+    let result = <Token as FromRequestParts<_>>::from_request_parts(&mut parts, &state).await;
+    assert!(matches!(result, Err(error) if error.status == StatusCode::UNAUTHORIZED));
+
+    // A valid token is rejected if it is longer than the configured maximum.
+    state.options = TokenValidationOptions::default().max_token_length(64);
+    let mut parts = token_in_header(&signing_key, AUTHORIZATION.as_str(), "Bearer {token}");
+    let result = <Token as FromRequestParts<_>>::from_request_parts(&mut parts, &state).await;
+    assert!(matches!(result, Err(error) if error.status == StatusCode::UNAUTHORIZED));
+}
+
+/// Serialized tokens that are rejected as unauthenticated: malformed, untrusted, and expired.
+fn rejected_tokens(signing_key: &SigningJsonWebKey) -> [String; 3] {
+    let untrusted_key = SigningJsonWebKey::generate("kid".to_string(), Algorithm::ES256).unwrap();
+    let untrusted = untrusted_key
+        .issue("subject".to_string(), TokenType::Common)
+        .unwrap();
+    let expired = signing_key
+        .issue_with_clock(
+            "subject".to_string(),
+            TokenType::Common,
+            &TestClock::new(Timestamp::now() - SignedDuration::from_hours(365 * 24)),
+        )
+        .unwrap();
+
+    [
+        "not-a-token".to_string(),
+        untrusted.serialize(),
+        expired.serialize(),
+    ]
+}
+
+#[tokio::test]
+async fn LenientToken_Absent_IsNone() {
+    let signing_key = SigningJsonWebKey::generate("kid".to_string(), Algorithm::ES256).unwrap();
+    let state = state(&signing_key).await;
+
+    let mut parts = Request::builder().body(()).unwrap().into_parts().0;
+    let result = Lenient::<Token>::from_request_parts(&mut parts, &state).await;
+    assert!(matches!(result, Ok(Lenient(None))));
+}
+
+#[tokio::test]
+async fn LenientToken_Valid_IsSome() {
+    let signing_key = SigningJsonWebKey::generate("kid".to_string(), Algorithm::ES256).unwrap();
+    let state = state(&signing_key).await;
+
+    let mut parts = parts(&signing_key, TokenType::Common);
+    let result = Lenient::<Token>::from_request_parts(&mut parts, &state).await;
+    assert!(matches!(result, Ok(Lenient(Some(Token(token)))) if token.claims.sub == "subject"));
+}
+
+#[tokio::test]
+async fn LenientToken_RejectedToken_IsNone() {
+    let signing_key = SigningJsonWebKey::generate("kid".to_string(), Algorithm::ES256).unwrap();
+    let state = state(&signing_key).await;
+
+    for token in rejected_tokens(&signing_key) {
+        let mut parts = Request::builder()
+            .header(AUTHORIZATION, format!("Bearer {token}"))
+            .body(())
+            .unwrap()
+            .into_parts()
+            .0;
+        let result = Lenient::<Token>::from_request_parts(&mut parts, &state).await;
+        assert!(matches!(result, Ok(Lenient(None))), "{token}");
+    }
+}
+
+#[tokio::test]
+async fn LenientCookieToken_Absent_IsNone() {
+    let signing_key = SigningJsonWebKey::generate("kid".to_string(), Algorithm::ES256).unwrap();
+    let state = state(&signing_key).await;
+
+    let mut parts = token_in_cookies(&signing_key, &["theme=dark"]);
+    let result = Lenient::<CookieToken>::from_request_parts(&mut parts, &state).await;
+    assert!(matches!(result, Ok(Lenient(None))));
+}
+
+#[tokio::test]
+async fn LenientCookieToken_Valid_IsSome() {
+    let signing_key = SigningJsonWebKey::generate("kid".to_string(), Algorithm::ES256).unwrap();
+    let state = state(&signing_key).await;
+
+    let mut parts = token_in_cookies(&signing_key, &["access_token={token}"]);
+    let result = Lenient::<CookieToken>::from_request_parts(&mut parts, &state).await;
+    assert!(
+        matches!(result, Ok(Lenient(Some(CookieToken(token)))) if token.claims.sub == "subject")
+    );
+}
+
+#[tokio::test]
+async fn LenientCookieToken_RejectedToken_IsNone() {
+    let signing_key = SigningJsonWebKey::generate("kid".to_string(), Algorithm::ES256).unwrap();
+    let state = state(&signing_key).await;
+
+    for token in rejected_tokens(&signing_key) {
+        let mut parts = Request::builder()
+            .header(COOKIE, format!("access_token={token}"))
+            .body(())
+            .unwrap()
+            .into_parts()
+            .0;
+        let result = Lenient::<CookieToken>::from_request_parts(&mut parts, &state).await;
+        assert!(matches!(result, Ok(Lenient(None))), "{token}");
+    }
+}
@@ -0,0 +1,75 @@
+#![allow(missing_docs, non_snake_case)]
+#![cfg(not(feature = "reqwest"))]
+
+use std::sync::Arc;
+
+use axum::extract::FromRequestParts;
+use http::{Request, StatusCode, header::AUTHORIZATION};
+use ts_api_helper::token::{
+    Algorithm, DbRevocation, JsonWebKeySetCache, SigningJsonWebKey,
+    extractor::{HasKeySetCache, HasRevocationEndpoint, Token, TokenValidationOptions},
+    json_web_key::{JsonWebKeySet, JwksFetcher, fetcher::FetchFuture},
+    json_web_token::TokenType,
+};
+
+/// A fetcher that always returns the same key set.
+#[derive(Debug)]
+struct StaticFetcher(Vec<u8>);
+impl JwksFetcher for StaticFetcher {
+    fn fetch<'a>(&'a self, _url: &'a str) -> FetchFuture<'a> {
+        Box::pin(async move { Ok(self.0.clone()) })
+    }
+}
+
+struct State {
+    jwks_cache: JsonWebKeySetCache,
+    options: TokenValidationOptions,
+}
+impl HasKeySetCache for State {
+    fn jwks_cache(&self) -> &JsonWebKeySetCache {
+        &self.jwks_cache
+    }
+
+    fn token_validation_options(&self) -> &TokenValidationOptions {
+        &self.options
+    }
+}
+impl HasRevocationEndpoint for State {
+    fn revocation_endpoint(&self) -> &str {
+        "http://127.0.0.1:1/revoked-tokens"
+    }
+
+    fn revocation_database(&self) -> Option<&DbRevocation> {
+        None
+    }
+}
+
+#[tokio::test]
+async fn Token_RevocationEndpointWithoutReqwest_IsInternalServerError() {
+    let signing_key = SigningJsonWebKey::generate("kid".to_string(), Algorithm::ES256).unwrap();
+    let jwks = JsonWebKeySet {
+        keys: vec![signing_key.jwk.clone()],
+    };
+    let fetcher = Arc::new(StaticFetcher(serde_json::to_vec(&jwks).unwrap()));
+    let state = State {
+        jwks_cache: JsonWebKeySetCache::from_fetcher(
+            "https://example.com/jwks.json".to_string(),
+            fetcher,
+        ),
+        options: TokenValidationOptions::default(),
+    };
+    state.jwks_cache.prime().await.unwrap();
+
+    let token = signing_key
+        .issue("subject".to_string(), TokenType::Common)
+        .unwrap();
+    let (mut parts, ()) = Request::builder()
+        .header(AUTHORIZATION, format!("Bearer {}", token.serialize()))
+        .body(())
+        .unwrap()
+        .into_parts();
+
+    let result = <Token as FromRequestParts<_>>::from_request_parts(&mut parts, &state).await;
+
+    assert!(matches!(result, Err(error) if error.status == StatusCode::INTERNAL_SERVER_ERROR));
+}
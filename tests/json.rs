@@ -0,0 +1,205 @@
+#![allow(missing_docs, non_snake_case)]
+
+use axum::{Router, body::Body, extract::Request, response::IntoResponse, routing::post};
+use http::{StatusCode, header::CONTENT_TYPE};
+use serde::Deserialize;
+use tower::ServiceExt;
+use ts_api_helper::{Json, JsonArray, PreciseJson, StrictJson};
+
+#[derive(Deserialize)]
+struct Transfer {
+    amount: u32,
+}
+
+async fn post_body(body: &'static str) -> (StatusCode, serde_json::Value) {
+    let router = Router::new().route(
+        "/",
+        post(
+            |StrictJson(transfer): StrictJson<Transfer>| async move { transfer.amount.to_string() },
+        ),
+    );
+
+    let request = Request::builder()
+        .method("POST")
+        .uri("/")
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(body))
+        .unwrap();
+    let response = router.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+
+    (status, serde_json::from_slice(&body).unwrap_or_default())
+}
+
+#[tokio::test]
+async fn StrictJson_UniqueKeys_IsExtracted() {
+    let (status, body) = post_body(r#"{"amount": 10}"#).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, 10);
+}
+
+#[tokio::test]
+async fn StrictJson_DuplicateKey_IsBadRequestPointingAtKey() {
+    let (status, body) = post_body(r#"{"amount": 10, "amount": 1000}"#).await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["problems"][0]["pointer"], "$.amount");
+}
+
+#[tokio::test]
+async fn StrictJson_NestedDuplicateKey_PointsAtNestedKey() {
+    let (status, body) = post_body(r#"{"amount": 10, "meta": [{"a": 1, "a": 2}]}"#).await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["problems"][0]["pointer"], "$.meta[0].a");
+}
+
+async fn json_array_body(
+    items: Vec<Result<u32, std::io::Error>>,
+) -> Result<axum::body::Bytes, axum::Error> {
+    let response = JsonArray(futures_util::stream::iter(items)).into_response();
+    assert_eq!(response.headers()[CONTENT_TYPE], "application/json");
+
+    axum::body::to_bytes(response.into_body(), usize::MAX).await
+}
+
+#[tokio::test]
+async fn JsonArray_Items_IsJsonArray() {
+    let body = json_array_body(vec![Ok(1), Ok(2), Ok(3)]).await.unwrap();
+
+    assert_eq!(body, "[1,2,3]");
+}
+
+#[tokio::test]
+async fn JsonArray_Empty_IsEmptyArray() {
+    let body = json_array_body(vec![]).await.unwrap();
+
+    assert_eq!(body, "[]");
+}
+
+#[tokio::test]
+async fn JsonArray_ErrorMidStream_IsTruncated() {
+    let items = vec![Ok(1), Err(std::io::Error::other("connection lost")), Ok(3)];
+
+    assert!(json_array_body(items).await.is_err());
+}
+
+#[derive(Deserialize)]
+struct Order {
+    lines: Vec<Line>,
+}
+
+#[derive(Deserialize)]
+struct Line {
+    price: f64,
+    quantity: u64,
+}
+
+async fn post_order(body: String) -> (StatusCode, serde_json::Value) {
+    let router = Router::new().route(
+        "/",
+        post(|PreciseJson(order): PreciseJson<Order>| async move {
+            let quantity: u64 = order.lines.iter().map(|line| line.quantity).sum();
+            let total: f64 = order.lines.iter().map(|line| line.price).sum();
+            format!("{quantity} {total}")
+        }),
+    );
+
+    let request = Request::builder()
+        .method("POST")
+        .uri("/")
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(body))
+        .unwrap();
+    let response = router.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+
+    (status, serde_json::from_slice(&body).unwrap_or_default())
+}
+
+#[tokio::test]
+async fn PreciseJson_ExactNumbers_IsExtracted() {
+    let body = format!(
+        r#"{{"lines": [{{"price": 2.5, "quantity": 2}}, {{"price": {}, "quantity": {}}}]}}"#,
+        1u64 << 53,
+        1u64 << 60
+    );
+
+    let (status, _) = post_order(body).await;
+
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn PreciseJson_LargeIntegerIntoFloat_IsBadRequestPointingAtField() {
+    let body = format!(
+        r#"{{"lines": [{{"price": 1, "quantity": 1}}, {{"price": {}, "quantity": 1}}]}}"#,
+        1u64 << 60
+    );
+
+    let (status, body) = post_order(body).await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["problems"][0]["pointer"], "$.lines[1].price");
+}
+
+#[tokio::test]
+async fn PreciseJson_InvalidBody_IsUnprocessable() {
+    let (status, _) = post_order(r#"{"lines": [{"price": "1"}]}"#.to_string()).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let (status, _) = post_order(r#"{"lines": []} []"#.to_string()).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+async fn post_with_content_type(content_type: &'static str) -> (StatusCode, serde_json::Value) {
+    let router = Router::new().route(
+        "/",
+        post(|Json(transfer): Json<Transfer>| async move { transfer.amount.to_string() }),
+    );
+
+    let request = Request::builder()
+        .method("POST")
+        .uri("/")
+        .header(CONTENT_TYPE, content_type)
+        .body(Body::from(r#"{"amount": 10}"#))
+        .unwrap();
+    let response = router.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+
+    (status, serde_json::from_slice(&body).unwrap_or_default())
+}
+
+#[tokio::test]
+async fn Json_Utf8Charset_IsExtracted() {
+    let (status, body) = post_with_content_type("application/json; charset=utf-8").await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, 10);
+}
+
+#[tokio::test]
+async fn Json_Utf16Charset_IsUnsupportedMediaType() {
+    let (status, body) = post_with_content_type("application/json; charset=UTF-16").await;
+
+    assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    assert_eq!(body, serde_json::Value::Null);
+}
+
+#[tokio::test]
+async fn Json_NoCharset_IsExtracted() {
+    let (status, body) = post_with_content_type("application/json").await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, 10);
+}
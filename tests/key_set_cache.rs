@@ -0,0 +1,628 @@
+#![allow(missing_docs, non_snake_case)]
+#![cfg(feature = "reqwest")]
+
+use std::{
+    io::{Read, Write},
+    net::TcpListener,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread,
+};
+
+use base64ct::{Base64UrlUnpadded, Encoding};
+use http::StatusCode;
+use jiff::{SignedDuration, Timestamp};
+use openssl::{ecdsa::EcdsaSig, hash::MessageDigest, sign::Signer};
+use reqwest::Client;
+use ts_api_helper::token::{
+    Algorithm, Clock, EncryptingJsonWebKey, JsonWebKeySetCache, JsonWebToken, SigningJsonWebKey,
+    TestClock, VerifyingJsonWebKey,
+    extractor::TokenValidationOptions,
+    json_web_key::{
+        FetchError, JsonWebKeySet, JwkCache, JwksFetcher, MergeConflict,
+        fetcher::FetchFuture,
+        key_set_cache::{RefreshCacheError, VerifyError},
+    },
+    json_web_token::TokenType,
+};
+
+#[tokio::test]
+async fn Refresh_AfterFailure_BacksOff() {
+    let cache = JsonWebKeySetCache::new("http://127.0.0.1:1/jwks.json".to_string(), Client::new());
+
+    let first = cache.refresh().await.unwrap_err();
+    assert!(!matches!(first, RefreshCacheError::BackingOff { .. }));
+
+    let backoff = cache.backoff().await;
+    assert_eq!(backoff.failures, 1);
+    assert!(backoff.retry_after > Timestamp::now());
+
+    let second = cache.refresh().await.unwrap_err();
+    assert!(matches!(second, RefreshCacheError::BackingOff { .. }));
+    assert_eq!(cache.backoff().await.failures, 1);
+}
+
+/// Serve a single HTTP response with a JSON body, returning the URL.
+fn serve_once(body: String) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/jwks.json", listener.local_addr().unwrap());
+
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = [0u8; 4096];
+        let _ = stream.read(&mut request).unwrap();
+
+        let response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        stream.write_all(response.as_bytes()).unwrap();
+    });
+
+    url
+}
+
+#[tokio::test]
+async fn Prime_ReachableEndpoint_CachesKeys() {
+    let signing_key = SigningJsonWebKey::generate("primed".to_string(), Algorithm::ES256).unwrap();
+    let jwks = JsonWebKeySet {
+        keys: vec![signing_key.jwk],
+    };
+    let url = serve_once(serde_json::to_string(&jwks).unwrap());
+
+    let cache = JsonWebKeySetCache::new(url, Client::new());
+    cache.prime().await.unwrap();
+
+    assert!(cache.cache.read().await.contains_key("primed"));
+}
+
+/// A fetcher that returns a fixed body for one URL.
+#[derive(Debug)]
+struct StaticFetcher {
+    url: String,
+    body: Vec<u8>,
+}
+impl JwksFetcher for StaticFetcher {
+    fn fetch<'a>(&'a self, url: &'a str) -> FetchFuture<'a> {
+        Box::pin(async move {
+            if url == self.url {
+                Ok(self.body.clone())
+            } else {
+                Err(FetchError::error_response(StatusCode::NOT_FOUND))
+            }
+        })
+    }
+}
+
+#[tokio::test]
+async fn Prime_CustomFetcher_CachesKeys() {
+    let signing_key = SigningJsonWebKey::generate("fetched".to_string(), Algorithm::ES256).unwrap();
+    let jwks = JsonWebKeySet {
+        keys: vec![signing_key.jwk],
+    };
+    let fetcher = Arc::new(StaticFetcher {
+        url: "https://example.com/jwks.json".to_string(),
+        body: serde_json::to_vec(&jwks).unwrap(),
+    });
+
+    let cache = JsonWebKeySetCache::from_fetcher(
+        "https://example.com/jwks.json".to_string(),
+        fetcher.clone(),
+    );
+    cache.prime().await.unwrap();
+    assert!(cache.cache.read().await.contains_key("fetched"));
+
+    let cache =
+        JsonWebKeySetCache::from_fetcher("https://example.com/other.json".to_string(), fetcher);
+    let error = cache.prime().await.unwrap_err();
+    assert!(matches!(
+        error,
+        RefreshCacheError::Fetch {
+            source: FetchError::ErrorResponse {
+                status: StatusCode::NOT_FOUND,
+                ..
+            },
+            ..
+        }
+    ));
+}
+
+#[tokio::test]
+async fn Prime_AlgorithmCurveMismatch_SkipsKey() {
+    let mut mismatched =
+        SigningJsonWebKey::generate("mismatched".to_string(), Algorithm::ES256).unwrap();
+    mismatched.jwk.alg = Algorithm::EdDSA;
+    let valid = SigningJsonWebKey::generate("valid".to_string(), Algorithm::ES256).unwrap();
+    let jwks = JsonWebKeySet {
+        keys: vec![mismatched.jwk, valid.jwk],
+    };
+    let fetcher = Arc::new(StaticFetcher {
+        url: "https://example.com/jwks.json".to_string(),
+        body: serde_json::to_vec(&jwks).unwrap(),
+    });
+
+    let cache =
+        JsonWebKeySetCache::from_fetcher("https://example.com/jwks.json".to_string(), fetcher);
+    cache.prime().await.unwrap();
+
+    let keys = cache.cache.read().await;
+    assert!(keys.contains_key("valid"));
+    assert!(!keys.contains_key("mismatched"));
+    assert_ne!(*cache.last_refresh.read().await, Timestamp::UNIX_EPOCH);
+}
+
+#[tokio::test]
+async fn JwkCache_EncryptingKeys_CachesKeys() {
+    let signing_key =
+        SigningJsonWebKey::generate("recipient".to_string(), Algorithm::ES256).unwrap();
+    let jwks = JsonWebKeySet {
+        keys: vec![signing_key.jwk],
+    };
+    let url = serve_once(serde_json::to_string(&jwks).unwrap());
+
+    let cache = JwkCache::<EncryptingJsonWebKey>::new(url, Client::new());
+    cache.prime().await.unwrap();
+
+    let cache = cache.cache.read().await;
+    assert_eq!(cache.get("recipient").unwrap().kid, "recipient");
+}
+
+#[test]
+fn JsonWebKeySet_UnsupportedKeyType_IsSkipped() {
+    let signing_key = SigningJsonWebKey::generate("ec".to_string(), Algorithm::ES256).unwrap();
+    let mut jwks = serde_json::to_value(JsonWebKeySet {
+        keys: vec![signing_key.jwk],
+    })
+    .unwrap();
+    jwks["keys"].as_array_mut().unwrap().insert(
+        0,
+        serde_json::json!({ "kty": "OKP", "kid": "ed", "crv": "Ed25519", "x": "AA" }),
+    );
+
+    let jwks: JsonWebKeySet = serde_json::from_value(jwks).unwrap();
+
+    assert_eq!(jwks.keys.len(), 1);
+    assert_eq!(jwks.keys[0].kid, "ec");
+}
+
+fn key_set(kids: &[&str]) -> JsonWebKeySet {
+    let keys = kids
+        .iter()
+        .map(|kid| {
+            SigningJsonWebKey::generate(kid.to_string(), Algorithm::ES256)
+                .unwrap()
+                .jwk
+        })
+        .collect();
+
+    JsonWebKeySet { keys }
+}
+
+#[test]
+fn JsonWebKeySetMerge_DistinctKeys_CombinesKeys() {
+    let primary = key_set(&["primary-1", "primary-2"]);
+    let migration = key_set(&["migration"]);
+
+    let merged = primary.clone().merge(migration.clone()).unwrap();
+
+    assert_eq!(merged.keys.len(), 3);
+    assert_eq!(merged.keys[..2], primary.keys);
+    assert_eq!(merged.keys[2], migration.keys[0]);
+}
+
+#[test]
+fn JsonWebKeySetMerge_IdenticalDuplicate_IsKeptOnce() {
+    let primary = key_set(&["shared", "primary"]);
+    let migration = JsonWebKeySet {
+        keys: vec![primary.keys[0].clone()],
+    };
+
+    let merged = primary.clone().merge(migration).unwrap();
+
+    assert_eq!(merged, primary);
+}
+
+#[test]
+fn JsonWebKeySetMerge_DifferentKeysWithSameKid_IsConflict() {
+    let primary = key_set(&["shared", "primary"]);
+    let migration = key_set(&["shared"]);
+
+    let result = primary.merge(migration);
+
+    assert!(matches!(result, Err(MergeConflict { kid, .. }) if kid == "shared"));
+}
+
+#[tokio::test]
+async fn Prime_UnreachableEndpoint_ReturnsErrorWithoutBackingOff() {
+    let cache = JsonWebKeySetCache::new("http://127.0.0.1:1/jwks.json".to_string(), Client::new());
+
+    assert!(cache.prime().await.is_err());
+    assert_eq!(cache.backoff().await.failures, 0);
+}
+
+/// Create a cache holding a single key that will not be refreshed.
+async fn cache_with(signing_key: &SigningJsonWebKey) -> JsonWebKeySetCache {
+    let cache = JsonWebKeySetCache::new("http://127.0.0.1:1/jwks.json".to_string(), Client::new());
+    let verifying_key = VerifyingJsonWebKey::try_from(signing_key.jwk.clone()).unwrap();
+    cache
+        .cache
+        .write()
+        .await
+        .insert(signing_key.jwk.kid.clone(), verifying_key);
+    *cache.last_refresh.write().await = Timestamp::now();
+    cache
+}
+
+#[tokio::test]
+async fn VerifyCompact_ValidToken_IsVerified() {
+    let signing_key = SigningJsonWebKey::generate("kid".to_string(), Algorithm::ES256).unwrap();
+    let cache = cache_with(&signing_key).await;
+    let token = signing_key
+        .issue("subject".to_string(), TokenType::Common)
+        .unwrap();
+
+    let verified = cache.verify_compact(&token.serialize()).await.unwrap();
+
+    assert_eq!(verified, token);
+}
+
+#[tokio::test]
+async fn VerifyCompact_Malformed_IsMalformed() {
+    let signing_key = SigningJsonWebKey::generate("kid".to_string(), Algorithm::ES256).unwrap();
+    let cache = cache_with(&signing_key).await;
+
+    let error = cache.verify_compact("not-a-token").await.unwrap_err();
+
+    assert!(matches!(error, VerifyError::Malformed { .. }));
+}
+
+#[tokio::test]
+async fn VerifyCompact_UnknownKid_IsUnknownKid() {
+    let signing_key = SigningJsonWebKey::generate("kid".to_string(), Algorithm::ES256).unwrap();
+    let other_key = SigningJsonWebKey::generate("other".to_string(), Algorithm::ES256).unwrap();
+    let cache = cache_with(&signing_key).await;
+    let token = other_key
+        .issue("subject".to_string(), TokenType::Common)
+        .unwrap();
+
+    let error = cache.verify_compact(&token.serialize()).await.unwrap_err();
+
+    assert!(matches!(error, VerifyError::UnknownKid { kid, .. } if kid == "other"));
+}
+
+#[tokio::test]
+async fn VerifyCompact_TamperedClaims_IsBadSignature() {
+    let signing_key = SigningJsonWebKey::generate("kid".to_string(), Algorithm::ES256).unwrap();
+    let cache = cache_with(&signing_key).await;
+    let mut token = signing_key
+        .issue("subject".to_string(), TokenType::Common)
+        .unwrap();
+    token.claims.sub = "admin".into();
+
+    let error = cache.verify_compact(&token.serialize()).await.unwrap_err();
+
+    assert!(matches!(error, VerifyError::BadSignature { .. }));
+}
+
+#[tokio::test]
+async fn VerifyCompact_ExpiredToken_IsExpired() {
+    let signing_key = SigningJsonWebKey::generate("kid".to_string(), Algorithm::ES256).unwrap();
+    let cache = cache_with(&signing_key).await;
+    let mut token = signing_key
+        .issue("subject".to_string(), TokenType::Common)
+        .unwrap();
+    token.claims.exp = Timestamp::now() - SignedDuration::from_mins(1);
+    token.signature = sign(&signing_key, &token);
+
+    let error = cache.verify_compact(&token.serialize()).await.unwrap_err();
+    assert!(matches!(error, VerifyError::Expired { .. }));
+
+    let leeway = SignedDuration::from_mins(5);
+    assert!(
+        cache
+            .verify_compact_with(
+                &token.serialize(),
+                &TokenValidationOptions::default().leeway(leeway)
+            )
+            .await
+            .is_ok()
+    );
+}
+
+/// Sign the token's current signing input, producing a fixed-size `r || s` signature.
+fn sign(signing_key: &SigningJsonWebKey, token: &JsonWebToken) -> Vec<u8> {
+    der_to_fixed(&sign_der(signing_key, &token.signing_input()))
+}
+
+/// Sign a signing input with OpenSSL, producing a DER encoded signature.
+fn sign_der(signing_key: &SigningJsonWebKey, signing_input: &str) -> Vec<u8> {
+    let mut signer = Signer::new(MessageDigest::sha256(), &signing_key.key).unwrap();
+    signer
+        .sign_oneshot_to_vec(signing_input.as_bytes())
+        .unwrap()
+}
+
+/// Convert a DER encoded ES256 signature to the fixed-size `r || s` form.
+fn der_to_fixed(der: &[u8]) -> Vec<u8> {
+    let signature = EcdsaSig::from_der(der).unwrap();
+    let mut fixed = signature.r().to_vec_padded(32).unwrap();
+    fixed.extend(signature.s().to_vec_padded(32).unwrap());
+    fixed
+}
+
+/// Build a compact token from the exact header and claims JSON, signed in the `r || s` form.
+fn compact(signing_key: &SigningJsonWebKey, header: &str, claims: &str) -> String {
+    let signing_input = format!(
+        "{}.{}",
+        Base64UrlUnpadded::encode_string(header.as_bytes()),
+        Base64UrlUnpadded::encode_string(claims.as_bytes())
+    );
+
+    let fixed = der_to_fixed(&sign_der(signing_key, &signing_input));
+
+    format!(
+        "{signing_input}.{}",
+        Base64UrlUnpadded::encode_string(&fixed)
+    )
+}
+
+#[tokio::test]
+async fn VerifyCompact_DerSignature_RequiresOption() {
+    let signing_key = SigningJsonWebKey::generate("kid".to_string(), Algorithm::ES256).unwrap();
+    let cache = cache_with(&signing_key).await;
+    let mut token = signing_key
+        .issue("subject".to_string(), TokenType::Common)
+        .unwrap();
+    token.signature = sign_der(&signing_key, &token.signing_input());
+
+    let error = cache.verify_compact(&token.serialize()).await.unwrap_err();
+    assert!(matches!(error, VerifyError::BadSignature { .. }));
+
+    let options = TokenValidationOptions::default().accept_der_signatures();
+    assert!(
+        cache
+            .verify_compact_with(&token.serialize(), &options)
+            .await
+            .is_ok()
+    );
+}
+
+#[tokio::test]
+async fn VerifyCompact_NonCanonicalJson_IsVerified() {
+    let signing_key = SigningJsonWebKey::generate("kid".to_string(), Algorithm::ES256).unwrap();
+    let cache = cache_with(&signing_key).await;
+    let now = Timestamp::now().as_second();
+    let claims = format!(
+        r#"{{ "sub": "subject", "typ": "common", "extra": true, "tid": "id", "iat": {now}.5, "exp": {} }}"#,
+        now + 60
+    );
+    let serialized = compact(
+        &signing_key,
+        r#"{"kid": "kid", "typ": "JWT", "alg": "ES256"}"#,
+        &claims,
+    );
+
+    let token = cache.verify_compact(&serialized).await.unwrap();
+
+    assert_eq!(token.claims.sub.0, "subject");
+    let verifying_key = VerifyingJsonWebKey::try_from(signing_key.jwk.clone()).unwrap();
+    assert!(!verifying_key.verify(&token).unwrap());
+}
+
+#[tokio::test]
+async fn VerifyCompact_CriticalParameter_RequiresUnderstanding() {
+    let signing_key = SigningJsonWebKey::generate("kid".to_string(), Algorithm::ES256).unwrap();
+    let cache = cache_with(&signing_key).await;
+    let mut token = signing_key
+        .issue("subject".to_string(), TokenType::Common)
+        .unwrap();
+    token.header.crit = vec!["b64".to_string()];
+    token.signature = sign(&signing_key, &token);
+
+    let error = cache.verify_compact(&token.serialize()).await.unwrap_err();
+    assert!(matches!(error, VerifyError::UnknownCritical { parameter, .. } if parameter == "b64"));
+
+    let options = TokenValidationOptions::default().understand_critical("b64".to_string());
+    assert!(
+        cache
+            .verify_compact_with(&token.serialize(), &options)
+            .await
+            .is_ok()
+    );
+}
+
+#[tokio::test]
+async fn VerifyCompact_TestClockAdvancedPastExpiry_IsExpired() {
+    let clock = TestClock::new("2025-01-01T00:00:00Z".parse().unwrap());
+    let signing_key = SigningJsonWebKey::generate("kid".to_string(), Algorithm::ES256).unwrap();
+    let cache = cache_with(&signing_key)
+        .await
+        .with_clock(Arc::new(clock.clone()));
+    let token = signing_key
+        .issue_with_clock(
+            "subject".to_string(),
+            TokenType::Consent { act: vec![] },
+            &clock,
+        )
+        .unwrap();
+    assert_eq!(token.claims.iat, clock.now());
+
+    clock.advance(SignedDuration::from_mins(5));
+    assert!(cache.verify_compact(&token.serialize()).await.is_ok());
+
+    clock.advance(SignedDuration::from_secs(1));
+    let error = cache.verify_compact(&token.serialize()).await.unwrap_err();
+    assert!(matches!(error, VerifyError::Expired { .. }));
+}
+
+#[tokio::test]
+async fn TimeUntilRefresh_AfterRefresh_IsRefreshInterval() {
+    let clock = TestClock::new("2025-01-01T00:00:00Z".parse().unwrap());
+    let cache = JsonWebKeySetCache::new("http://127.0.0.1:1/jwks.json".to_string(), Client::new())
+        .with_clock(Arc::new(clock.clone()));
+    assert_eq!(cache.time_until_refresh().await, SignedDuration::ZERO);
+
+    *cache.last_refresh.write().await = clock.now();
+    assert_eq!(
+        cache.next_refresh_allowed_at().await,
+        clock.now() + JsonWebKeySetCache::REFRESH_INTERVAL
+    );
+
+    clock.advance(SignedDuration::from_hours(1));
+    assert_eq!(
+        cache.time_until_refresh().await,
+        JsonWebKeySetCache::REFRESH_INTERVAL - SignedDuration::from_hours(1)
+    );
+}
+
+#[tokio::test]
+async fn VerifyCompact_FutureIat_RejectedBeyondLeewayWhenEnabled() {
+    let signing_key = SigningJsonWebKey::generate("kid".to_string(), Algorithm::ES256).unwrap();
+    let clock = TestClock::new(Timestamp::now());
+    let cache = cache_with(&signing_key)
+        .await
+        .with_clock(Arc::new(clock.clone()));
+    let token = signing_key
+        .issue_with_clock("subject".to_string(), TokenType::Common, &clock)
+        .unwrap();
+    let leeway = SignedDuration::from_secs(30);
+    let options = TokenValidationOptions::default()
+        .leeway(leeway)
+        .reject_future_iat();
+
+    // Issued within the leeway of now.
+    clock.advance(-leeway);
+    assert!(
+        cache
+            .verify_compact_with(&token.serialize(), &options)
+            .await
+            .is_ok()
+    );
+
+    // Issued beyond the leeway of now.
+    clock.advance(SignedDuration::from_secs(-1));
+    let error = cache
+        .verify_compact_with(&token.serialize(), &options)
+        .await
+        .unwrap_err();
+    assert!(matches!(error, VerifyError::IssuedInFuture { .. }));
+
+    // Not checked by default.
+    assert!(
+        cache
+            .verify_compact_with(
+                &token.serialize(),
+                &TokenValidationOptions::default().leeway(leeway)
+            )
+            .await
+            .is_ok()
+    );
+}
+
+#[tokio::test]
+async fn VerifyCompact_KeyReferencedByThumbprint_IsVerified() {
+    let mut signing_key = SigningJsonWebKey::generate("kid".to_string(), Algorithm::ES256).unwrap();
+    signing_key.jwk.x5t_s256 = Some("sha256-thumbprint".to_string());
+    let cache = cache_with(&signing_key).await;
+
+    let mut token = signing_key
+        .issue("subject".to_string(), TokenType::Common)
+        .unwrap();
+    token.header.kid = String::new();
+    token.header.x5t_s256 = Some("sha256-thumbprint".to_string());
+    token.signature = sign(&signing_key, &token);
+
+    assert!(cache.verify_compact(&token.serialize()).await.is_ok());
+
+    token.header.x5t_s256 = Some("other-thumbprint".to_string());
+    token.signature = sign(&signing_key, &token);
+
+    let error = cache.verify_compact(&token.serialize()).await.unwrap_err();
+    assert!(matches!(error, VerifyError::UnknownKid { .. }));
+}
+
+#[tokio::test]
+async fn VerifyCompact_ThumbprintKid_IsVerifiedByX5t() {
+    let signing_key =
+        SigningJsonWebKey::generate("sha1-thumbprint".to_string(), Algorithm::ES256).unwrap();
+    let cache = cache_with(&signing_key).await;
+
+    let mut token = signing_key
+        .issue("subject".to_string(), TokenType::Common)
+        .unwrap();
+    token.header.kid = String::new();
+    token.header.x5t = Some("sha1-thumbprint".to_string());
+    token.signature = sign(&signing_key, &token);
+
+    assert!(cache.verify_compact(&token.serialize()).await.is_ok());
+}
+
+#[tokio::test]
+async fn VerifyCompact_ThumbprintWithoutKid_IsVerified() {
+    let mut signing_key = SigningJsonWebKey::generate("kid".to_string(), Algorithm::ES256).unwrap();
+    signing_key.jwk.x5t_s256 = Some("sha256-thumbprint".to_string());
+    let cache = cache_with(&signing_key).await;
+    let now = Timestamp::now().as_second();
+    let claims = format!(
+        r#"{{"tid":"id","exp":{},"iat":{now},"sub":"subject","typ":"common"}}"#,
+        now + 60
+    );
+
+    let serialized = compact(
+        &signing_key,
+        r#"{"alg":"ES256","typ":"JWT","x5t#S256":"sha256-thumbprint"}"#,
+        &claims,
+    );
+    let token = cache.verify_compact(&serialized).await.unwrap();
+    assert_eq!(token.header.kid, "");
+
+    let serialized = compact(
+        &signing_key,
+        r#"{"alg":"ES256","typ":"JWT","x5t":"kid"}"#,
+        &claims,
+    );
+    assert!(cache.verify_compact(&serialized).await.is_ok());
+}
+
+/// A fetcher that counts its fetches and always returns an empty key set.
+#[derive(Debug, Default)]
+struct CountingFetcher(AtomicUsize);
+impl JwksFetcher for CountingFetcher {
+    fn fetch<'a>(&'a self, _url: &'a str) -> FetchFuture<'a> {
+        Box::pin(async move {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(br#"{"keys":[]}"#.to_vec())
+        })
+    }
+}
+
+#[tokio::test]
+async fn Evict_CachedKey_RefreshesOnNextUse() {
+    let signing_key = SigningJsonWebKey::generate("kid".to_string(), Algorithm::ES256).unwrap();
+    let fetcher = Arc::new(CountingFetcher::default());
+    let cache = JsonWebKeySetCache::from_fetcher(
+        "https://example.com/jwks.json".to_string(),
+        fetcher.clone(),
+    );
+    cache.cache.write().await.insert(
+        "kid".to_string(),
+        VerifyingJsonWebKey::try_from(signing_key.jwk.clone()).unwrap(),
+    );
+    *cache.last_refresh.write().await = Timestamp::now();
+
+    let token = signing_key
+        .issue("subject".to_string(), TokenType::Common)
+        .unwrap()
+        .serialize();
+    assert!(cache.verify_compact(&token).await.is_ok());
+    assert_eq!(fetcher.0.load(Ordering::SeqCst), 0);
+
+    cache.evict("kid").await;
+    assert!(!cache.cache.read().await.contains_key("kid"));
+
+    let error = cache.verify_compact(&token).await.unwrap_err();
+    assert!(matches!(error, VerifyError::UnknownKid { kid, .. } if kid == "kid"));
+    assert_eq!(fetcher.0.load(Ordering::SeqCst), 1);
+}
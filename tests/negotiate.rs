@@ -0,0 +1,94 @@
+#![allow(missing_docs, non_snake_case)]
+
+use axum::{Router, body::Body, extract::Request, routing::get};
+use http::{
+    StatusCode,
+    header::{ACCEPT, CONTENT_TYPE, VARY},
+};
+use serde::{Deserialize, Serialize};
+use tower::ServiceExt;
+use ts_api_helper::{Negotiated, ResponseFormat};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Item {
+    name: String,
+    count: u32,
+}
+
+fn item() -> Item {
+    Item {
+        name: "widget".to_string(),
+        count: 3,
+    }
+}
+
+fn router() -> Router {
+    Router::new().route(
+        "/",
+        get(|format: ResponseFormat| async move { Negotiated(format, item()) }),
+    )
+}
+
+async fn get_item(accept: Option<&str>) -> (String, String, Vec<u8>) {
+    let mut request = Request::builder().uri("/");
+    if let Some(accept) = accept {
+        request = request.header(ACCEPT, accept);
+    }
+    let request = request.body(Body::empty()).unwrap();
+
+    let response = router().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let header = |name| response.headers()[name].to_str().unwrap().to_string();
+    let content_type = header(CONTENT_TYPE);
+    let vary = header(VARY);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (content_type, vary, body.to_vec())
+}
+
+#[tokio::test]
+async fn Negotiated_AcceptMessagePack_IsMessagePack() {
+    for accept in [
+        "application/msgpack",
+        "application/vnd.msgpack",
+        "application/x-msgpack",
+        "application/json;q=0.5, application/msgpack",
+        "application/msgpack, application/json",
+    ] {
+        let (content_type, vary, body) = get_item(Some(accept)).await;
+
+        assert_eq!(content_type, "application/msgpack");
+        assert_eq!(vary, "accept");
+        assert_eq!(rmp_serde::from_slice::<Item>(&body).unwrap(), item());
+    }
+}
+
+#[tokio::test]
+async fn Negotiated_AcceptJson_IsJson() {
+    for accept in [
+        "application/json",
+        "*/*",
+        "application/json, application/msgpack",
+        "application/msgpack;q=0.5, application/json",
+        "application/msgpack;q=0, */*",
+        "text/html",
+    ] {
+        let (content_type, vary, body) = get_item(Some(accept)).await;
+
+        assert_eq!(content_type, "application/json");
+        assert_eq!(vary, "accept");
+        assert_eq!(serde_json::from_slice::<Item>(&body).unwrap(), item());
+    }
+}
+
+#[tokio::test]
+async fn Negotiated_NoAccept_IsJson() {
+    let (content_type, vary, body) = get_item(None).await;
+
+    assert_eq!(content_type, "application/json");
+    assert_eq!(vary, "accept");
+    assert_eq!(serde_json::from_slice::<Item>(&body).unwrap(), item());
+}
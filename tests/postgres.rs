@@ -0,0 +1,212 @@
+#![allow(missing_docs, non_snake_case)]
+
+use core::time::Duration;
+
+use bb8::Pool;
+use bb8_postgres::PostgresConnectionManager;
+use http::StatusCode;
+use jiff::{SignedDuration, Timestamp};
+use tokio_postgres::NoTls;
+use ts_api_helper::{
+    ConnectionPool, ErrorResponse, PoolConfig, TransactionConflict, retry_on_conflict,
+    setup_connection_pool_with_config,
+    token::db_revocation::{DbRevocation, prune_revoked_tokens},
+    transaction, with_retry,
+};
+
+#[derive(Debug, PartialEq)]
+enum FakeError {
+    SerializationFailure,
+    Other,
+}
+impl TransactionConflict for FakeError {
+    fn is_transaction_conflict(&self) -> bool {
+        *self == Self::SerializationFailure
+    }
+}
+
+#[tokio::test]
+async fn RetryOnConflict_ConflictThenSuccess_Succeeds() {
+    let mut calls = 0;
+    let result = retry_on_conflict(3, || {
+        calls += 1;
+        let result = if calls == 1 {
+            Err(FakeError::SerializationFailure)
+        } else {
+            Ok(calls)
+        };
+        async move { result }
+    })
+    .await;
+
+    assert_eq!(result, Ok(2));
+}
+
+#[tokio::test]
+async fn RetryOnConflict_OtherError_IsNotRetried() {
+    let mut calls = 0;
+    let result: Result<(), _> = retry_on_conflict(3, || {
+        calls += 1;
+        async { Err(FakeError::Other) }
+    })
+    .await;
+
+    assert_eq!(result, Err(FakeError::Other));
+    assert_eq!(calls, 1);
+}
+
+#[tokio::test]
+async fn RetryOnConflict_PersistentConflict_StopsAfterAttempts() {
+    let mut calls = 0;
+    let result: Result<(), _> = retry_on_conflict(3, || {
+        calls += 1;
+        async { Err(FakeError::SerializationFailure) }
+    })
+    .await;
+
+    assert_eq!(result, Err(FakeError::SerializationFailure));
+    assert_eq!(calls, 3);
+}
+
+#[tokio::test]
+async fn WithRetry_Future_IsSend() {
+    fn assert_send<T: Send>(_: T) {}
+
+    let manager =
+        PostgresConnectionManager::new_from_stringlike("postgres://localhost", NoTls).unwrap();
+    let pool: ConnectionPool = Pool::builder().build_unchecked(manager);
+
+    assert_send(with_retry(&pool, 3, |transaction| {
+        Box::pin(async move { transaction.execute("SELECT 1", &[]).await })
+    }));
+}
+
+#[tokio::test]
+async fn SetupConnectionPool_MinIdleUnreachable_IsError() {
+    let config = PoolConfig::default()
+        .min_idle(2)
+        .connection_timeout(Duration::from_millis(500));
+
+    let result = setup_connection_pool_with_config("postgres://127.0.0.1:1", &config).await;
+
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+#[ignore = "requires a database at `TEST_DATABASE_URL`"]
+async fn SetupConnectionPool_MinIdle_IsWarm() {
+    let connection_string = std::env::var("TEST_DATABASE_URL").unwrap();
+    let config = PoolConfig::default().max_size(4).min_idle(3);
+
+    let pool = setup_connection_pool_with_config(connection_string, &config)
+        .await
+        .unwrap();
+
+    assert_eq!(pool.state().idle_connections, 3);
+}
+
+#[tokio::test]
+#[ignore = "requires a database at `TEST_DATABASE_URL`"]
+async fn DbRevocation_RevokeThenPrune_RoundTrips() {
+    let connection_string = std::env::var("TEST_DATABASE_URL").unwrap();
+    // A single connection, so every query sees the temporary table.
+    let config = PoolConfig::default().max_size(1);
+    let pool = setup_connection_pool_with_config(connection_string, &config)
+        .await
+        .unwrap();
+    pool.get()
+        .await
+        .unwrap()
+        .batch_execute(
+            "CREATE TEMPORARY TABLE IF NOT EXISTS revoked_tokens (tid TEXT PRIMARY KEY, exp TIMESTAMPTZ NOT NULL)",
+        )
+        .await
+        .unwrap();
+    let revocation = DbRevocation::new(pool.clone());
+
+    let exp = Timestamp::now() + SignedDuration::from_mins(5);
+    assert!(!revocation.is_revoked("tid").await.unwrap());
+    revocation.revoke("tid", exp).await.unwrap();
+    revocation.revoke("tid", exp).await.unwrap();
+    assert!(revocation.is_revoked("tid").await.unwrap());
+
+    let leeway = SignedDuration::from_secs(30);
+    let pruned = prune_revoked_tokens(&pool, exp + SignedDuration::from_secs(1), leeway)
+        .await
+        .unwrap();
+    assert_eq!(pruned, 0);
+    assert!(revocation.is_revoked("tid").await.unwrap());
+
+    let pruned = prune_revoked_tokens(&pool, exp + leeway + SignedDuration::from_secs(1), leeway)
+        .await
+        .unwrap();
+    assert_eq!(pruned, 1);
+    assert!(!revocation.is_revoked("tid").await.unwrap());
+}
+
+#[tokio::test]
+async fn Transaction_Unreachable_IsInternalServerError() {
+    let manager =
+        PostgresConnectionManager::new_from_stringlike("postgres://127.0.0.1:1", NoTls).unwrap();
+    let pool: ConnectionPool = Pool::builder()
+        .connection_timeout(Duration::from_millis(500))
+        .build_unchecked(manager);
+
+    let result = transaction(&pool, |_| Box::pin(async { Ok(()) })).await;
+
+    assert_eq!(
+        result.unwrap_err().status,
+        StatusCode::INTERNAL_SERVER_ERROR
+    );
+}
+
+#[tokio::test]
+#[ignore = "requires a database at `TEST_DATABASE_URL`"]
+async fn Transaction_ClosureError_RollsBack() {
+    let connection_string = std::env::var("TEST_DATABASE_URL").unwrap();
+    // A single connection, so every query sees the temporary table.
+    let config = PoolConfig::default().max_size(1);
+    let pool = setup_connection_pool_with_config(connection_string, &config)
+        .await
+        .unwrap();
+    pool.get()
+        .await
+        .unwrap()
+        .batch_execute("CREATE TEMPORARY TABLE IF NOT EXISTS items (name TEXT NOT NULL)")
+        .await
+        .unwrap();
+
+    let result: Result<(), _> = transaction(&pool, |transaction| {
+        Box::pin(async move {
+            transaction
+                .execute("INSERT INTO items (name) VALUES ('rolled back')", &[])
+                .await?;
+            Err(ErrorResponse::bad_request(vec![]))
+        })
+    })
+    .await;
+    assert_eq!(result.unwrap_err().status, StatusCode::BAD_REQUEST);
+
+    transaction(&pool, |transaction| {
+        Box::pin(async move {
+            transaction
+                .execute("INSERT INTO items (name) VALUES ('committed')", &[])
+                .await?;
+            Ok(())
+        })
+    })
+    .await
+    .unwrap();
+
+    let names: Vec<String> = pool
+        .get()
+        .await
+        .unwrap()
+        .query("SELECT name FROM items", &[])
+        .await
+        .unwrap()
+        .iter()
+        .map(|row| row.get("name"))
+        .collect();
+    assert_eq!(names, ["committed"]);
+}
@@ -0,0 +1,312 @@
+#![allow(missing_docs, non_snake_case)]
+
+use axum::{
+    Router,
+    body::Body,
+    extract::{FromRequest, Request},
+    response::IntoResponse,
+    routing::get,
+};
+use http::{
+    StatusCode,
+    header::{ACCEPT_LANGUAGE, CONTENT_TYPE, VARY},
+};
+use tower::ServiceExt;
+use ts_api_helper::{
+    AcceptLanguage, DecodeBase64, ErrorResponse, InlineReportedErrorResponse, Json, Pointer,
+    Problem, ProblemCatalog, ProblemCategory, ReportedError, localize_problems_layer,
+};
+
+#[test]
+fn NotAcceptable_IntoResponse_Is406() {
+    let response = ErrorResponse::not_acceptable().into_response();
+    assert_eq!(response.status(), StatusCode::NOT_ACCEPTABLE);
+}
+
+#[test]
+fn UnsupportedMediaType_IntoResponse_Is415() {
+    let response = ErrorResponse::unsupported_media_type().into_response();
+    assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+}
+
+#[tokio::test]
+async fn JsonExtractor_WrongContentType_Is415() {
+    let request = Request::builder()
+        .header(CONTENT_TYPE, "application/xml")
+        .body(Body::from("<value>1</value>"))
+        .unwrap();
+
+    let Err(rejection) = Json::<u32>::from_request(request, &()).await else {
+        panic!("request should have been rejected");
+    };
+
+    assert_eq!(rejection.status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+}
+
+#[tokio::test]
+async fn JsonExtractor_InvalidBody_Is422() {
+    let request = Request::builder()
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from("\"not a number\""))
+        .unwrap();
+
+    let Err(rejection) = Json::<u32>::from_request(request, &()).await else {
+        panic!("request should have been rejected");
+    };
+
+    assert_eq!(rejection.status, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[test]
+fn Pointer_FieldsAndIndices_IsJsonPath() {
+    let pointer = Pointer::root().field("items").index(3).field("price");
+    assert_eq!(pointer.to_string(), "$.items[3].price");
+
+    let problem = Problem::new(pointer, "must be positive");
+    assert_eq!(problem.pointer, "$.items[3].price");
+}
+
+#[test]
+fn Pointer_NonIdentifierField_UsesBracketNotation() {
+    assert_eq!(
+        Pointer::root().field("content-type").to_string(),
+        "$['content-type']"
+    );
+    assert_eq!(Pointer::root().field("it's").to_string(), "$['it\\'s']");
+    assert_eq!(Pointer::root().field("").to_string(), "$['']");
+}
+
+struct Catalog;
+impl ProblemCatalog for Catalog {
+    fn template(&self, code: &str, language: &str) -> Option<&str> {
+        match (code, language) {
+            ("too_long", "fr") => Some("doit comporter au plus {max} caractères"),
+            _ => None,
+        }
+    }
+}
+
+fn too_long() -> ErrorResponse {
+    ErrorResponse::bad_request(vec![
+        Problem::with_code("$.name", "too_long", "must be at most 10 characters").param("max", 10),
+    ])
+}
+
+#[test]
+fn AcceptLanguage_Parse_OrdersByQualityWithPrimaryFallback() {
+    let languages = AcceptLanguage::parse("fr;q=0.5, en-NZ, de;q=0, *;q=0.1, en;q=0.8");
+
+    assert_eq!(languages.0, ["en-nz", "en", "fr"]);
+}
+
+#[test]
+fn ErrorResponseLocalize_MatchingLanguage_UsesTemplate() {
+    let languages = AcceptLanguage::parse("fr-CA, en;q=0.5");
+    let response = too_long().localize(&Catalog, &languages.0);
+
+    assert_eq!(
+        response.problems[0].detail,
+        "doit comporter au plus 10 caractères"
+    );
+}
+
+#[test]
+fn ErrorResponseLocalize_NoMatchingLanguage_KeepsEnglishDetail() {
+    let languages = AcceptLanguage::parse("de");
+    let response = too_long().localize(&Catalog, &languages.0);
+
+    assert_eq!(response.problems[0].detail, "must be at most 10 characters");
+}
+
+async fn localized_detail(accept_language: &str) -> (serde_json::Value, Option<String>) {
+    let router = Router::new()
+        .route("/", get(|| async { too_long() }))
+        .layer(localize_problems_layer(Catalog));
+    let request = Request::builder()
+        .uri("/")
+        .header(ACCEPT_LANGUAGE, accept_language)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = router.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let vary = response
+        .headers()
+        .get(VARY)
+        .map(|value| value.to_str().unwrap().to_string());
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+    (body["problems"][0]["detail"].clone(), vary)
+}
+
+#[tokio::test]
+async fn LocalizeProblemsLayer_MatchingLanguage_LocalizesResponse() {
+    let (detail, vary) = localized_detail("fr-CA, en;q=0.5").await;
+
+    assert_eq!(detail, "doit comporter au plus 10 caractères");
+    assert_eq!(vary.as_deref(), Some("accept-language"));
+}
+
+#[tokio::test]
+async fn LocalizeProblemsLayer_NoMatchingLanguage_KeepsEnglishDetail() {
+    let (detail, _) = localized_detail("de").await;
+
+    assert_eq!(detail, "must be at most 10 characters");
+}
+
+#[tokio::test]
+async fn ForbiddenWith_Problems_AreSerialized() {
+    let response =
+        ErrorResponse::forbidden_with(vec![Problem::new("$", "missing scope billing:write")])
+            .into_response();
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["problems"][0]["detail"], "missing scope billing:write");
+}
+
+#[test]
+fn FromProblems_MixedCategories_UsesMostSevere() {
+    let problem = |category| Problem::new("$", "problem").with_category(category);
+
+    let cases = [
+        (
+            vec![Problem::new("$.name", "is required")],
+            StatusCode::BAD_REQUEST,
+        ),
+        (
+            vec![
+                Problem::new("$.name", "is required"),
+                problem(ProblemCategory::Conflict),
+            ],
+            StatusCode::CONFLICT,
+        ),
+        (
+            vec![
+                problem(ProblemCategory::RateLimit),
+                problem(ProblemCategory::Authorization),
+            ],
+            StatusCode::FORBIDDEN,
+        ),
+        (
+            vec![
+                problem(ProblemCategory::Authorization),
+                problem(ProblemCategory::Authentication),
+            ],
+            StatusCode::UNAUTHORIZED,
+        ),
+        (
+            vec![
+                problem(ProblemCategory::Internal),
+                problem(ProblemCategory::Authentication),
+            ],
+            StatusCode::INTERNAL_SERVER_ERROR,
+        ),
+        (vec![], StatusCode::BAD_REQUEST),
+    ];
+
+    for (problems, status) in cases {
+        assert_eq!(ErrorResponse::from_problems(problems).status, status);
+    }
+}
+
+#[test]
+fn ProblemStatusHint_Category_MatchesStatus() {
+    assert_eq!(Problem::new("$", "problem").status_hint(), None);
+    assert_eq!(
+        Problem::new("$", "problem")
+            .with_category(ProblemCategory::RateLimit)
+            .status_hint(),
+        Some(StatusCode::TOO_MANY_REQUESTS)
+    );
+}
+
+#[tokio::test]
+async fn Unauthenticated_NoProblems_HasEmptyBody() {
+    let response = ErrorResponse::unauthenticated().into_response();
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert!(body.is_empty());
+}
+
+#[test]
+fn FromBase64Error_InvalidBase64_Is400WithoutInput() {
+    fn handler(id: &str) -> Result<Vec<u8>, ErrorResponse> {
+        Ok(id.decode_base64()?)
+    }
+
+    let response = handler("not*base64").unwrap_err();
+
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert_eq!(response.problems.len(), 1);
+    assert!(!response.problems[0].detail.contains("not*base64"));
+}
+
+#[test]
+fn FromUuidError_InvalidUuid_Is400WithProblem() {
+    fn handler(id: &str) -> Result<uuid::Uuid, ErrorResponse> {
+        Ok(uuid::Uuid::parse_str(id)?)
+    }
+
+    let response = handler("not-a-uuid").unwrap_err();
+
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert_eq!(response.problems.len(), 1);
+    assert_eq!(response.problems[0].detail, "must be a valid UUID");
+}
+
+#[cfg(feature = "anyhow")]
+#[test]
+fn FromAnyhow_ErrorChain_IsOpaqueInternalServerError() {
+    use anyhow::Context;
+
+    fn handler() -> Result<(), ErrorResponse> {
+        Err(anyhow::anyhow!("connection refused")).context("failed to load account")?;
+        Ok(())
+    }
+
+    let response = handler().unwrap_err();
+
+    assert_eq!(response.status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(response.problems.is_empty());
+}
+
+#[tokio::test]
+async fn InternalServerErrorFrom_IntoResponse_KeepsErrorInExtensionsOnly() {
+    let error = std::io::Error::other("database is on fire");
+
+    let response = ErrorResponse::internal_server_error_from(error).into_response();
+
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    let reported = response.extensions().get::<ReportedError>().unwrap();
+    assert_eq!(reported.to_string(), "database is on fire");
+    assert!(reported.0.downcast_ref::<std::io::Error>().is_some());
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert!(body.is_empty());
+
+    let response = ErrorResponse::internal_server_error().into_response();
+    assert!(response.extensions().get::<ReportedError>().is_none());
+}
+
+#[test]
+fn ReportedInternalServerError_Error_IsKeptAsSource() {
+    let result: Result<(), _> = Err(std::io::Error::other("database is on fire"));
+
+    let response = result.reported_internal_server_error().unwrap_err();
+
+    assert_eq!(response.status, StatusCode::INTERNAL_SERVER_ERROR);
+    let source = response.source.unwrap();
+    assert_eq!(source.to_string(), "database is on fire");
+}
@@ -0,0 +1,56 @@
+#![allow(missing_docs, non_snake_case)]
+
+use axum::{Router, body::Body, extract::Request, routing::get};
+use http::{StatusCode, header::AUTHORIZATION};
+use tower::ServiceExt;
+use ts_api_helper::{RequestLimits, request_limits_layer};
+
+async fn get_status(request: Request) -> StatusCode {
+    let router = Router::new()
+        .route("/", get(|| async {}))
+        .layer(request_limits_layer(RequestLimits {
+            max_headers: 4,
+            max_header_bytes: 256,
+        }));
+
+    router.oneshot(request).await.unwrap().status()
+}
+
+#[tokio::test]
+async fn RequestLimits_WithinLimits_IsAllowed() {
+    let request = Request::builder()
+        .uri("/")
+        .header(AUTHORIZATION, "bearer token")
+        .body(Body::empty())
+        .unwrap();
+
+    assert_eq!(get_status(request).await, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn RequestLimits_TooManyHeaders_Is431() {
+    let mut request = Request::builder().uri("/");
+    for index in 0..5 {
+        request = request.header(format!("x-header-{index}"), "value");
+    }
+    let request = request.body(Body::empty()).unwrap();
+
+    assert_eq!(
+        get_status(request).await,
+        StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE
+    );
+}
+
+#[tokio::test]
+async fn RequestLimits_HeaderTooLarge_Is431() {
+    let request = Request::builder()
+        .uri("/")
+        .header(AUTHORIZATION, format!("bearer {}", "a".repeat(256)))
+        .body(Body::empty())
+        .unwrap();
+
+    assert_eq!(
+        get_status(request).await,
+        StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE
+    );
+}
@@ -0,0 +1,77 @@
+#![allow(missing_docs, non_snake_case)]
+
+use axum::extract::FromRequestParts;
+use http::{HeaderName, Request, StatusCode};
+use ts_api_helper::{HasForwardedProtoHeader, RequireHttps};
+
+struct DefaultHeader;
+impl HasForwardedProtoHeader for DefaultHeader {}
+
+struct SslHeader(HeaderName);
+impl HasForwardedProtoHeader for SslHeader {
+    fn forwarded_proto_header(&self) -> &HeaderName {
+        &self.0
+    }
+}
+
+async fn extract<S>(state: &S, header: Option<(&str, &str)>) -> Result<(), StatusCode>
+where
+    S: Send + Sync + HasForwardedProtoHeader,
+{
+    let mut request = Request::builder();
+    if let Some((name, value)) = header {
+        request = request.header(name, value);
+    }
+    let (mut parts, ()) = request.body(()).unwrap().into_parts();
+
+    RequireHttps::from_request_parts(&mut parts, state)
+        .await
+        .map(|_| ())
+        .map_err(|error| error.status)
+}
+
+#[tokio::test]
+async fn RequireHttps_Https_IsExtracted() {
+    for value in ["https", "HTTPS", "https, http"] {
+        let result = extract(&DefaultHeader, Some(("x-forwarded-proto", value))).await;
+        assert_eq!(result, Ok(()), "{value}");
+    }
+}
+
+#[tokio::test]
+async fn RequireHttps_Http_IsForbidden() {
+    for value in ["http", "http, https"] {
+        let result = extract(&DefaultHeader, Some(("x-forwarded-proto", value))).await;
+        assert_eq!(result, Err(StatusCode::FORBIDDEN), "{value}");
+    }
+}
+
+#[tokio::test]
+async fn RequireHttps_MissingHeader_IsForbidden() {
+    assert_eq!(
+        extract(&DefaultHeader, None).await,
+        Err(StatusCode::FORBIDDEN)
+    );
+    assert_eq!(
+        extract(&DefaultHeader, Some(("x-forwarded-ssl", "on"))).await,
+        Err(StatusCode::FORBIDDEN)
+    );
+}
+
+#[tokio::test]
+async fn RequireHttps_ConfiguredHeader_IsUsed() {
+    let state = SslHeader(HeaderName::from_static("x-forwarded-ssl"));
+
+    assert_eq!(
+        extract(&state, Some(("x-forwarded-ssl", "on"))).await,
+        Ok(())
+    );
+    assert_eq!(
+        extract(&state, Some(("x-forwarded-ssl", "off"))).await,
+        Err(StatusCode::FORBIDDEN)
+    );
+    assert_eq!(
+        extract(&state, Some(("x-forwarded-proto", "https"))).await,
+        Err(StatusCode::FORBIDDEN)
+    );
+}
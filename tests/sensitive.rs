@@ -0,0 +1,25 @@
+#![allow(missing_docs, non_snake_case)]
+
+use axum::response::IntoResponse;
+use http::{
+    StatusCode,
+    header::{CACHE_CONTROL, PRAGMA},
+};
+use ts_api_helper::{ErrorResponse, Json, Sensitive};
+
+#[test]
+fn Sensitive_IntoResponse_SetsNoStoreHeaders() {
+    let response = Sensitive(Json("token")).into_response();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers().get(CACHE_CONTROL).unwrap(), "no-store");
+    assert_eq!(response.headers().get(PRAGMA).unwrap(), "no-cache");
+}
+
+#[test]
+fn Sensitive_ErrorResponse_SetsNoStoreHeaders() {
+    let response = Sensitive(ErrorResponse::unauthenticated()).into_response();
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(response.headers().get(CACHE_CONTROL).unwrap(), "no-store");
+}
@@ -1,22 +1,36 @@
 #![allow(missing_docs, non_snake_case)]
 
+use std::sync::Arc;
+
 use base64ct::{Base64UrlUnpadded, Encoding};
+use jiff::{SignedDuration, Timestamp};
 use openssl::{
     bn::{BigNum, BigNumContext},
-    ec::EcGroup,
+    ec::{EcGroup, EcKey},
+    ecdsa::EcdsaSig,
+    hash::MessageDigest,
     nid::Nid,
+    pkey::Private,
+    sign::{Signer, Verifier},
 };
 use ts_api_helper::token::{
-    Algorithm, JsonWebKey, SigningJsonWebKey, VerifyingJsonWebKey,
-    json_web_key::{Curve, JsonWebKeyParameters},
-    json_web_token::TokenType,
+    Algorithm, DecryptingJsonWebKey, EncryptingJsonWebKey, JsonWebEncryption, JsonWebKey,
+    JsonWebToken, SigningJsonWebKey, SymmetricKey, TestClock, VerifiedTokenCache,
+    VerifyingJsonWebKey,
+    extractor::SubjectFormat,
+    json_web_key::{
+        Curve, JsonWebKeyParameters,
+        verifying::{EcFromJwkError, FromJwkError, FromPublicKeyError},
+    },
+    json_web_token::{
+        Audience, Claims, DeserializeTokenError, Issuer, PublicClaims, Subject, TokenPart,
+        TokenType,
+    },
 };
 
-#[test]
-fn SignToken_EC_IsCorrect() {
+fn ec_jwk() -> (JsonWebKey, EcKey<Private>) {
     let ec_key =
-        openssl::ec::EcKey::generate(&EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap())
-            .unwrap();
+        EcKey::generate(&EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap()).unwrap();
 
     let mut ctx = BigNumContext::new().unwrap();
     let mut x = BigNum::new().unwrap();
@@ -26,8 +40,8 @@ fn SignToken_EC_IsCorrect() {
         .affine_coordinates(ec_key.group(), &mut x, &mut y, &mut ctx)
         .unwrap();
 
-    let x = Base64UrlUnpadded::encode_string(&x.to_vec());
-    let y = Base64UrlUnpadded::encode_string(&y.to_vec());
+    let x = Base64UrlUnpadded::encode_string(&x.to_vec_padded(32).unwrap());
+    let y = Base64UrlUnpadded::encode_string(&y.to_vec_padded(32).unwrap());
 
     let jwk = JsonWebKey {
         kid: "1".to_string(),
@@ -38,8 +52,26 @@ fn SignToken_EC_IsCorrect() {
             x,
             y,
         },
+        x5t: None,
+        x5t_s256: None,
     };
 
+    (jwk, ec_key)
+}
+
+fn signing_key() -> SigningJsonWebKey {
+    let (jwk, ec_key) = ec_jwk();
+    signing_key_from(jwk, &ec_key)
+}
+
+fn signing_key_from(jwk: JsonWebKey, ec_key: &EcKey<Private>) -> SigningJsonWebKey {
+    SigningJsonWebKey::try_from_pem(jwk, &ec_key.private_key_to_pem().unwrap()).unwrap()
+}
+
+#[test]
+fn SignToken_EC_IsCorrect() {
+    let (jwk, ec_key) = ec_jwk();
+
     let signing_key =
         SigningJsonWebKey::try_from_pem(jwk.clone(), &ec_key.private_key_to_pem().unwrap())
             .unwrap();
@@ -52,7 +84,7 @@ fn SignToken_EC_IsCorrect() {
         .issue(
             "subject".to_string(),
             TokenType::Consent {
-                act: "Action".to_string(),
+                act: vec!["Action".to_string()],
             },
         )
         .unwrap();
@@ -60,5 +92,886 @@ fn SignToken_EC_IsCorrect() {
     let is_valid = verifying_key.verify(&token).unwrap();
 
     assert!(is_valid);
+    assert_eq!(token.signature.len(), 64);
     assert!(!token.claims.is_expired());
 }
+
+/// With the `ring` feature, ES256 tokens are signed and verified with `ring`, so this checks it
+/// against OpenSSL in both directions.
+#[test]
+fn SignToken_ES256_InteroperatesWithOpenSsl() {
+    let (jwk, ec_key) = ec_jwk();
+    let signing_key = signing_key_from(jwk.clone(), &ec_key);
+    let verifying_key = VerifyingJsonWebKey::try_from(jwk).unwrap();
+    let mut token = signing_key
+        .issue("subject".to_string(), TokenType::Common)
+        .unwrap();
+    let signing_input = token.signing_input();
+
+    let (r, s) = token.signature.split_at(32);
+    let der = EcdsaSig::from_private_components(
+        BigNum::from_slice(r).unwrap(),
+        BigNum::from_slice(s).unwrap(),
+    )
+    .unwrap()
+    .to_der()
+    .unwrap();
+    let mut verifier = Verifier::new(MessageDigest::sha256(), &signing_key.key).unwrap();
+    assert!(
+        verifier
+            .verify_oneshot(&der, signing_input.as_bytes())
+            .unwrap()
+    );
+
+    let der = Signer::new(MessageDigest::sha256(), &signing_key.key)
+        .unwrap()
+        .sign_oneshot_to_vec(signing_input.as_bytes())
+        .unwrap();
+    let signature = EcdsaSig::from_der(&der).unwrap();
+    let mut fixed = signature.r().to_vec_padded(32).unwrap();
+    fixed.extend(signature.s().to_vec_padded(32).unwrap());
+
+    token.signature = fixed;
+    assert!(verifying_key.verify(&token).unwrap());
+    token.signature[10] ^= 0x01;
+    assert!(!verifying_key.verify(&token).unwrap());
+    token.signature = der;
+    assert!(!verifying_key.verify(&token).unwrap());
+}
+
+/// With the `ring` feature, the key is converted for `ring` to sign, so this checks a replaced key
+/// is not signed with a stale conversion.
+#[cfg(feature = "ring")]
+#[test]
+fn SignToken_RingReplacedKey_SignsWithNewKey() {
+    let (jwk, ec_key) = ec_jwk();
+    let mut signing_key = signing_key_from(jwk, &ec_key);
+    let first = signing_key
+        .issue("subject".to_string(), TokenType::Common)
+        .unwrap();
+
+    let (jwk, ec_key) = ec_jwk();
+    let replacement = signing_key_from(jwk.clone(), &ec_key);
+    signing_key.jwk = replacement.jwk;
+    signing_key.key = replacement.key;
+    let second = signing_key
+        .issue("subject".to_string(), TokenType::Common)
+        .unwrap();
+
+    let verifying_key = VerifyingJsonWebKey::try_from(jwk).unwrap();
+    assert!(!verifying_key.verify(&first).unwrap());
+    assert!(verifying_key.verify(&second).unwrap());
+}
+
+#[test]
+fn JsonSerialization_RoundTrip_MatchesCompact() {
+    let token = signing_key()
+        .issue("subject".to_string(), TokenType::Common)
+        .unwrap();
+
+    let compact = token.serialize();
+    let json = JsonWebToken::deserialize(&compact)
+        .unwrap()
+        .to_json_serialization();
+    let from_json = JsonWebToken::from_json_serialization(&json).unwrap();
+
+    assert_eq!(from_json.serialize(), compact);
+}
+
+#[test]
+fn JsonSerialization_InvalidJson_IsNone() {
+    assert!(JsonWebToken::from_json_serialization("{\"protected\":\"\"}").is_none());
+    assert!(JsonWebToken::from_json_serialization("a.b.c").is_none());
+}
+
+#[cfg(feature = "jsonwebtoken")]
+#[test]
+fn JsonWebTokenCompat_TokenIssued_IsVerifiedByJsonWebToken() {
+    let (jwk, ec_key) = ec_jwk();
+    let signing_key =
+        SigningJsonWebKey::try_from_pem(jwk.clone(), &ec_key.private_key_to_pem().unwrap())
+            .unwrap();
+    let verifying_key = VerifyingJsonWebKey::try_from(jwk).unwrap();
+
+    let token = signing_key
+        .issue("subject".to_string(), TokenType::Common)
+        .unwrap();
+
+    let key = verifying_key.decoding_key().unwrap();
+    let validation = jsonwebtoken::Validation::new(verifying_key.jwk.alg.clone().into());
+    let decoded = jsonwebtoken::decode::<Claims>(&token.serialize(), &key, &validation).unwrap();
+
+    assert_eq!(decoded.claims.sub, "subject");
+    assert!(matches!(
+        Algorithm::try_from(decoded.header.alg),
+        Ok(Algorithm::ES256)
+    ));
+}
+
+#[cfg(feature = "jsonwebtoken")]
+#[test]
+fn JsonWebTokenCompat_TokenData_RoundTripsHeaderAndClaims() {
+    let signing_key = signing_key();
+    let token = signing_key
+        .issue("subject".to_string(), TokenType::Provisioning)
+        .unwrap();
+
+    let token_data = jsonwebtoken::TokenData::<serde_json::Value>::from(&token);
+    assert_eq!(token_data.header.kid.as_deref(), Some("1"));
+    assert_eq!(token_data.header.alg, jsonwebtoken::Algorithm::ES256);
+    assert_eq!(token_data.claims["sub"], "subject");
+    assert_eq!(token_data.claims["typ"], "provisioning");
+    assert_eq!(token_data.claims["tid"], token.claims.tid.as_str());
+
+    let converted = JsonWebToken::try_from(token_data).unwrap();
+    assert_eq!(converted.header, token.header);
+    assert_eq!(converted.claims, token.claims);
+    assert!(converted.signature.is_empty());
+
+    // Token data decoded by `jsonwebtoken` converts to the same header and claims.
+    let verifying_key = VerifyingJsonWebKey::try_from(signing_key.jwk.clone()).unwrap();
+    let validation = jsonwebtoken::Validation::new(jsonwebtoken::Algorithm::ES256);
+    let decoded = jsonwebtoken::decode::<serde_json::Value>(
+        &token.serialize(),
+        &verifying_key.decoding_key().unwrap(),
+        &validation,
+    )
+    .unwrap();
+    let converted = JsonWebToken::try_from(decoded).unwrap();
+    assert_eq!(converted.claims, token.claims);
+}
+
+#[cfg(feature = "jsonwebtoken")]
+#[test]
+fn JsonWebTokenCompat_SignedByJsonWebToken_IsVerified() {
+    let (jwk, ec_key) = ec_jwk();
+    let verifying_key = VerifyingJsonWebKey::try_from(jwk.clone()).unwrap();
+    let mut token = signing_key_from(jwk.clone(), &ec_key)
+        .issue("subject".to_string(), TokenType::Common)
+        .unwrap();
+
+    let pkcs8 = openssl::pkey::PKey::from_ec_key(ec_key)
+        .unwrap()
+        .private_key_to_pkcs8()
+        .unwrap();
+    let key = jsonwebtoken::EncodingKey::from_ec_der(&pkcs8);
+    let signing_input = token.signing_input();
+    let signature =
+        jsonwebtoken::crypto::sign(signing_input.as_bytes(), &key, jwk.alg.clone().into()).unwrap();
+    token.signature = Base64UrlUnpadded::decode_vec(&signature).unwrap();
+
+    assert_eq!(token.signature.len(), 64);
+    assert!(verifying_key.verify(&token).unwrap());
+}
+
+#[test]
+fn JsonWebEncryption_RoundTrip_DecryptsPlaintext() {
+    let (jwk, ec_key) = ec_jwk();
+
+    let encrypting_key =
+        EncryptingJsonWebKey::try_from_parameters(jwk.kid.clone(), &jwk.parameters).unwrap();
+    let decrypting_key =
+        DecryptingJsonWebKey::try_from_pem(jwk.kid, &ec_key.private_key_to_pem().unwrap()).unwrap();
+
+    let jwe = encrypting_key.encrypt(b"sensitive claims").unwrap();
+    let serialized = jwe.serialize();
+    assert_eq!(serialized.split('.').count(), 5);
+
+    let jwe = JsonWebEncryption::deserialize(&serialized).unwrap();
+    let plaintext = decrypting_key.decrypt(&jwe).unwrap();
+
+    assert_eq!(plaintext, b"sensitive claims");
+}
+
+#[test]
+fn JsonWebEncryption_TamperedCiphertext_FailsToDecrypt() {
+    let (jwk, ec_key) = ec_jwk();
+
+    let encrypting_key =
+        EncryptingJsonWebKey::try_from_parameters(jwk.kid.clone(), &jwk.parameters).unwrap();
+    let decrypting_key =
+        DecryptingJsonWebKey::try_from_pem(jwk.kid, &ec_key.private_key_to_pem().unwrap()).unwrap();
+
+    let mut jwe = encrypting_key.encrypt(b"sensitive claims").unwrap();
+    jwe.ciphertext[0] ^= 1;
+
+    assert!(decrypting_key.decrypt(&jwe).is_err());
+}
+
+#[test]
+fn JsonWebEncryption_TruncatedTag_FailsToDecrypt() {
+    let (jwk, ec_key) = ec_jwk();
+
+    let encrypting_key =
+        EncryptingJsonWebKey::try_from_parameters(jwk.kid.clone(), &jwk.parameters).unwrap();
+    let decrypting_key =
+        DecryptingJsonWebKey::try_from_pem(jwk.kid, &ec_key.private_key_to_pem().unwrap()).unwrap();
+
+    let mut jwe = encrypting_key.encrypt(b"sensitive claims").unwrap();
+    jwe.tag.truncate(1);
+
+    assert!(JsonWebEncryption::deserialize(&jwe.serialize()).is_none());
+    assert!(decrypting_key.decrypt(&jwe).is_err());
+}
+
+#[tokio::test]
+async fn VerifiedTokenCache_WithinTtl_IsHit() {
+    let token = signing_key()
+        .issue("subject".to_string(), TokenType::Common)
+        .unwrap();
+    let serialized = token.serialize();
+
+    let cache = VerifiedTokenCache::new(SignedDuration::from_secs(5));
+    assert!(cache.get(&serialized).await.is_none());
+
+    cache.insert(serialized.clone(), token).await;
+    assert!(cache.get(&serialized).await.is_some());
+}
+
+#[tokio::test]
+async fn VerifiedTokenCache_PastTtl_IsMiss() {
+    let token = signing_key()
+        .issue("subject".to_string(), TokenType::Common)
+        .unwrap();
+    let serialized = token.serialize();
+
+    let cache = VerifiedTokenCache::new(SignedDuration::ZERO);
+    cache.insert(serialized.clone(), token).await;
+
+    assert!(cache.get(&serialized).await.is_none());
+}
+
+#[tokio::test]
+async fn VerifiedTokenCache_Insert_PrunesAtMostOncePerTtl() {
+    let start = Timestamp::now();
+    let clock = TestClock::new(start);
+    let signing_key = signing_key();
+    let issue = || {
+        signing_key
+            .issue_with_clock("subject".to_string(), TokenType::Common, &clock)
+            .unwrap()
+    };
+    let ttl = SignedDuration::from_secs(5);
+    let cache = VerifiedTokenCache::new(ttl).with_clock(Arc::new(clock.clone()));
+
+    let first = issue();
+    let first_serialized = first.serialize();
+    cache.insert(first_serialized.clone(), first).await;
+    clock.set(start + SignedDuration::from_secs(3));
+    assert_eq!(cache.prune().await, 0);
+
+    // The first token is stale, but the cache was pruned less than `ttl` ago.
+    clock.set(start + ttl);
+    let second = issue();
+    cache.insert(second.serialize(), second).await;
+    assert_eq!(cache.len().await, 2);
+
+    clock.set(start + SignedDuration::from_secs(8));
+    let third = issue();
+    cache.insert(third.serialize(), third).await;
+    assert_eq!(cache.len().await, 2);
+    assert!(!cache.cache.read().await.contains_key(&first_serialized));
+}
+
+#[tokio::test]
+async fn VerifiedTokenCache_ClockPastExpiry_IsPruned() {
+    let clock = TestClock::new(Timestamp::now());
+    let token = signing_key()
+        .issue_with_clock("subject".to_string(), TokenType::Common, &clock)
+        .unwrap();
+    let serialized = token.serialize();
+
+    let cache = VerifiedTokenCache::new(SignedDuration::from_hours(24 * 365))
+        .with_clock(Arc::new(clock.clone()));
+    cache.insert(serialized.clone(), token.clone()).await;
+    assert_eq!(cache.len().await, 1);
+    assert_eq!(cache.prune().await, 0);
+
+    clock.set(token.claims.exp + SignedDuration::from_secs(1));
+    assert_eq!(cache.prune().await, 1);
+    assert!(cache.is_empty().await);
+
+    cache.insert(serialized.clone(), token).await;
+    assert!(cache.get(&serialized).await.is_none());
+    assert!(cache.is_empty().await);
+
+    clock.set(Timestamp::now());
+    let token = signing_key()
+        .issue("subject".to_string(), TokenType::Common)
+        .unwrap();
+    cache.insert(token.serialize(), token).await;
+    cache.clear().await;
+    assert_eq!(cache.len().await, 0);
+}
+
+#[test]
+fn ClaimsIsExpiredAt_Boundaries_RespectLeeway() {
+    let token = signing_key()
+        .issue("subject".to_string(), TokenType::Common)
+        .unwrap();
+    let exp = token.claims.exp;
+    let leeway = SignedDuration::from_secs(30);
+
+    assert!(!token.claims.is_expired_at(exp, SignedDuration::ZERO));
+    assert!(
+        token
+            .claims
+            .is_expired_at(exp + SignedDuration::from_secs(1), SignedDuration::ZERO)
+    );
+    assert!(!token.claims.is_expired_at(exp + leeway, leeway));
+    assert!(
+        token
+            .claims
+            .is_expired_at(exp + leeway + SignedDuration::from_secs(1), leeway)
+    );
+}
+
+#[test]
+fn ClaimsIsExpiredAt_ExtremeLeeway_DoesNotOverflow() {
+    let mut token = signing_key()
+        .issue("subject".to_string(), TokenType::Common)
+        .unwrap();
+    token.claims.exp = Timestamp::MAX;
+
+    assert!(
+        !token
+            .claims
+            .is_expired_at(Timestamp::MAX, SignedDuration::MAX)
+    );
+
+    token.claims.exp = Timestamp::MIN;
+
+    assert!(
+        token
+            .claims
+            .is_expired_at(Timestamp::now(), SignedDuration::MIN)
+    );
+}
+
+#[test]
+fn SigningJsonWebKeyGenerate_ES256_RoundTripsThroughPem() {
+    let signing_key =
+        SigningJsonWebKey::generate("generated".to_string(), Algorithm::ES256).unwrap();
+
+    let loaded =
+        SigningJsonWebKey::try_from_pem(signing_key.jwk.clone(), &signing_key.to_pem().unwrap())
+            .unwrap();
+    let verifying_key = VerifyingJsonWebKey::try_from(signing_key.jwk.clone()).unwrap();
+
+    let token = loaded
+        .issue("subject".to_string(), TokenType::Common)
+        .unwrap();
+
+    assert_eq!(signing_key.jwk.kid, "generated");
+    assert!(verifying_key.verify(&token).unwrap());
+}
+
+#[test]
+fn SigningJsonWebKeyGenerate_EdDSA_RoundTripsThroughPem() {
+    let signing_key = SigningJsonWebKey::generate("ed".to_string(), Algorithm::EdDSA).unwrap();
+
+    let loaded =
+        SigningJsonWebKey::try_from_pem(signing_key.jwk.clone(), &signing_key.to_pem().unwrap())
+            .unwrap();
+    let verifying_key = VerifyingJsonWebKey::try_from(signing_key.jwk.clone()).unwrap();
+
+    let token = loaded
+        .issue("subject".to_string(), TokenType::Common)
+        .unwrap();
+
+    assert_eq!(token.signature.len(), 64);
+    assert!(verifying_key.verify(&token).unwrap());
+    assert_eq!(
+        verifying_key.verify_str(&token.serialize()).unwrap(),
+        Some(token.clone())
+    );
+
+    let mut tampered = token;
+    tampered.claims.sub = "other".into();
+    assert!(!verifying_key.verify(&tampered).unwrap());
+}
+
+#[test]
+fn VerifyingJsonWebKeyFromEd25519_PemAndRaw_VerifyToken() {
+    let signing_key = SigningJsonWebKey::generate("ed".to_string(), Algorithm::EdDSA).unwrap();
+    let token = signing_key
+        .issue("subject".to_string(), TokenType::Common)
+        .unwrap();
+
+    let pem = signing_key.key.public_key_to_pem().unwrap();
+    let from_pem = VerifyingJsonWebKey::from_ed25519_pem("ed".to_string(), &pem).unwrap();
+    let raw = signing_key.key.raw_public_key().unwrap();
+    let from_raw = VerifyingJsonWebKey::from_ed25519_raw("ed".to_string(), &raw).unwrap();
+
+    assert!(from_pem.verify(&token).unwrap());
+    assert!(from_raw.verify(&token).unwrap());
+    assert_eq!(from_pem.jwk, signing_key.jwk);
+    assert_eq!(
+        serde_json::to_value(&from_raw.jwk.parameters).unwrap(),
+        serde_json::json!({
+            "kty": "OKP",
+            "crv": "Ed25519",
+            "x": Base64UrlUnpadded::encode_string(&raw),
+        })
+    );
+
+    let ec_key = SigningJsonWebKey::generate("ec".to_string(), Algorithm::ES256).unwrap();
+    let ec_pem = ec_key.key.public_key_to_pem().unwrap();
+    assert!(matches!(
+        VerifyingJsonWebKey::from_ed25519_pem("ec".to_string(), &ec_pem),
+        Err(FromPublicKeyError::NotEd25519 { .. })
+    ));
+    assert!(VerifyingJsonWebKey::from_ed25519_raw("ed".to_string(), &raw[..31]).is_err());
+}
+
+#[test]
+fn ConsentAct_SingleString_DeserializesToOneAction() {
+    let token_type: TokenType =
+        serde_json::from_str(r#"{"typ": "consent", "act": "delete:account"}"#).unwrap();
+
+    assert_eq!(
+        token_type,
+        TokenType::Consent {
+            act: vec!["delete:account".to_string()]
+        }
+    );
+    assert_eq!(
+        serde_json::to_value(&token_type).unwrap(),
+        serde_json::json!({"typ": "consent", "act": "delete:account"})
+    );
+}
+
+#[test]
+fn ConsentAct_Array_DeserializesToManyActions() {
+    let token_type: TokenType =
+        serde_json::from_str(r#"{"typ": "consent", "act": ["delete:account", "export:data"]}"#)
+            .unwrap();
+
+    assert!(token_type.grants_action("delete:account"));
+    assert!(token_type.grants_action("export:data"));
+    assert!(!token_type.grants_action("update:account"));
+    assert_eq!(
+        serde_json::to_value(&token_type).unwrap(),
+        serde_json::json!({"typ": "consent", "act": ["delete:account", "export:data"]})
+    );
+}
+
+#[test]
+fn VerifyingJsonWebKey_AlgorithmCurveMismatch_IsRejected() {
+    let ec_key = EcKey::generate(&EcGroup::from_curve_name(Nid::SECP384R1).unwrap()).unwrap();
+
+    let mut ctx = BigNumContext::new().unwrap();
+    let mut x = BigNum::new().unwrap();
+    let mut y = BigNum::new().unwrap();
+    ec_key
+        .public_key()
+        .affine_coordinates(ec_key.group(), &mut x, &mut y, &mut ctx)
+        .unwrap();
+
+    let jwk = JsonWebKey {
+        kid: "1".to_string(),
+        alg: Algorithm::ES256,
+        usage: "sig".to_string(),
+        parameters: JsonWebKeyParameters::EC {
+            crv: Curve::P384,
+            x: Base64UrlUnpadded::encode_string(&x.to_vec_padded(48).unwrap()),
+            y: Base64UrlUnpadded::encode_string(&y.to_vec_padded(48).unwrap()),
+        },
+        x5t: None,
+        x5t_s256: None,
+    };
+
+    assert!(matches!(
+        VerifyingJsonWebKey::try_from(jwk),
+        Err(FromJwkError::AlgorithmCurveMismatch {
+            crv: Curve::P384,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn VerifyingJsonWebKey_EcKeyOnEdwardsCurve_IsUnsupportedCurve() {
+    let jwk = JsonWebKey {
+        kid: "1".to_string(),
+        alg: Algorithm::EdDSA,
+        usage: "sig".to_string(),
+        parameters: JsonWebKeyParameters::EC {
+            crv: Curve::Ed25519,
+            x: Base64UrlUnpadded::encode_string(&[1; 32]),
+            y: Base64UrlUnpadded::encode_string(&[2; 32]),
+        },
+        x5t: None,
+        x5t_s256: None,
+    };
+
+    assert!(matches!(
+        VerifyingJsonWebKey::try_from(jwk),
+        Err(FromJwkError::Ec {
+            source: EcFromJwkError::UnsupportedCurve {
+                crv: Curve::Ed25519,
+                ..
+            },
+        })
+    ));
+}
+
+#[test]
+fn JsonWebToken_DeserializedRoundTrip_IsEqualAndHashesEqual() {
+    use std::collections::HashSet;
+
+    let token = signing_key()
+        .issue("subject".to_string(), TokenType::Common)
+        .unwrap();
+    let round_trip = JsonWebToken::deserialize(&token.serialize()).unwrap();
+
+    assert_eq!(round_trip, token);
+
+    let tokens: HashSet<_> = [token, round_trip].into_iter().collect();
+    assert_eq!(tokens.len(), 1);
+}
+
+fn claims_with_aud(aud: serde_json::Value) -> Claims {
+    serde_json::from_value(serde_json::json!({
+        "tid": "1",
+        "exp": 0,
+        "iat": 0,
+        "sub": "subject",
+        "aud": aud,
+        "typ": "common",
+    }))
+    .unwrap()
+}
+
+#[test]
+fn ClaimsAud_StringForm_IsSingleAudience() {
+    let claims = claims_with_aud(serde_json::json!("api"));
+
+    assert_eq!(claims.aud, ["api"]);
+    assert!(claims.has_audience("api"));
+}
+
+#[test]
+fn ClaimsAud_ArrayForm_ContainsEachAudience() {
+    let claims = claims_with_aud(serde_json::json!(["api", "admin"]));
+
+    assert!(claims.has_audience("api"));
+    assert!(claims.has_audience("admin"));
+}
+
+#[test]
+fn ClaimsAud_NonMatchingAudience_IsRejected() {
+    let claims = claims_with_aud(serde_json::json!(["api", "admin"]));
+
+    assert!(!claims.has_audience("billing"));
+}
+
+#[test]
+fn ClaimsAud_Absent_IsEmptyAndNotSerialized() {
+    let token = signing_key()
+        .issue("subject".to_string(), TokenType::Common)
+        .unwrap();
+
+    assert!(token.claims.aud.is_empty());
+    assert!(
+        serde_json::to_value(&token.claims)
+            .unwrap()
+            .get("aud")
+            .is_none()
+    );
+}
+
+#[test]
+fn SigningInput_IssuedToken_IsSerializedPrefix() {
+    let token = signing_key()
+        .issue("subject".to_string(), TokenType::Common)
+        .unwrap();
+
+    let serialized = token.serialize();
+    let (signing_input, signature) = serialized.rsplit_once('.').unwrap();
+
+    assert_eq!(token.signing_input(), signing_input);
+    assert_eq!(
+        Base64UrlUnpadded::decode_vec(signature).unwrap(),
+        token.signature
+    );
+}
+
+#[test]
+fn SubjectFormatUuid_UuidSubject_IsAccepted() {
+    assert!(SubjectFormat::Uuid.matches("67e55044-10b1-426f-9247-bb680e5fe0c8"));
+}
+
+#[test]
+fn SubjectFormatUuid_NonUuidSubject_IsRejected() {
+    assert!(!SubjectFormat::Uuid.matches("'; drop table users; --"));
+    assert!(!SubjectFormat::Uuid.matches("67e5504410b1426f9247bb680e5fe0c8"));
+    assert!(SubjectFormat::Any.matches("'; drop table users; --"));
+}
+
+#[test]
+fn SubjectFormatPattern_WholeSubject_MustMatch() {
+    let format = SubjectFormat::pattern("[a-z]+|[a-z]+-[0-9]").unwrap();
+
+    assert!(format.matches("subject"));
+    assert!(format.matches("subject-1"));
+    assert!(!format.matches("subject-12"));
+}
+
+fn claims_with_exp(exp: serde_json::Value) -> Result<Claims, serde_json::Error> {
+    serde_json::from_value(serde_json::json!({
+        "tid": "1",
+        "exp": exp,
+        "iat": 0,
+        "sub": "subject",
+        "typ": "common",
+    }))
+}
+
+#[test]
+fn ClaimsExp_Integer_IsSeconds() {
+    let claims = claims_with_exp(serde_json::json!(1_700_000_000)).unwrap();
+
+    assert_eq!(claims.exp, Timestamp::from_second(1_700_000_000).unwrap());
+}
+
+#[test]
+fn ClaimsExp_Float_IsTruncatedToSeconds() {
+    let claims = claims_with_exp(serde_json::json!(1_700_000_000.5)).unwrap();
+
+    assert_eq!(claims.exp, Timestamp::from_second(1_700_000_000).unwrap());
+}
+
+#[test]
+fn ClaimsExp_OutOfRange_IsRejected() {
+    assert!(claims_with_exp(serde_json::json!(1e20)).is_err());
+    assert!(claims_with_exp(serde_json::json!(i64::MAX)).is_err());
+    assert!(claims_with_exp(serde_json::json!(u64::MAX)).is_err());
+    assert!(claims_with_exp(serde_json::json!("1700000000")).is_err());
+}
+
+#[test]
+fn VerifyStr_IssuedToken_ReturnsToken() {
+    let (jwk, ec_key) = ec_jwk();
+    let verifying_key = VerifyingJsonWebKey::try_from(jwk.clone()).unwrap();
+    let token = signing_key_from(jwk, &ec_key)
+        .issue("subject".to_string(), TokenType::Common)
+        .unwrap();
+
+    let verified = verifying_key.verify_str(&token.serialize()).unwrap();
+
+    assert_eq!(verified, Some(token));
+}
+
+#[test]
+fn VerifyStr_TamperedOrMalformed_IsNone() {
+    let (jwk, ec_key) = ec_jwk();
+    let verifying_key = VerifyingJsonWebKey::try_from(jwk.clone()).unwrap();
+    let mut token = signing_key_from(jwk, &ec_key)
+        .issue("subject".to_string(), TokenType::Common)
+        .unwrap();
+    token.claims.sub = "admin".into();
+
+    assert_eq!(verifying_key.verify_str(&token.serialize()).unwrap(), None);
+    assert_eq!(verifying_key.verify_str("not.a.token").unwrap(), None);
+}
+
+#[cfg(feature = "jsonwebtoken")]
+#[test]
+fn VerifyStr_EncodedByJsonWebToken_IsVerifiedAsSerialized() {
+    let (jwk, ec_key) = ec_jwk();
+    let verifying_key = VerifyingJsonWebKey::try_from(jwk.clone()).unwrap();
+    let claims = signing_key_from(jwk.clone(), &ec_key)
+        .issue("subject".to_string(), TokenType::Common)
+        .unwrap()
+        .claims;
+
+    let pkcs8 = openssl::pkey::PKey::from_ec_key(ec_key)
+        .unwrap()
+        .private_key_to_pkcs8()
+        .unwrap();
+    let mut header = jsonwebtoken::Header::new(jsonwebtoken::Algorithm::ES256);
+    header.kid = Some(jwk.kid.clone());
+    let serialized = jsonwebtoken::encode(
+        &header,
+        &claims,
+        &jsonwebtoken::EncodingKey::from_ec_der(&pkcs8),
+    )
+    .unwrap();
+
+    let verified = verifying_key.verify_str(&serialized).unwrap().unwrap();
+
+    assert_eq!(verified.claims, claims);
+}
+
+fn symmetric_key() -> SymmetricKey {
+    SymmetricKey::new(
+        "hmac".to_string(),
+        b"a shared secret of at least 32 bytes".to_vec(),
+    )
+}
+
+#[test]
+fn SymmetricKey_IssuedToken_IsVerified() {
+    let key = symmetric_key();
+    let token = key.issue("subject".to_string(), TokenType::Common).unwrap();
+
+    assert_eq!(token.header.alg, Algorithm::HS256);
+    assert_eq!(token.signature.len(), 32);
+    assert!(key.verify(&token).unwrap());
+    assert_eq!(key.verify_str(&token.serialize()).unwrap(), Some(token));
+}
+
+#[test]
+fn SymmetricKey_TamperedOrOtherSecret_IsRejected() {
+    let key = symmetric_key();
+    let mut token = key.issue("subject".to_string(), TokenType::Common).unwrap();
+
+    let other = SymmetricKey::new("hmac".to_string(), b"another secret".to_vec());
+    assert!(!other.verify(&token).unwrap());
+
+    token.claims.sub = "admin".into();
+    assert!(!key.verify(&token).unwrap());
+    assert_eq!(key.verify_str(&token.serialize()).unwrap(), None);
+}
+
+#[test]
+fn SymmetricKey_CrossAlgorithm_IsRejected() {
+    let (jwk, ec_key) = ec_jwk();
+    let verifying_key = VerifyingJsonWebKey::try_from(jwk.clone()).unwrap();
+
+    // A token signed with the EC key is not accepted by the HMAC key.
+    let ec_token = signing_key_from(jwk, &ec_key)
+        .issue("subject".to_string(), TokenType::Common)
+        .unwrap();
+    let key = symmetric_key();
+    assert!(!key.verify(&ec_token).unwrap());
+    let mut relabelled = ec_token.clone();
+    relabelled.header.alg = Algorithm::HS256;
+    assert!(!key.verify(&relabelled).unwrap());
+
+    // A token signed with HMAC, using the public key as the secret, is not accepted by the EC key.
+    let public_key = verifying_key.key.public_key_to_pem().unwrap();
+    let forged = SymmetricKey::new(verifying_key.jwk.kid.clone(), public_key)
+        .issue("admin".to_string(), TokenType::Common)
+        .unwrap();
+    assert!(!verifying_key.verify(&forged).unwrap());
+    assert_eq!(verifying_key.verify_str(&forged.serialize()).unwrap(), None);
+}
+
+#[test]
+fn SigningJsonWebKeyGenerate_HS256_IsUnsupported() {
+    assert!(SigningJsonWebKey::generate("kid".to_string(), Algorithm::HS256).is_err());
+}
+
+#[cfg(feature = "jsonwebtoken")]
+#[test]
+fn JsonWebTokenCompat_SymmetricKeyIssued_IsVerifiedByJsonWebToken() {
+    let token = symmetric_key()
+        .issue("subject".to_string(), TokenType::Common)
+        .unwrap();
+
+    let key = jsonwebtoken::DecodingKey::from_secret(b"a shared secret of at least 32 bytes");
+    let validation = jsonwebtoken::Validation::new(Algorithm::HS256.into());
+    let decoded = jsonwebtoken::decode::<Claims>(&token.serialize(), &key, &validation).unwrap();
+
+    assert_eq!(decoded.claims.sub, "subject");
+}
+
+#[test]
+fn PublicClaims_FromClaims_OmitsInternalClaims() {
+    let token = signing_key()
+        .issue(
+            "subject".to_string(),
+            TokenType::Consent {
+                act: vec!["delete:account".to_string()],
+            },
+        )
+        .unwrap();
+
+    let value = serde_json::to_value(PublicClaims::from(&token)).unwrap();
+
+    assert_eq!(
+        value,
+        serde_json::json!({
+            "subject": "subject",
+            "issuedAt": token.claims.iat.to_string(),
+            "expiresAt": token.claims.exp.to_string(),
+            "tokenType": "consent",
+            "actions": ["delete:account"],
+        })
+    );
+    assert!(value.get("tid").is_none());
+}
+
+#[test]
+fn JsonWebToken_ParseToString_RoundTrips() {
+    let token = signing_key()
+        .issue("subject".to_string(), TokenType::Common)
+        .unwrap();
+
+    let parsed: JsonWebToken = token.to_string().parse().unwrap();
+
+    assert_eq!(token.to_string(), token.serialize());
+    assert_eq!(parsed, token);
+}
+
+#[test]
+fn JsonWebToken_ParseMalformed_ReturnsError() {
+    let token = signing_key()
+        .issue("subject".to_string(), TokenType::Common)
+        .unwrap();
+    let serialized = token.serialize();
+    let (header, _) = serialized.split_once('.').unwrap();
+
+    assert!(matches!(
+        header.parse::<JsonWebToken>(),
+        Err(DeserializeTokenError::MissingPart {
+            part: TokenPart::Claims,
+            ..
+        })
+    ));
+    assert!(matches!(
+        format!("{header}.e30.!").parse::<JsonWebToken>(),
+        Err(DeserializeTokenError::Json {
+            part: TokenPart::Claims,
+            ..
+        })
+    ));
+    assert!(matches!(
+        format!("{}.!", token.signing_input()).parse::<JsonWebToken>(),
+        Err(DeserializeTokenError::Base64 {
+            part: TokenPart::Signature,
+            ..
+        })
+    ));
+    assert!(matches!(
+        format!("{serialized}.e30").parse::<JsonWebToken>(),
+        Err(DeserializeTokenError::TrailingPart { .. })
+    ));
+    assert!(JsonWebToken::deserialize(&format!("{serialized}.")).is_none());
+}
+
+#[test]
+fn Claims_TypedIssuerSubjectAudience_SerializeAsPlainStrings() {
+    let claims: Claims = serde_json::from_value(serde_json::json!({
+        "tid": "1",
+        "exp": 0,
+        "iat": 0,
+        "iss": "https://issuer.example",
+        "sub": "subject",
+        "aud": ["api", "admin"],
+        "typ": "common",
+    }))
+    .unwrap();
+
+    assert_eq!(claims.iss, Some(Issuer::from("https://issuer.example")));
+    assert_eq!(claims.sub, Subject::from("subject"));
+    assert_eq!(claims.aud, [Audience::from("api"), Audience::from("admin")]);
+    assert_eq!(claims.sub.to_string(), "subject");
+
+    let value = serde_json::to_value(&claims).unwrap();
+    assert_eq!(value["iss"], "https://issuer.example");
+    assert_eq!(value["sub"], "subject");
+    assert_eq!(value["aud"], serde_json::json!(["api", "admin"]));
+}
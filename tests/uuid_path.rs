@@ -0,0 +1,41 @@
+#![allow(missing_docs, non_snake_case)]
+
+use axum::{Router, body::Body, extract::Request, routing::get};
+use http::StatusCode;
+use tower::ServiceExt;
+use ts_api_helper::UuidPath;
+
+fn router() -> Router {
+    Router::new().route(
+        "/items/{id}",
+        get(|UuidPath(id): UuidPath| async move { id.to_string() }),
+    )
+}
+
+async fn get_status(uri: &str) -> (StatusCode, Vec<u8>) {
+    let request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+    let response = router().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, body.to_vec())
+}
+
+#[tokio::test]
+async fn UuidPath_ValidUuid_IsExtracted() {
+    let (status, body) = get_status("/items/67e55044-10b1-426f-9247-bb680e5fe0c8").await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, b"67e55044-10b1-426f-9247-bb680e5fe0c8");
+}
+
+#[tokio::test]
+async fn UuidPath_InvalidUuid_IsBadRequestPointingAtParameter() {
+    let (status, body) = get_status("/items/not-a-uuid").await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["problems"][0]["pointer"], "$.id");
+}
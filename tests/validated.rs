@@ -0,0 +1,116 @@
+#![allow(missing_docs, non_snake_case)]
+
+use axum::{
+    Router,
+    body::Body,
+    extract::{FromRequestParts, Request},
+    routing::get,
+};
+use http::{StatusCode, request::Parts};
+use tower::ServiceExt;
+use ts_api_helper::{ErrorResponse, Pointer, Problem, Validated};
+
+/// Extractor for a header that must be a number.
+struct NumberHeader<const NAME: char>(u32);
+
+impl<S, const NAME: char> FromRequestParts<S> for NumberHeader<NAME>
+where
+    S: Send + Sync,
+{
+    type Rejection = ErrorResponse;
+
+    async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Self::Rejection> {
+        let name = format!("x-{NAME}");
+
+        parts
+            .headers
+            .get(&name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok())
+            .map(Self)
+            .ok_or_else(|| {
+                ErrorResponse::bad_request(vec![Problem::new(
+                    Pointer::root().field(&name),
+                    "must be a number",
+                )])
+            })
+    }
+}
+
+/// Extractor that always rejects the request as unauthenticated.
+struct Unauthenticated;
+
+impl<S> FromRequestParts<S> for Unauthenticated
+where
+    S: Send + Sync,
+{
+    type Rejection = ErrorResponse;
+
+    async fn from_request_parts(_: &mut Parts, _: &S) -> Result<Self, Self::Rejection> {
+        Err(ErrorResponse::unauthenticated())
+    }
+}
+
+fn router() -> Router {
+    Router::new()
+        .route(
+            "/",
+            get(
+                |Validated((NumberHeader(a), NumberHeader(b))): Validated<(
+                    NumberHeader<'a'>,
+                    NumberHeader<'b'>,
+                )>| async move { (a + b).to_string() },
+            ),
+        )
+        .route(
+            "/authenticated",
+            get(|_: Validated<(NumberHeader<'a'>, Unauthenticated)>| async move { "" }),
+        )
+}
+
+async fn get_response(uri: &str, headers: &[(&str, &str)]) -> (StatusCode, Vec<u8>) {
+    let mut request = Request::builder().uri(uri);
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    let request = request.body(Body::empty()).unwrap();
+
+    let response = router().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, body.to_vec())
+}
+
+#[tokio::test]
+async fn Validated_AllValid_IsExtracted() {
+    let (status, body) = get_response("/", &[("x-a", "1"), ("x-b", "2")]).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, b"3");
+}
+
+#[tokio::test]
+async fn Validated_TwoInvalid_IsBadRequestWithBothProblems() {
+    let (status, body) = get_response("/", &[("x-a", "one"), ("x-b", "two")]).await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let pointers: Vec<_> = body["problems"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|problem| problem["pointer"].as_str().unwrap())
+        .collect();
+    assert_eq!(pointers, ["$['x-a']", "$['x-b']"]);
+}
+
+#[tokio::test]
+async fn Validated_OtherRejection_IsReturnedAsIs() {
+    let (status, body) = get_response("/authenticated", &[("x-a", "one")]).await;
+
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert!(body.is_empty());
+}
@@ -0,0 +1,875 @@
+#![allow(missing_docs, non_snake_case)]
+
+use core::{
+    convert::Infallible,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use std::sync::Mutex;
+
+use base64ct::{Base64Url, Encoding};
+use http::Uri;
+use jiff::{SignedDuration, Timestamp};
+use openssl::{
+    ec::{EcGroup, EcKey},
+    hash::MessageDigest,
+    nid::Nid,
+    pkey::{PKey, Private},
+};
+use serde_json::json;
+use ts_api_helper::{
+    EncodeBase64,
+    webauthn::{
+        assertion_response::{AuthenticatorData, Flags},
+        challenge::{Challenge, GenerateChallengeError},
+        persisted_public_key::PersistedPublicKey,
+        postgres_verifier::PostgresVerifier,
+        public_key_credential::{
+            Algorithm, ClientDataType, DEFAULT_TIMEOUT, InvalidCredentialError,
+            PublicKeyCredential, Response,
+        },
+        public_key_credential_creation_options::{
+            PublicKeyCredentialCreationOptions, PublicKeyParameters,
+        },
+        public_key_credential_request_options::PublicKeyCredentialRequestOptions,
+        verification::{
+            CounterPolicy, VerificationResult, Verifier, constant_time_eq, is_registrable_suffix,
+        },
+    },
+};
+use ts_sql_helper_lib::SqlTimestamp;
+
+fn assertion_credential(id: &str, raw_id: &[u8]) -> PublicKeyCredential {
+    try_assertion_credential(id, raw_id, "https://example.com").unwrap()
+}
+
+fn try_assertion_credential(
+    id: &str,
+    raw_id: &[u8],
+    origin: &str,
+) -> serde_json::Result<PublicKeyCredential> {
+    let value = assertion_value(id, raw_id, origin);
+
+    serde_json::from_slice(&serde_json::to_vec(&value).unwrap())
+}
+
+fn assertion_value(id: &str, raw_id: &[u8], origin: &str) -> serde_json::Value {
+    let client_data = json!({
+        "challenge": b"challenge".encode_base64(),
+        "origin": origin,
+        "type": "webauthn.get",
+    });
+
+    let authenticator_data = [0u8; 37];
+
+    json!({
+        "authenticatorAttachment": null,
+        "id": id,
+        "rawId": raw_id.encode_base64(),
+        "response": {
+            "authenticatorData": authenticator_data.encode_base64(),
+            "clientDataJSON": serde_json::to_vec(&client_data).unwrap().encode_base64(),
+            "signature": b"signature".encode_base64(),
+            "userHandle": null,
+        },
+    })
+}
+
+#[test]
+fn AssertionResponse_AbsentUserHandle_IsNone() {
+    let raw_id = b"credential-id";
+    let mut value = assertion_value(&raw_id.encode_base64(), raw_id, "https://example.com");
+    value["response"]
+        .as_object_mut()
+        .unwrap()
+        .remove("userHandle");
+
+    let credential: PublicKeyCredential =
+        serde_json::from_slice(&serde_json::to_vec(&value).unwrap()).unwrap();
+
+    let Response::AssertionResponse(response) = credential.response else {
+        panic!("credential should be an assertion");
+    };
+    assert_eq!(response.user_handle, None);
+}
+
+#[test]
+fn Validate_MatchingIds_IsOk() {
+    let raw_id = b"credential-id";
+    let credential = assertion_credential(&raw_id.encode_base64(), raw_id);
+
+    assert!(credential.validate().is_ok());
+}
+
+#[test]
+fn Validate_MismatchedIds_IsIdMismatch() {
+    let credential = assertion_credential(&b"other-id".encode_base64(), b"credential-id");
+
+    assert!(matches!(
+        credential.validate(),
+        Err(InvalidCredentialError::IdMismatch { .. })
+    ));
+}
+
+#[test]
+fn ClientDataJson_RelativeOrigin_IsRejected() {
+    assert!(try_assertion_credential("id", b"id", "/relative").is_err());
+    assert!(try_assertion_credential("id", b"id", "not a url").is_err());
+}
+
+#[test]
+fn ClientDataType_TryFromStr_RoundTripsDisplay() {
+    for client_data_type in [ClientDataType::WebAuthNCreate, ClientDataType::WebAuthNGet] {
+        let parsed = ClientDataType::try_from(client_data_type.to_string().as_str()).unwrap();
+        assert_eq!(parsed, client_data_type);
+    }
+
+    assert!(ClientDataType::try_from("webauthn.other").is_err());
+}
+
+#[test]
+fn ChallengeIsForOrigin_ParsedOrigin_IgnoresHostCase() {
+    let challenge = challenge(SignedDuration::ZERO, SignedDuration::from_mins(5));
+
+    assert!(challenge.is_for_origin(&Uri::from_static("https://EXAMPLE.com")));
+    assert!(!challenge.is_for_origin(&Uri::from_static("https://example.com:8443")));
+    assert!(!challenge.is_for_origin(&Uri::from_static("http://example.com")));
+}
+
+#[test]
+fn ChallengeIsForOrigin_DefaultPort_IsSameAsNoPort() {
+    let challenge = challenge(SignedDuration::ZERO, SignedDuration::from_mins(5));
+    assert!(challenge.is_for_origin(&Uri::from_static("https://example.com:443")));
+    assert!(!challenge.is_for_origin(&Uri::from_static("https://example.com:80")));
+
+    let mut challenge = challenge;
+    challenge.origin = "https://Example.com:443".to_string();
+    assert!(challenge.is_for_origin(&Uri::from_static("https://example.com")));
+}
+
+#[test]
+fn ChallengeGenerate_Origin_IsNormalized() {
+    let challenge = Challenge::generate(
+        "HTTPS://Example.com:443".to_string(),
+        None,
+        SignedDuration::from_mins(5),
+    )
+    .unwrap();
+
+    assert_eq!(challenge.origin, "https://example.com");
+    assert!(challenge.is_for_origin(&Uri::from_static("https://example.com:443")));
+}
+
+fn challenge(issued: SignedDuration, expires: SignedDuration) -> Challenge {
+    let now = Timestamp::now();
+    Challenge {
+        challenge: b"challenge".to_vec(),
+        identity_id: None,
+        issued: SqlTimestamp(now + issued),
+        expires: SqlTimestamp(now + expires),
+        origin: "https://example.com".to_string(),
+    }
+}
+
+#[test]
+fn ChallengeIsValid_IssuedSlightlyInFuture_IsValid() {
+    let challenge = challenge(SignedDuration::from_secs(5), SignedDuration::from_mins(5));
+
+    assert!(challenge.is_valid());
+}
+
+#[test]
+fn ChallengeIsValid_IssuedBeyondLeeway_IsInvalid() {
+    let challenge = challenge(
+        Challenge::ISSUED_LEEWAY + SignedDuration::from_secs(5),
+        SignedDuration::from_mins(5),
+    );
+
+    assert!(!challenge.is_valid());
+}
+
+#[test]
+fn ChallengeIsValidAt_ExpiryBoundary_RespectsLeeway() {
+    let challenge = challenge(SignedDuration::from_mins(-5), SignedDuration::ZERO);
+    let expires = challenge.expires.0;
+    let leeway = SignedDuration::from_secs(30);
+
+    assert!(challenge.is_valid_at(
+        expires - SignedDuration::from_millis(1),
+        SignedDuration::ZERO
+    ));
+    assert!(!challenge.is_valid_at(expires, SignedDuration::ZERO));
+    assert!(!challenge.is_valid_at(
+        expires + SignedDuration::from_millis(1),
+        SignedDuration::ZERO
+    ));
+
+    assert!(challenge.is_valid_at(expires + SignedDuration::from_millis(1), leeway));
+    assert!(challenge.is_valid_at(expires + leeway - SignedDuration::from_millis(1), leeway));
+    assert!(!challenge.is_valid_at(expires + leeway, leeway));
+}
+
+#[test]
+fn ChallengeIsValid_Expired_IsInvalid() {
+    let challenge = challenge(SignedDuration::from_mins(-5), SignedDuration::from_secs(-1));
+
+    assert!(!challenge.is_valid());
+}
+
+#[test]
+fn CounterPolicy_Sequences_MatchPolicy() {
+    let sequences = [(0, 0), (5, 6), (5, 5), (5, 4)];
+    let cases = [
+        (CounterPolicy::Ignore, [true, true, true, true]),
+        (CounterPolicy::WarnOnRegression, [true, true, true, true]),
+        (CounterPolicy::RequireIncreasing, [true, true, false, false]),
+        (CounterPolicy::RequireNonZero, [false, true, false, false]),
+    ];
+
+    for (policy, expected) in cases {
+        for ((persisted, received), expected) in sequences.into_iter().zip(expected) {
+            assert_eq!(
+                policy.check(persisted, received),
+                expected,
+                "{policy:?} with [{persisted}, {received}]"
+            );
+        }
+    }
+}
+
+#[test]
+fn Flags_RepresentativeBytes_AccessorsMatchBits() {
+    // User present and verified.
+    let flags = Flags(0b0000_0101);
+    assert!(flags.user_present());
+    assert!(flags.user_verified());
+    assert!(!flags.backup_eligible());
+    assert!(!flags.backup_state());
+    assert!(!flags.attested_credential_data());
+    assert!(!flags.extension_data());
+
+    // Typical registration: present, verified, backup eligible and backed up, attested data.
+    let flags = Flags(0b0101_1101);
+    assert!(flags.user_present());
+    assert!(flags.user_verified());
+    assert!(flags.backup_eligible());
+    assert!(flags.backup_state());
+    assert!(flags.attested_credential_data());
+    assert!(!flags.extension_data());
+
+    let flags = Flags(0);
+    assert!(!flags.user_present());
+    assert!(!flags.extension_data());
+
+    let flags = Flags(u8::MAX);
+    assert!(flags.user_present());
+    assert!(flags.extension_data());
+}
+
+#[test]
+fn Flags_BitOperations_CombineFlags() {
+    let flags = Flags::USER_PRESENCE | Flags::USER_VERIFICATION;
+
+    assert!(flags.contains(Flags::USER_PRESENCE | Flags::USER_VERIFICATION));
+    assert!(!flags.contains(Flags::USER_PRESENCE | Flags::BACKUP_STATE));
+    assert_eq!(flags & Flags::USER_VERIFICATION, Flags::USER_VERIFICATION);
+    assert_eq!(flags & Flags::BACKUP_STATE, Flags(0));
+}
+
+#[tokio::test]
+async fn PostgresVerifier_Configuration_IsExposedThroughVerifier() {
+    let manager = bb8_postgres::PostgresConnectionManager::new_from_stringlike(
+        "postgres://localhost",
+        tokio_postgres::NoTls,
+    )
+    .unwrap();
+    let pool = bb8::Pool::builder().build_unchecked(manager);
+
+    let verifier = PostgresVerifier::new(pool, "example.com".to_string())
+        .with_challenges_table("auth.challenges".to_string())
+        .with_counter_policy(CounterPolicy::RequireIncreasing)
+        .with_challenge_expiry_leeway(SignedDuration::from_secs(30));
+
+    assert_eq!(verifier.relying_party_id(), "example.com");
+    assert_eq!(
+        verifier.challenge_expiry_leeway(),
+        SignedDuration::from_secs(30)
+    );
+    assert_eq!(verifier.counter_policy(), CounterPolicy::RequireIncreasing);
+}
+
+fn request_options(timeout: Option<u64>) -> PublicKeyCredentialRequestOptions {
+    let mut value = json!({ "challenge": "AAAA", "rpId": "example.com" });
+    if let Some(timeout) = timeout {
+        value["timeout"] = json!(timeout);
+    }
+
+    serde_json::from_str(&value.to_string()).unwrap()
+}
+
+#[test]
+fn RequestOptionsTimeout_Absent_IsDefault() {
+    let options = request_options(None);
+
+    assert_eq!(options.timeout, DEFAULT_TIMEOUT);
+    assert!(options.validate().is_ok());
+}
+
+#[test]
+fn RequestOptionsTimeout_ZeroOrTooLarge_IsProblem() {
+    for timeout in [0, 1_000, 3_600_000] {
+        let problem = request_options(Some(timeout)).validate().unwrap_err();
+        assert_eq!(problem.pointer, "$.timeout");
+    }
+
+    assert!(request_options(Some(60_000)).validate().is_ok());
+}
+
+#[test]
+fn CreationOptionsTimeout_AbsentOrZero_IsValidated() {
+    let value = json!({
+        "challenge": "AAAA",
+        "pubKeyCredParams": [{ "alg": -7, "type": "public-key" }],
+        "rp": { "id": "example.com", "name": "Example" },
+        "user": { "displayName": "User", "id": "AAAA", "name": "user" },
+    });
+    let options: PublicKeyCredentialCreationOptions =
+        serde_json::from_str(&value.to_string()).unwrap();
+    assert_eq!(options.timeout, DEFAULT_TIMEOUT);
+    assert!(options.validate().is_ok());
+
+    let mut value = value;
+    value["timeout"] = json!(0);
+    let options: PublicKeyCredentialCreationOptions =
+        serde_json::from_str(&value.to_string()).unwrap();
+    assert!(options.validate().is_err());
+}
+
+#[test]
+fn PublicKeyParametersRecommended_ExcludesDeprecatedAndNotRecommended() {
+    let recommended: Vec<i32> = PublicKeyParameters::recommended()
+        .iter()
+        .map(|parameters| parameters.algorithm.value())
+        .collect();
+
+    // ESP256, ESP384, ESP512, Ed25519, PS256, PS384, PS512.
+    assert_eq!(recommended, [-9, -51, -52, -19, -37, -38, -39]);
+    // ES256, ES384, ES512, EdDSA, RS256, RS384, RS512.
+    for deprecated in [-7, -35, -36, -8, -257, -258, -259] {
+        assert!(!recommended.contains(&deprecated));
+    }
+}
+
+#[test]
+fn ConstantTimeEq_MatchesPlainEquality() {
+    let cases: [(&[u8], &[u8]); 6] = [
+        (b"", b""),
+        (b"identity", b"identity"),
+        (b"identity", b"identitx"),
+        (b"identity", b"xdentity"),
+        (b"identity", b"identity-long"),
+        (b"", b"identity"),
+    ];
+
+    for (a, b) in cases {
+        assert_eq!(constant_time_eq(a, b), a == b);
+        assert_eq!(constant_time_eq(b, a), a == b);
+    }
+}
+
+#[test]
+fn PublicKeyCredential_CredPropsExtensionResult_IsParsed() {
+    let raw_id = b"credential-id";
+    let mut value = assertion_value(&raw_id.encode_base64(), raw_id, "https://example.com");
+    value["clientExtensionResults"] = json!({
+        "credProps": { "rk": true },
+        "unknownExtension": 1,
+    });
+
+    let credential: PublicKeyCredential = serde_json::from_str(&value.to_string()).unwrap();
+
+    let cred_props = credential.client_extension_results.cred_props.unwrap();
+    assert_eq!(cred_props.rk, Some(true));
+    assert_eq!(credential.client_extension_results.large_blob, None);
+
+    let credential = assertion_credential(&raw_id.encode_base64(), raw_id);
+    assert_eq!(credential.client_extension_results.cred_props, None);
+}
+
+#[test]
+fn CreationOptions_RealisticPayload_RoundTripsLosslessly() {
+    let value = json!({
+        "attestation": "direct",
+        "attestationFormats": ["packed", "tpm"],
+        "authenticatorSelection": {
+            "authenticatorAttachment": "cross-platform",
+            "requireResidentKey": true,
+            "residentKey": "required",
+            "userVerification": "preferred",
+        },
+        "challenge": b"challenge".encode_base64(),
+        "excludeCredentials": [
+            { "id": b"credential".encode_base64(), "transports": ["usb", "hybrid"], "type": "public-key" },
+            { "id": b"other".encode_base64(), "type": "public-key" },
+        ],
+        "extensions": { "credProps": true },
+        "pubKeyCredParams": [
+            { "alg": -8, "type": "public-key" },
+            { "alg": -7, "type": "public-key" },
+            { "alg": -257, "type": "public-key" },
+        ],
+        "rp": { "id": "example.com", "name": "Example" },
+        "timeout": 60_000,
+        "user": { "displayName": "User", "id": b"user".encode_base64(), "name": "user" },
+        "hints": ["security-key", "client-device"],
+    });
+
+    let options: PublicKeyCredentialCreationOptions =
+        serde_json::from_str(&value.to_string()).unwrap();
+    let serialized = serde_json::to_string(&options).unwrap();
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&serialized).unwrap(),
+        value
+    );
+
+    let options: PublicKeyCredentialCreationOptions = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(serde_json::to_string(&options).unwrap(), serialized);
+}
+
+#[test]
+fn RequestOptions_RealisticPayload_RoundTripsLosslessly() {
+    let value = json!({
+        "allowCredentials": [
+            { "id": b"credential".encode_base64(), "transports": ["internal"], "type": "public-key" },
+            { "id": b"other".encode_base64(), "type": "public-key" },
+        ],
+        "challenge": b"challenge".encode_base64(),
+        "extensions": {},
+        "hints": ["hybrid"],
+        "rpId": "example.com",
+        "timeout": 60_000,
+        "userVerification": "required",
+    });
+
+    let options: PublicKeyCredentialRequestOptions =
+        serde_json::from_str(&value.to_string()).unwrap();
+    let serialized = serde_json::to_string(&options).unwrap();
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&serialized).unwrap(),
+        value
+    );
+
+    let options: PublicKeyCredentialRequestOptions = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(serde_json::to_string(&options).unwrap(), serialized);
+}
+
+#[test]
+fn CreationOptions_NoChallenge_IsNone() {
+    let value = json!({
+        "pubKeyCredParams": [{ "alg": -7, "type": "public-key" }],
+        "rp": { "id": "example.com", "name": "Example" },
+        "user": { "displayName": "User", "id": "AAAA", "name": "user" },
+    });
+
+    let options: PublicKeyCredentialCreationOptions =
+        serde_json::from_str(&value.to_string()).unwrap();
+
+    assert!(options.challenge.is_none());
+}
+
+#[test]
+fn IsRegistrableSuffix_Suffixes_OnlyAllowsRegistrableDomains() {
+    assert!(is_registrable_suffix(
+        "login.example.com",
+        "login.example.com"
+    ));
+    assert!(is_registrable_suffix("login.example.com", "example.com"));
+    assert!(is_registrable_suffix(
+        "a.login.example.co.uk",
+        "example.co.uk"
+    ));
+    assert!(is_registrable_suffix("Login.Example.com", "example.COM"));
+
+    assert!(!is_registrable_suffix("login.example.com", "com"));
+    assert!(!is_registrable_suffix("login.example.co.uk", "co.uk"));
+    assert!(!is_registrable_suffix("login.example.com", "ample.com"));
+    assert!(!is_registrable_suffix("login.example.com", ".example.com"));
+    assert!(!is_registrable_suffix("example.com", "login.example.com"));
+    assert!(!is_registrable_suffix("login.example.com", "example.org"));
+}
+
+#[derive(Debug)]
+struct SuffixVerifier {
+    suffixes: Vec<String>,
+    checked_challenge: AtomicBool,
+}
+impl Verifier for SuffixVerifier {
+    type Error = Infallible;
+
+    async fn get_challenge(&self, _challenge: &[u8]) -> Result<Option<Challenge>, Self::Error> {
+        self.checked_challenge.store(true, Ordering::Relaxed);
+        Ok(None)
+    }
+
+    async fn get_public_key(
+        &self,
+        _raw_id: &[u8],
+    ) -> Result<Option<PersistedPublicKey>, Self::Error> {
+        Ok(None)
+    }
+
+    fn relying_party_id(&self) -> &str {
+        "login.example.com"
+    }
+
+    fn relying_party_id_suffixes(&self) -> &[String] {
+        &self.suffixes
+    }
+}
+
+/// Returns if an assertion scoped to `relying_party_id` passed the relying party ID check.
+async fn passes_relying_party_id_check(relying_party_id: &str, suffixes: &[&str]) -> bool {
+    let raw_id = b"credential-id";
+    let mut value = assertion_value(&raw_id.encode_base64(), raw_id, "https://login.example.com");
+    let mut authenticator_data = [0u8; 37];
+    authenticator_data[..32].copy_from_slice(&openssl::sha::sha256(relying_party_id.as_bytes()));
+    value["response"]["authenticatorData"] = json!(authenticator_data.encode_base64());
+    let credential: PublicKeyCredential =
+        serde_json::from_slice(&serde_json::to_vec(&value).unwrap()).unwrap();
+
+    let verifier = SuffixVerifier {
+        suffixes: suffixes.iter().map(ToString::to_string).collect(),
+        checked_challenge: AtomicBool::new(false),
+    };
+    let result = credential.verify(&verifier, None).await.unwrap();
+
+    assert!(matches!(result, VerificationResult::Invalid));
+    verifier.checked_challenge.load(Ordering::Relaxed)
+}
+
+#[tokio::test]
+async fn VerifyAssertion_RegistrableSuffix_IsAccepted() {
+    assert!(passes_relying_party_id_check("login.example.com", &[]).await);
+    assert!(passes_relying_party_id_check("example.com", &["example.com"]).await);
+}
+
+#[tokio::test]
+async fn VerifyAssertion_InvalidSuffix_IsRejected() {
+    assert!(!passes_relying_party_id_check("example.com", &[]).await);
+    assert!(!passes_relying_party_id_check("com", &["com"]).await);
+    assert!(!passes_relying_party_id_check("ample.com", &["ample.com"]).await);
+    assert!(!passes_relying_party_id_check("example.org", &["example.com"]).await);
+}
+
+#[test]
+fn ChallengeGenerate_TwoChallenges_AreRandomAndValid() {
+    let ttl = SignedDuration::from_mins(5);
+    let first = Challenge::generate("https://example.com".to_string(), None, ttl).unwrap();
+    let second = Challenge::generate("https://example.com".to_string(), None, ttl).unwrap();
+
+    assert_eq!(first.challenge.len(), Challenge::DEFAULT_LENGTH);
+    assert_ne!(first.challenge, second.challenge);
+    assert_eq!(first.issued.0.duration_until(first.expires.0), ttl);
+    assert!(first.is_valid());
+}
+
+#[test]
+fn ChallengeWithLength_BelowMinimum_IsTooShort() {
+    let ttl = SignedDuration::from_mins(5);
+    let challenge =
+        Challenge::with_length("https://example.com".to_string(), None, ttl, 64).unwrap();
+    assert_eq!(challenge.challenge.len(), 64);
+
+    assert!(matches!(
+        Challenge::with_length("https://example.com".to_string(), None, ttl, 15),
+        Err(GenerateChallengeError::TooShort { length: 15, .. })
+    ));
+}
+
+/// Authenticator data from a registration with a P-256 credential, as produced by a security key.
+fn registration_authenticator_data(flags: u8, extensions: &[u8]) -> Vec<u8> {
+    let mut data = openssl::sha::sha256(b"example.com").to_vec();
+    data.push(flags);
+    data.extend(0u32.to_be_bytes());
+    // AAGUID.
+    data.extend([
+        0xcb, 0x69, 0x48, 0x1e, 0x8f, 0xf7, 0x40, 0x39, 0x93, 0xec, 0x0a, 0x27, 0x29, 0xa1, 0x54,
+        0xa8,
+    ]);
+    // Credential ID.
+    data.extend(16u16.to_be_bytes());
+    data.extend([0x11; 16]);
+    // COSE_Key: {1: 2, 3: -7, -1: 1, -2: x, -3: y}.
+    data.extend([0xa5, 0x01, 0x02, 0x03, 0x26, 0x20, 0x01, 0x21, 0x58, 0x20]);
+    data.extend([0x22; 32]);
+    data.extend([0x22, 0x58, 0x20]);
+    data.extend([0x33; 32]);
+    data.extend(extensions);
+    data
+}
+
+fn parse_authenticator_data(bytes: &[u8]) -> serde_json::Result<AuthenticatorData> {
+    serde_json::from_str(&format!("\"{}\"", bytes.encode_base64()))
+}
+
+#[test]
+fn AuthenticatorData_AttestedCredentialData_IsParsed() {
+    let bytes = registration_authenticator_data(0x45, &[]);
+
+    let data = parse_authenticator_data(&bytes).unwrap();
+
+    let attested = data.attested_credential_data.unwrap();
+    assert_eq!(attested.aaguid[..2], [0xcb, 0x69]);
+    assert_eq!(attested.credential_id, [0x11; 16]);
+    assert_eq!(attested.credential_public_key.len(), 77);
+    assert_eq!(attested.credential_public_key[..3], [0xa5, 0x01, 0x02]);
+    assert_eq!(data.extensions, None);
+    assert_eq!(data.raw, bytes);
+}
+
+#[test]
+fn AuthenticatorData_ExtensionData_IsParsedAfterCredential() {
+    // {"credProtect": 2}
+    let mut extensions = vec![0xa1, 0x6b];
+    extensions.extend(b"credProtect");
+    extensions.push(0x02);
+    let bytes = registration_authenticator_data(0xc5, &extensions);
+
+    let data = parse_authenticator_data(&bytes).unwrap();
+
+    assert!(data.attested_credential_data.is_some());
+    assert_eq!(data.extensions, Some(extensions));
+}
+
+#[test]
+fn AuthenticatorData_TruncatedOrTrailing_IsRejected() {
+    let bytes = registration_authenticator_data(0x45, &[]);
+    assert!(parse_authenticator_data(&bytes[..bytes.len() - 1]).is_err());
+
+    let mut trailing = bytes.clone();
+    trailing.push(0x00);
+    assert!(parse_authenticator_data(&trailing).is_err());
+
+    // The attested credential data flag is not set, so the credential is trailing bytes.
+    assert!(parse_authenticator_data(&registration_authenticator_data(0x05, &[])).is_err());
+}
+
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+struct Base64Fields {
+    #[serde(with = "ts_api_helper::serde_base64")]
+    bytes: Vec<u8>,
+    #[serde(with = "ts_api_helper::maybe_serde_base64")]
+    maybe_bytes: Option<Vec<u8>>,
+}
+
+#[test]
+fn SerdeBase64_PaddedOrUnpadded_DecodesSameBytes() {
+    let expected = Base64Fields {
+        bytes: vec![0x01, 0x02, 0x03, 0x04],
+        maybe_bytes: Some(vec![0xfb, 0xff]),
+    };
+
+    let unpadded: Base64Fields =
+        serde_json::from_str(r#"{"bytes": "AQIDBA", "maybe_bytes": "-_8"}"#).unwrap();
+    let padded: Base64Fields =
+        serde_json::from_str(r#"{"bytes": "AQIDBA==", "maybe_bytes": "-_8="}"#).unwrap();
+
+    assert_eq!(unpadded, expected);
+    assert_eq!(padded, expected);
+    assert_eq!(
+        serde_json::to_value(&padded).unwrap(),
+        json!({ "bytes": "AQIDBA", "maybe_bytes": "-_8" })
+    );
+}
+
+#[test]
+fn SerdeBase64_InvalidPadding_IsRejected() {
+    let result: serde_json::Result<Base64Fields> =
+        serde_json::from_str(r#"{"bytes": "AQIDBA=", "maybe_bytes": null}"#);
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn AuthenticatorData_PaddedBase64_IsParsed() {
+    // 37 bytes, so the padded encoding ends in `==`.
+    let bytes = registration_authenticator_data(0x05, &[])[..37].to_vec();
+    let padded = Base64Url::encode_string(&bytes);
+    assert!(padded.ends_with("=="));
+
+    let data: AuthenticatorData = serde_json::from_str(&format!("\"{padded}\"")).unwrap();
+
+    assert_eq!(data.raw, bytes);
+}
+
+/// A verifier for signed assertions, with a challenge bound to `challenge_identity` and a
+/// credential of `key_identity`.
+#[derive(Debug)]
+struct AssertionVerifier {
+    challenge_identity: Option<Vec<u8>>,
+    key_identity: Vec<u8>,
+    public_key: Vec<u8>,
+    signature_counter: Mutex<i64>,
+}
+impl AssertionVerifier {
+    fn new(challenge_identity: Option<&[u8]>, key_identity: &[u8], key: &PKey<Private>) -> Self {
+        Self {
+            challenge_identity: challenge_identity.map(<[u8]>::to_vec),
+            key_identity: key_identity.to_vec(),
+            public_key: key.public_key_to_der().unwrap(),
+            signature_counter: Mutex::new(0),
+        }
+    }
+}
+impl Verifier for AssertionVerifier {
+    type Error = Infallible;
+
+    async fn get_challenge(&self, _challenge: &[u8]) -> Result<Option<Challenge>, Self::Error> {
+        let mut challenge = challenge(SignedDuration::ZERO, SignedDuration::from_mins(5));
+        challenge.identity_id = self.challenge_identity.clone();
+        Ok(Some(challenge))
+    }
+
+    async fn get_public_key(
+        &self,
+        raw_id: &[u8],
+    ) -> Result<Option<PersistedPublicKey>, Self::Error> {
+        Ok(Some(PersistedPublicKey {
+            raw_id: raw_id.to_vec(),
+            identity_id: self.key_identity.clone(),
+            display_name: "key".to_string(),
+            public_key: self.public_key.clone(),
+            public_key_algorithm: Algorithm::ES256,
+            transports: vec![],
+            signature_counter: *self.signature_counter.lock().unwrap(),
+            created: SqlTimestamp(Timestamp::now()),
+            last_used: None,
+        }))
+    }
+
+    async fn update_signature_counter(
+        &self,
+        _raw_id: &[u8],
+        signature_counter: u32,
+    ) -> Result<(), Self::Error> {
+        *self.signature_counter.lock().unwrap() = i64::from(signature_counter);
+        Ok(())
+    }
+
+    fn relying_party_id(&self) -> &str {
+        "example.com"
+    }
+
+    fn counter_policy(&self) -> CounterPolicy {
+        CounterPolicy::RequireIncreasing
+    }
+}
+
+/// Sign an assertion for `example.com` with `key`.
+fn signed_assertion(
+    key: &PKey<Private>,
+    user_handle: Option<&[u8]>,
+    flags: Flags,
+    signature_counter: u32,
+) -> PublicKeyCredential {
+    let value = signed_assertion_value(key, user_handle, flags, signature_counter);
+    serde_json::from_slice(&serde_json::to_vec(&value).unwrap()).unwrap()
+}
+
+/// The JSON of an assertion for `example.com` signed with `key`.
+fn signed_assertion_value(
+    key: &PKey<Private>,
+    user_handle: Option<&[u8]>,
+    flags: Flags,
+    signature_counter: u32,
+) -> serde_json::Value {
+    let raw_id = b"credential-id";
+    let mut value = assertion_value(&raw_id.encode_base64(), raw_id, "https://example.com");
+
+    let mut authenticator_data = openssl::sha::sha256(b"example.com").to_vec();
+    authenticator_data.push(flags.0);
+    authenticator_data.extend_from_slice(&signature_counter.to_be_bytes());
+
+    let client_data_json = serde_json::to_vec(&json!({
+        "challenge": b"challenge".encode_base64(),
+        "origin": "https://example.com",
+        "type": "webauthn.get",
+    }))
+    .unwrap();
+    let mut signed = authenticator_data.clone();
+    signed.extend_from_slice(&openssl::sha::sha256(&client_data_json));
+    let mut signer = openssl::sign::Signer::new(MessageDigest::sha256(), key).unwrap();
+    let signature = signer.sign_oneshot_to_vec(&signed).unwrap();
+
+    value["response"]["authenticatorData"] = json!(authenticator_data.encode_base64());
+    value["response"]["clientDataJSON"] = json!(client_data_json.encode_base64());
+    value["response"]["signature"] = json!(signature.encode_base64());
+    value["response"]["userHandle"] = json!(user_handle.map(|handle| handle.encode_base64()));
+
+    value
+}
+
+fn assertion_key() -> PKey<Private> {
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+    PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap()
+}
+
+#[tokio::test]
+async fn VerifyAssertion_SignedForChallengeIdentity_IsValid() {
+    let key = assertion_key();
+    let verifier = AssertionVerifier::new(Some(b"identity-x"), b"identity-x", &key);
+    let credential = signed_assertion(&key, None, Flags::USER_PRESENCE, 1);
+
+    let result = credential
+        .verify(&verifier, Some(b"identity-x"))
+        .await
+        .unwrap();
+
+    assert!(
+        matches!(result, VerificationResult::Valid { identity_id, .. } if identity_id == b"identity-x")
+    );
+}
+
+#[tokio::test]
+async fn VerifyAssertion_PaddedId_IsValid() {
+    let key = assertion_key();
+    let verifier = AssertionVerifier::new(None, b"identity-x", &key);
+    let mut value = signed_assertion_value(&key, Some(b"identity-x"), Flags::USER_PRESENCE, 1);
+    let padded_id = Base64Url::encode_string(b"credential-id");
+    assert!(padded_id.ends_with('='));
+    value["id"] = json!(padded_id);
+    value["rawId"] = json!(padded_id);
+    let credential: PublicKeyCredential =
+        serde_json::from_slice(&serde_json::to_vec(&value).unwrap()).unwrap();
+
+    let result = credential.verify(&verifier, None).await.unwrap();
+
+    assert!(matches!(result, VerificationResult::Valid { .. }));
+}
+
+#[tokio::test]
+async fn VerifyAssertion_NoUserHandleAndOtherIdentitysKey_IsInvalid() {
+    let key = assertion_key();
+    let verifier = AssertionVerifier::new(Some(b"identity-x"), b"identity-y", &key);
+    let credential = signed_assertion(&key, None, Flags::USER_PRESENCE, 1);
+
+    let result = credential
+        .verify(&verifier, Some(b"identity-x"))
+        .await
+        .unwrap();
+
+    assert!(matches!(result, VerificationResult::Invalid));
+}
+
+#[tokio::test]
+async fn VerifyAssertion_ReplayedCounter_IsInvalidAfterCounterIsPersisted() {
+    let key = assertion_key();
+    let verifier = AssertionVerifier::new(None, b"identity-x", &key);
+
+    let credential = signed_assertion(&key, Some(b"identity-x"), Flags::USER_PRESENCE, 5);
+    let result = credential.verify(&verifier, None).await.unwrap();
+    assert!(matches!(result, VerificationResult::Valid { .. }));
+    assert_eq!(*verifier.signature_counter.lock().unwrap(), 5);
+
+    let result = credential.verify(&verifier, None).await.unwrap();
+    assert!(matches!(result, VerificationResult::Invalid));
+}